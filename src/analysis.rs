@@ -0,0 +1,128 @@
+use crate::diagnostic::Diagnostic;
+use crate::diagnostic::ToDiagnostic;
+use crate::parse::parse_expr_recovering;
+use crate::resolve::resolve;
+use crate::resolve::Resolution;
+use crate::span::Span;
+use crate::ttype::Type;
+use crate::typecheck::TypeChecker;
+
+/// What an editor shows for a source file: its diagnostics, and the type
+/// and definition of what is at each position.
+///
+/// Source with parse errors is still resolved and type checked as far as
+/// possible, so positions outside the errors have types and definitions.
+pub struct Analysis {
+    /// As from diagnostic::check.
+    pub diagnostics: Vec<Diagnostic>,
+    types: Vec<(Span, Type)>,
+    resolution: Resolution,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let (expr, errors) = parse_expr_recovering(source);
+        let resolution = resolve(&expr);
+        let mut checker = TypeChecker::new();
+        let checked = checker.check(&expr);
+
+        let mut diagnostics = diagnose(&errors, source);
+        if errors.is_empty() {
+            diagnostics = diagnose(&resolution.errors, source);
+            if !diagnostics.iter().any(Diagnostic::is_error) {
+                if let Err(error) = checked {
+                    diagnostics.push(error.to_diagnostic(source));
+                }
+            }
+        }
+        Self {
+            diagnostics,
+            types: checker.types().to_vec(),
+            resolution,
+        }
+    }
+
+    /// Type of the innermost expression or binding name at the byte offset,
+    /// with its span.  A span ending at offset counts, so that the type of
+    /// a name is found from just after it.
+    pub fn type_at(&self, offset: usize) -> Option<(Span, &Type)> {
+        self.types
+            .iter()
+            .filter(|(span, _)| contains(*span, offset))
+            .min_by_key(|(span, _)| span.len())
+            .map(|(span, ttype)| (*span, ttype))
+    }
+
+    /// Span of the variable reference at the byte offset, with the span of
+    /// the name it refers to.
+    pub fn definition_at(&self, offset: usize) -> Option<(Span, Span)> {
+        self.resolution
+            .references
+            .iter()
+            .find(|(reference, _)| contains(*reference, offset))
+            .copied()
+    }
+}
+
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+fn diagnose(errors: &[impl ToDiagnostic], source: &str) -> Vec<Diagnostic> {
+    errors
+        .iter()
+        .map(|error| error.to_diagnostic(source))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_at() {
+        let source = "(let add = fn (a: int, b: int) -> int (a + b); add(1, 2) < 4)";
+        let analysis = Analysis::new(source);
+        assert!(analysis.diagnostics.is_empty());
+        let type_at = |offset| {
+            analysis
+                .type_at(offset)
+                .map(|(span, ttype)| (span.range(), ttype.to_string()))
+        };
+        assert_eq!(type_at(6), Some((5..8, "fn(int, int) -> int".to_owned())));
+        assert_eq!(type_at(16), Some((15..16, "int".to_owned())));
+        assert_eq!(type_at(42), Some((38..45, "int".to_owned())));
+        assert_eq!(type_at(51), Some((51..52, "int".to_owned())));
+        assert_eq!(type_at(56), Some((47..56, "int".to_owned())));
+        assert_eq!(type_at(57), Some((47..60, "bool".to_owned())));
+        assert_eq!(type_at(62), None);
+    }
+
+    #[test]
+    fn test_definition_at() {
+        let source = "(let x = 1; let f = fn (y: int) -> int (x + y); f(x))";
+        let analysis = Analysis::new(source);
+        let definition_at = |offset| {
+            analysis
+                .definition_at(offset)
+                .map(|(reference, definition)| (reference.range(), definition.range()))
+        };
+        assert_eq!(definition_at(40), Some((40..41, 5..6)));
+        assert_eq!(definition_at(45), Some((44..45, 24..25)));
+        assert_eq!(definition_at(48), Some((48..49, 16..17)));
+        assert_eq!(definition_at(50), Some((50..51, 5..6)));
+        assert_eq!(definition_at(5), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let source = "(let x = true; x +)";
+        let analysis = Analysis::new(source);
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(
+            analysis.type_at(15).map(|(_, ttype)| ttype.clone()),
+            Some(Type::Bool)
+        );
+        assert!(analysis.definition_at(15).is_some());
+    }
+}
@@ -1 +1,7 @@
+pub(crate) mod display;
 pub mod expr;
+pub mod fold;
+pub mod visit;
+
+#[cfg(test)]
+mod tests;
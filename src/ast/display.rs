@@ -0,0 +1,225 @@
+use std::fmt;
+
+use super::expr::BinaryOp;
+use super::expr::Expr;
+use super::expr::ExprKind;
+use super::expr::Function;
+use super::expr::Literal;
+use super::expr::StrPart;
+use super::expr::UnaryOp;
+use crate::parse::operator::Assoc;
+use crate::parse::operator::BinaryOperator;
+use crate::parse::operator::Precedence;
+use crate::parse::operator::UnaryOperator;
+use crate::parse::string::escape_text;
+
+/// How tightly an expression holds together, loosest first.
+/// An expression needs parentheses where the parser expects a tighter one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Binding {
+    /// Only valid as an item of a block.
+    Let,
+    /// An if extends as far right as it can, so is never an operand.
+    If,
+    Binary(Precedence),
+    Unary,
+    Primary,
+}
+
+pub(crate) fn binding(expr: &Expr) -> Binding {
+    match &expr.kind {
+        ExprKind::Let { .. } => Binding::Let,
+        ExprKind::If { .. } => Binding::If,
+        ExprKind::Binary { op, .. } => Binding::Binary(BinaryOperator::from_op(*op).precedence),
+        ExprKind::Unary { .. } | ExprKind::Literal(Literal::Int(i64::MIN..=-1)) => Binding::Unary,
+        ExprKind::Literal(Literal::Float(x)) if x.is_sign_negative() => Binding::Unary,
+        ExprKind::Literal(_)
+        | ExprKind::Var(_)
+        | ExprKind::Block { .. }
+        | ExprKind::Fn(_)
+        | ExprKind::Call { .. }
+        | ExprKind::Interpolate(_)
+        | ExprKind::Error => Binding::Primary,
+    }
+}
+
+/// Loosest bindings the left and right operands of op can have without parentheses.
+pub(crate) fn operand_bindings(op: BinaryOp) -> (Binding, Binding) {
+    let operator = BinaryOperator::from_op(op);
+    let (left, right) = match operator.assoc {
+        Assoc::Left => (operator.precedence, operator.precedence + 1),
+        Assoc::Right => (operator.precedence + 1, operator.precedence),
+        Assoc::None => (operator.precedence + 1, operator.precedence + 1),
+    };
+    (Binding::Binary(left), Binding::Binary(right))
+}
+
+/// Loosest binding the operand of op can have without parentheses.
+pub(crate) fn unary_operand_binding(op: UnaryOp) -> Binding {
+    Binding::Binary(UnaryOperator::from_op(op).precedence)
+}
+
+/// Text between a prefix operator and its operand: a space keeps words
+/// apart, and `- -x` from running together.
+pub(crate) fn unary_separator(op: UnaryOp, right: &Expr) -> &'static str {
+    let word = UnaryOperator::from_op(op)
+        .symbol
+        .starts_with(char::is_alphabetic);
+    if word || binding(right) == Binding::Unary {
+        " "
+    } else {
+        ""
+    }
+}
+
+/// Print as Carbide source, with only the parentheses the parser needs.
+/// Parsing the result gives back the same Expr, apart from spans, except for:
+/// - a Block of one expression, which is read back as just the expression
+/// - a negative Int or Float literal, which is read back as a negation
+/// - a Let outside of a Block, which does not parse on its own, and as an
+///   operand is read back in a Block of its own
+/// - an empty Block or ExprKind::Error, which print as unparseable text
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_expr(f, self)
+    }
+}
+
+/// Write expr, in parentheses if it binds looser than min.
+fn write_operand(f: &mut fmt::Formatter<'_>, expr: &Expr, min: Binding) -> fmt::Result {
+    if binding(expr) < min {
+        write!(f, "(")?;
+        write_expr(f, expr)?;
+        write!(f, ")")
+    } else {
+        write_expr(f, expr)
+    }
+}
+
+/// Write expr where the parser expects a parenthesized block, as for the
+/// then branch of an if or the body of a function.
+fn write_block(f: &mut fmt::Formatter<'_>, expr: &Expr) -> fmt::Result {
+    match expr.kind {
+        ExprKind::Block { .. } => write_expr(f, expr),
+        _ => {
+            write!(f, "(")?;
+            write_expr(f, expr)?;
+            write!(f, ")")
+        }
+    }
+}
+
+fn write_expr(f: &mut fmt::Formatter<'_>, expr: &Expr) -> fmt::Result {
+    match &expr.kind {
+        ExprKind::Literal(literal) => write!(f, "{literal}"),
+        ExprKind::Unary { op, right } => {
+            write!(f, "{op}{}", unary_separator(*op, right))?;
+            write_operand(f, right, unary_operand_binding(*op))
+        }
+        ExprKind::Binary { left, op, right } => {
+            let (left_min, right_min) = operand_bindings(*op);
+            write_operand(f, left, left_min)?;
+            write!(f, " {op} ")?;
+            write_operand(f, right, right_min)
+        }
+        ExprKind::Block {
+            contents,
+            trailing_semicolon,
+        } => {
+            write!(f, "(")?;
+            for (i, item) in contents.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "; ")?;
+                }
+                write_expr(f, item)?;
+            }
+            if *trailing_semicolon {
+                write!(f, ";")?;
+            }
+            write!(f, ")")
+        }
+        ExprKind::Var(name) => write!(f, "{name}"),
+        ExprKind::Let { name, value } => {
+            write!(f, "let {} = ", name.name)?;
+            write_operand(f, value, Binding::If)
+        }
+        ExprKind::If {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            write!(f, "if ")?;
+            write_operand(f, condition, Binding::If)?;
+            write!(f, " ")?;
+            write_block(f, then_expr)?;
+            if let Some(else_expr) = else_expr {
+                write!(f, " else ")?;
+                write_operand(f, else_expr, Binding::If)?;
+            }
+            Ok(())
+        }
+        ExprKind::Fn(function) => write_function(f, function),
+        ExprKind::Call { callee, args } => {
+            write_operand(f, callee, Binding::Primary)?;
+            write!(f, "(")?;
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_operand(f, arg, Binding::If)?;
+            }
+            write!(f, ")")
+        }
+        ExprKind::Interpolate(parts) => {
+            write!(f, "\"")?;
+            for part in parts {
+                match part {
+                    StrPart::Text(text) => write!(f, "{}", escape_text(text))?,
+                    StrPart::Expr(expr) => write!(f, "{{{expr}}}")?,
+                }
+            }
+            write!(f, "\"")
+        }
+        ExprKind::Error => write!(f, "<error>"),
+    }
+}
+
+fn write_function(f: &mut fmt::Formatter<'_>, function: &Function) -> fmt::Result {
+    match &function.name {
+        Some(name) => write!(f, "fn {}(", name.name)?,
+        None => write!(f, "fn (")?,
+    }
+    for (i, param) in function.params.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: {}", param.name.name, param.ttype)?;
+    }
+    write!(f, ") -> {} ", function.ret)?;
+    write_block(f, &function.body)
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Bool(b) => write!(f, "{b}"),
+            Literal::Int(i) => write!(f, "{i}"),
+            // Debug keeps the `.0` of whole floats, and uses an exponent for
+            // very large or small ones.
+            Literal::Float(x) => write!(f, "{x:?}"),
+            Literal::Str(s) => write!(f, "\"{}\"", escape_text(s)),
+        }
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(UnaryOperator::from_op(*self).symbol)
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(BinaryOperator::from_op(*self).symbol)
+    }
+}
@@ -1,5 +1,43 @@
+use super::fold::Folder;
+use crate::span::Span;
+use crate::ttype::Type;
+
+/// Expression of the syntax tree.
+///
+/// With the `serde` feature, expressions serialize as objects with `kind`
+/// and `span` members.  Enum variants are named in snake case, and
+/// externally tagged: unit variants are strings, and other variants are
+/// objects with the variant name as their only key.  For example, `x + 1`
+/// at the start of the source is
+///
+/// ```json
+/// {"kind": {"binary": {
+///     "left": {"kind": {"var": "x"}, "span": {"start": 0, "end": 1, "line": 0, "col": 0}},
+///     "op": "add",
+///     "right": {"kind": {"literal": {"int": 1}}, "span": {"start": 4, "end": 5, "line": 0, "col": 4}}}},
+///  "span": {"start": 0, "end": 5, "line": 0, "col": 0}}
+/// ```
+///
+/// A missing `span` deserializes as the default span.  Types, as in
+/// function parameters, follow the same rules, as in `{"fn": {"params":
+/// ["int"], "ret": "bool"}}`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expr {
+    pub kind: ExprKind,
+    /// Source span covering all of the expression's tokens.
+    /// Expressions built by hand have the default (empty) span.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
 #[derive(Clone, Debug, PartialEq)]
-pub enum Expr {
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ExprKind {
     Literal(Literal),
     Unary {
         op: UnaryOp,
@@ -10,28 +48,142 @@ pub enum Expr {
         op: BinaryOp,
         right: Box<Expr>,
     },
-    Block(Vec<Expr>),
+    /// Expressions separated by `;`, in parentheses.  The block's value is
+    /// that of the last expression, or unit if the last is followed by a
+    /// trailing `;`.  A single expression in parentheses is just grouping,
+    /// unless it is a let.
+    Block {
+        contents: Vec<Expr>,
+        trailing_semicolon: bool,
+    },
+    /// Variable reference
+    Var(String),
+    /// Binding for the rest of the enclosing block.  This evaluates to unit.
+    Let {
+        name: Ident,
+        value: Box<Expr>,
+    },
     If {
         condition: Box<Expr>,
         then_expr: Box<Expr>,
         else_expr: Option<Box<Expr>>,
     },
+    /// Function literal, which evaluates to a closure over the bindings in scope.
+    Fn(Function),
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    /// String with the values of expressions in it, as in `"x = {x}"`.
+    Interpolate(Vec<StrPart>),
+    /// Placeholder for an expression that could not be parsed.
+    Error,
+}
+
+/// Parameters, return type and body of a function literal.
+/// A named function is bound to its name within its own body, so it can recurse.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Function {
+    pub name: Option<Ident>,
+    pub params: Vec<Param>,
+    pub ret: Type,
+    pub body: Box<Expr>,
+}
+
+impl Function {
+    /// Type of the closures this function literal evaluates to.
+    pub fn ttype(&self) -> Type {
+        let params = self
+            .params
+            .iter()
+            .map(|param| param.ttype.clone())
+            .collect();
+        Type::function(params, self.ret.clone())
+    }
+}
+
+/// Function parameter, with its declared type.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Param {
+    pub name: Ident,
+    pub ttype: Type,
+}
+
+impl Param {
+    pub fn new(name: impl Into<String>, ttype: Type) -> Self {
+        Self {
+            name: Ident::new(name, Span::default()),
+            ttype,
+        }
+    }
+}
+
+/// Name introduced by a binding, with the span of the name.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ident {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+impl Ident {
+    pub fn new(name: impl Into<String>, span: Span) -> Self {
+        Self {
+            name: name.into(),
+            span,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Literal {
     Bool(bool),
     Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+/// Piece of an interpolated string.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum StrPart {
+    Text(String),
+    /// Expression whose value is displayed in the string.
+    Expr(Expr),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum UnaryOp {
     Not,
     Neg,
     Pos,
+    /// Number of characters in a string.
+    Len,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum BinaryOp {
     And,
     Or,
@@ -48,34 +200,134 @@ pub enum BinaryOp {
 }
 
 impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Self { span, ..self }
+    }
+
     pub fn bool(b: bool) -> Self {
-        Expr::Literal(Literal::Bool(b))
+        ExprKind::Literal(Literal::Bool(b)).into()
     }
 
     pub fn int(i: i64) -> Self {
-        Expr::Literal(Literal::Int(i))
+        ExprKind::Literal(Literal::Int(i)).into()
+    }
+
+    pub fn float(x: f64) -> Self {
+        ExprKind::Literal(Literal::Float(x)).into()
+    }
+
+    pub fn str(s: impl Into<String>) -> Self {
+        ExprKind::Literal(Literal::Str(s.into())).into()
+    }
+
+    pub fn interpolate(parts: Vec<StrPart>) -> Self {
+        ExprKind::Interpolate(parts).into()
     }
 
     pub fn unary(op: UnaryOp, right: Expr) -> Self {
-        Expr::Unary {
+        ExprKind::Unary {
             op,
             right: Box::new(right),
         }
+        .into()
     }
 
     pub fn binary(left: Expr, op: BinaryOp, right: Expr) -> Self {
-        Expr::Binary {
+        ExprKind::Binary {
             left: Box::new(left),
             op,
             right: Box::new(right),
         }
+        .into()
+    }
+
+    pub fn block(contents: Vec<Expr>) -> Self {
+        ExprKind::Block {
+            contents,
+            trailing_semicolon: false,
+        }
+        .into()
+    }
+
+    /// Block with a trailing `;`, which has value unit.
+    pub fn unit_block(contents: Vec<Expr>) -> Self {
+        ExprKind::Block {
+            contents,
+            trailing_semicolon: true,
+        }
+        .into()
+    }
+
+    pub fn var(name: impl Into<String>) -> Self {
+        ExprKind::Var(name.into()).into()
+    }
+
+    pub fn let_(name: impl Into<String>, value: Expr) -> Self {
+        ExprKind::Let {
+            name: Ident::new(name, Span::default()),
+            value: Box::new(value),
+        }
+        .into()
     }
 
     pub fn ifthen(cond: Expr, then_: Expr, else_: Option<Expr>) -> Self {
-        Expr::If {
+        ExprKind::If {
             condition: Box::new(cond),
             then_expr: Box::new(then_),
             else_expr: else_.map(Box::new),
         }
+        .into()
+    }
+
+    /// Anonymous function literal.
+    pub fn lambda(params: Vec<Param>, ret: Type, body: Expr) -> Self {
+        ExprKind::Fn(Function {
+            name: None,
+            params,
+            ret,
+            body: Box::new(body),
+        })
+        .into()
+    }
+
+    /// Function literal whose name is bound in its body.
+    pub fn named_fn(name: impl Into<String>, params: Vec<Param>, ret: Type, body: Expr) -> Self {
+        ExprKind::Fn(Function {
+            name: Some(Ident::new(name, Span::default())),
+            params,
+            ret,
+            body: Box::new(body),
+        })
+        .into()
+    }
+
+    pub fn call(callee: Expr, args: Vec<Expr>) -> Self {
+        ExprKind::Call {
+            callee: Box::new(callee),
+            args,
+        }
+        .into()
+    }
+
+    /// Reset the span of this expression and all subexpressions to the default,
+    /// so that parsed expressions can be compared with hand-built ones.
+    pub fn without_spans(self) -> Self {
+        struct SpanEraser;
+        impl Folder for SpanEraser {
+            fn fold_span(&mut self, _span: Span) -> Span {
+                Span::default()
+            }
+        }
+        SpanEraser.fold_expr(self)
+    }
+}
+
+impl From<ExprKind> for Expr {
+    fn from(kind: ExprKind) -> Self {
+        Expr::new(kind, Span::default())
     }
 }
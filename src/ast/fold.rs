@@ -0,0 +1,213 @@
+use super::expr::BinaryOp;
+use super::expr::Expr;
+use super::expr::ExprKind;
+use super::expr::Function;
+use super::expr::Ident;
+use super::expr::Literal;
+use super::expr::Param;
+use super::expr::StrPart;
+use super::expr::UnaryOp;
+use crate::span::Span;
+
+/// Rewriting traversal of an Expr, which consumes the expression and
+/// returns its replacement.
+///
+/// fold_expr calls the method for the expression's variant, whose default
+/// folds its subexpressions in source order and rebuilds it with the
+/// Expr constructors.  Override the methods for the nodes to rewrite, and
+/// call the matching walk function from an override to rebuild the node
+/// with its subexpressions folded.
+pub trait Folder {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        walk_expr(self, expr)
+    }
+
+    /// Map the span of each expression and name.
+    fn fold_span(&mut self, span: Span) -> Span {
+        span
+    }
+
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        let span = self.fold_span(ident.span);
+        Ident::new(ident.name, span)
+    }
+
+    fn fold_literal(&mut self, span: Span, literal: Literal) -> Expr {
+        Expr::new(ExprKind::Literal(literal), self.fold_span(span))
+    }
+
+    fn fold_unary(&mut self, span: Span, op: UnaryOp, right: Expr) -> Expr {
+        walk_unary(self, span, op, right)
+    }
+
+    fn fold_binary(&mut self, span: Span, left: Expr, op: BinaryOp, right: Expr) -> Expr {
+        walk_binary(self, span, left, op, right)
+    }
+
+    fn fold_block(&mut self, span: Span, contents: Vec<Expr>, trailing_semicolon: bool) -> Expr {
+        walk_block(self, span, contents, trailing_semicolon)
+    }
+
+    fn fold_var(&mut self, span: Span, name: String) -> Expr {
+        Expr::var(name).with_span(self.fold_span(span))
+    }
+
+    fn fold_let(&mut self, span: Span, name: Ident, value: Expr) -> Expr {
+        walk_let(self, span, name, value)
+    }
+
+    fn fold_if(
+        &mut self,
+        span: Span,
+        condition: Expr,
+        then_expr: Expr,
+        else_expr: Option<Expr>,
+    ) -> Expr {
+        walk_if(self, span, condition, then_expr, else_expr)
+    }
+
+    fn fold_fn(&mut self, span: Span, function: Function) -> Expr {
+        walk_fn(self, span, function)
+    }
+
+    fn fold_call(&mut self, span: Span, callee: Expr, args: Vec<Expr>) -> Expr {
+        walk_call(self, span, callee, args)
+    }
+
+    fn fold_interpolate(&mut self, span: Span, parts: Vec<StrPart>) -> Expr {
+        walk_interpolate(self, span, parts)
+    }
+
+    fn fold_error(&mut self, span: Span) -> Expr {
+        Expr::new(ExprKind::Error, self.fold_span(span))
+    }
+}
+
+/// Call the folder's method for the variant of expr.
+pub fn walk_expr<F: Folder + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    let span = expr.span;
+    match expr.kind {
+        ExprKind::Literal(literal) => folder.fold_literal(span, literal),
+        ExprKind::Unary { op, right } => folder.fold_unary(span, op, *right),
+        ExprKind::Binary { left, op, right } => folder.fold_binary(span, *left, op, *right),
+        ExprKind::Block {
+            contents,
+            trailing_semicolon,
+        } => folder.fold_block(span, contents, trailing_semicolon),
+        ExprKind::Var(name) => folder.fold_var(span, name),
+        ExprKind::Let { name, value } => folder.fold_let(span, name, *value),
+        ExprKind::If {
+            condition,
+            then_expr,
+            else_expr,
+        } => folder.fold_if(span, *condition, *then_expr, else_expr.map(|e| *e)),
+        ExprKind::Fn(function) => folder.fold_fn(span, function),
+        ExprKind::Call { callee, args } => folder.fold_call(span, *callee, args),
+        ExprKind::Interpolate(parts) => folder.fold_interpolate(span, parts),
+        ExprKind::Error => folder.fold_error(span),
+    }
+}
+
+pub fn walk_unary<F: Folder + ?Sized>(
+    folder: &mut F,
+    span: Span,
+    op: UnaryOp,
+    right: Expr,
+) -> Expr {
+    let right = folder.fold_expr(right);
+    Expr::unary(op, right).with_span(folder.fold_span(span))
+}
+
+pub fn walk_binary<F: Folder + ?Sized>(
+    folder: &mut F,
+    span: Span,
+    left: Expr,
+    op: BinaryOp,
+    right: Expr,
+) -> Expr {
+    let left = folder.fold_expr(left);
+    let right = folder.fold_expr(right);
+    Expr::binary(left, op, right).with_span(folder.fold_span(span))
+}
+
+pub fn walk_block<F: Folder + ?Sized>(
+    folder: &mut F,
+    span: Span,
+    contents: Vec<Expr>,
+    trailing_semicolon: bool,
+) -> Expr {
+    let contents = contents
+        .into_iter()
+        .map(|expr| folder.fold_expr(expr))
+        .collect();
+    let kind = ExprKind::Block {
+        contents,
+        trailing_semicolon,
+    };
+    Expr::new(kind, folder.fold_span(span))
+}
+
+pub fn walk_let<F: Folder + ?Sized>(folder: &mut F, span: Span, name: Ident, value: Expr) -> Expr {
+    let name = folder.fold_ident(name);
+    let value = Box::new(folder.fold_expr(value));
+    Expr::new(ExprKind::Let { name, value }, folder.fold_span(span))
+}
+
+pub fn walk_if<F: Folder + ?Sized>(
+    folder: &mut F,
+    span: Span,
+    condition: Expr,
+    then_expr: Expr,
+    else_expr: Option<Expr>,
+) -> Expr {
+    let condition = folder.fold_expr(condition);
+    let then_expr = folder.fold_expr(then_expr);
+    let else_expr = else_expr.map(|else_expr| folder.fold_expr(else_expr));
+    Expr::ifthen(condition, then_expr, else_expr).with_span(folder.fold_span(span))
+}
+
+pub fn walk_fn<F: Folder + ?Sized>(folder: &mut F, span: Span, function: Function) -> Expr {
+    let name = function.name.map(|name| folder.fold_ident(name));
+    let params = function
+        .params
+        .into_iter()
+        .map(|param| Param {
+            name: folder.fold_ident(param.name),
+            ttype: param.ttype,
+        })
+        .collect();
+    let body = Box::new(folder.fold_expr(*function.body));
+    let function = Function {
+        name,
+        params,
+        ret: function.ret,
+        body,
+    };
+    Expr::new(ExprKind::Fn(function), folder.fold_span(span))
+}
+
+pub fn walk_call<F: Folder + ?Sized>(
+    folder: &mut F,
+    span: Span,
+    callee: Expr,
+    args: Vec<Expr>,
+) -> Expr {
+    let callee = folder.fold_expr(callee);
+    let args = args.into_iter().map(|arg| folder.fold_expr(arg)).collect();
+    Expr::call(callee, args).with_span(folder.fold_span(span))
+}
+
+pub fn walk_interpolate<F: Folder + ?Sized>(
+    folder: &mut F,
+    span: Span,
+    parts: Vec<StrPart>,
+) -> Expr {
+    let parts = parts
+        .into_iter()
+        .map(|part| match part {
+            StrPart::Text(text) => StrPart::Text(text),
+            StrPart::Expr(expr) => StrPart::Expr(folder.fold_expr(expr)),
+        })
+        .collect();
+    Expr::interpolate(parts).with_span(folder.fold_span(span))
+}
@@ -0,0 +1,324 @@
+use super::expr::BinaryOp;
+use super::expr::Expr;
+use super::expr::ExprKind;
+use super::expr::Function;
+use super::expr::Literal;
+use super::expr::Param;
+use super::expr::UnaryOp;
+use super::fold;
+use super::fold::Folder;
+use super::visit;
+use super::visit::Visitor;
+use crate::parse::operator::BINARY_OPERATORS;
+use crate::parse::operator::UNARY_OPERATORS;
+use crate::parse::parse_expr;
+use crate::span::Span;
+use crate::test_util::CORPUS;
+use crate::ttype::Type;
+
+/// Assert that expr prints as expected, and that it parses back to expr.
+fn assert_prints(expr: Expr, expected: &str) {
+    assert_eq!(expr.to_string(), expected);
+    assert_round_trips(expr);
+}
+
+fn assert_round_trips(expr: Expr) {
+    let source = expr.to_string();
+    let parsed = parse_expr(&source).unwrap_or_else(|e| panic!("{source:?}: {e}"));
+    assert_eq!(
+        parsed.without_spans(),
+        expr.without_spans(),
+        "for {source:?}"
+    );
+}
+
+fn x() -> Expr {
+    Expr::var("x")
+}
+
+fn y() -> Expr {
+    Expr::var("y")
+}
+
+fn z() -> Expr {
+    Expr::var("z")
+}
+
+#[test]
+fn test_binary_parens() {
+    let sum = Expr::binary(x(), BinaryOp::Add, y());
+    assert_prints(
+        Expr::binary(sum.clone(), BinaryOp::Mult, z()),
+        "(x + y) * z",
+    );
+    assert_prints(
+        Expr::binary(z(), BinaryOp::Mult, sum.clone()),
+        "z * (x + y)",
+    );
+    assert_prints(Expr::binary(sum.clone(), BinaryOp::Sub, z()), "x + y - z");
+    assert_prints(Expr::binary(z(), BinaryOp::Sub, sum.clone()), "z - (x + y)");
+
+    let product = Expr::binary(x(), BinaryOp::Mult, y());
+    assert_prints(Expr::binary(product, BinaryOp::Add, z()), "x * y + z");
+
+    let less = Expr::binary(x(), BinaryOp::Less, y());
+    assert_prints(
+        Expr::binary(less.clone(), BinaryOp::Equal, Expr::bool(true)),
+        "x < y == true",
+    );
+    assert_prints(Expr::binary(less, BinaryOp::Less, z()), "(x < y) < z");
+}
+
+#[test]
+fn test_unary_parens() {
+    let sum = Expr::binary(x(), BinaryOp::Add, y());
+    assert_prints(Expr::unary(UnaryOp::Neg, sum), "-(x + y)");
+    assert_prints(Expr::unary(UnaryOp::Not, x()), "not x");
+    assert_prints(
+        Expr::unary(UnaryOp::Neg, Expr::unary(UnaryOp::Neg, x())),
+        "- -x",
+    );
+    assert_prints(
+        Expr::binary(Expr::unary(UnaryOp::Neg, x()), BinaryOp::Mult, y()),
+        "-x * y",
+    );
+    assert_prints(Expr::call(Expr::unary(UnaryOp::Neg, x()), vec![]), "(-x)()");
+}
+
+#[test]
+fn test_float_literals() {
+    assert_prints(Expr::float(1.0), "1.0");
+    assert_prints(Expr::float(0.25), "0.25");
+    assert_prints(Expr::float(1e-7), "1e-7");
+    assert_prints(Expr::float(1.5e20), "1.5e20");
+    // A negative literal binds as a negation would.
+    let call = Expr::call(Expr::float(-0.0), vec![]);
+    assert_eq!(call.to_string(), "(-0.0)()");
+}
+
+/// The trees Display documents as not reading back the same.
+#[test]
+fn test_round_trip_exceptions() {
+    let reparse = |expr: &Expr| parse_expr(&expr.to_string()).map(|expr| expr.without_spans());
+    let block = |contents, trailing_semicolon| {
+        Expr::from(ExprKind::Block {
+            contents,
+            trailing_semicolon,
+        })
+    };
+
+    assert_eq!(block(vec![x()], false).to_string(), "(x)");
+    assert_eq!(reparse(&block(vec![x()], false)), Ok(x()));
+
+    let negation = |literal| Expr::unary(UnaryOp::Neg, literal);
+    assert_eq!(reparse(&Expr::int(-3)), Ok(negation(Expr::int(3))));
+    assert_eq!(reparse(&Expr::float(-1.5)), Ok(negation(Expr::float(1.5))));
+
+    let let_ = Expr::let_("x", Expr::int(1));
+    assert_eq!(let_.to_string(), "let x = 1");
+    assert!(reparse(&let_).is_err());
+    let sum = Expr::binary(let_.clone(), BinaryOp::Add, y());
+    assert_eq!(sum.to_string(), "(let x = 1) + y");
+    assert_eq!(
+        reparse(&sum),
+        Ok(Expr::binary(block(vec![let_], false), BinaryOp::Add, y()))
+    );
+
+    for (expr, printed) in [
+        (block(vec![], false), "()"),
+        (block(vec![], true), "(;)"),
+        (Expr::from(ExprKind::Error), "<error>"),
+    ] {
+        assert_eq!(expr.to_string(), printed);
+        assert!(reparse(&expr).is_err(), "for {printed:?}");
+    }
+}
+
+#[test]
+fn test_every_operator_pair() {
+    for outer in BINARY_OPERATORS {
+        for inner in BINARY_OPERATORS {
+            let left = Expr::binary(x(), inner.op, y());
+            assert_round_trips(Expr::binary(left, outer.op, z()));
+            let right = Expr::binary(y(), inner.op, z());
+            assert_round_trips(Expr::binary(x(), outer.op, right));
+        }
+        for unary in UNARY_OPERATORS {
+            let binary = Expr::binary(x(), outer.op, y());
+            assert_round_trips(Expr::unary(unary.op, binary));
+            let operand = Expr::unary(unary.op, x());
+            assert_round_trips(Expr::binary(operand.clone(), outer.op, y()));
+            assert_round_trips(Expr::binary(y(), outer.op, operand));
+        }
+    }
+}
+
+#[test]
+fn test_keywords() {
+    let if_ = Expr::ifthen(x(), y(), Some(z()));
+    assert_prints(if_.clone(), "if x (y) else z");
+    assert_prints(
+        Expr::binary(if_.clone(), BinaryOp::Add, Expr::int(1)),
+        "(if x (y) else z) + 1",
+    );
+    assert_prints(
+        Expr::binary(Expr::int(1), BinaryOp::Add, if_.clone()),
+        "1 + (if x (y) else z)",
+    );
+    assert_prints(
+        Expr::ifthen(if_, Expr::int(1), None),
+        "if if x (y) else z (1)",
+    );
+
+    let block = Expr::unit_block(vec![Expr::let_("x", Expr::int(1)), x()]);
+    assert_prints(block.clone(), "(let x = 1; x;)");
+    assert_prints(Expr::ifthen(y(), block, None), "if y (let x = 1; x;)");
+    assert_prints(Expr::block(vec![Expr::let_("x", y())]), "(let x = y)");
+}
+
+#[test]
+fn test_functions() {
+    let params = vec![Param::new("x", Type::Int), Param::new("y", Type::Bool)];
+    let f = Expr::named_fn("f", params, Type::Int, x());
+    assert_prints(f.clone(), "fn f(x: int, y: bool) -> int (x)");
+    assert_prints(
+        Expr::call(f, vec![Expr::int(1), Expr::bool(true)]),
+        "fn f(x: int, y: bool) -> int (x)(1, true)",
+    );
+
+    let ret = Type::function(vec![Type::Int], Type::Int);
+    let lambda = Expr::lambda(vec![], ret, y());
+    assert_prints(lambda.clone(), "fn () -> fn(int) -> int (y)");
+    assert_prints(
+        Expr::call(Expr::call(lambda, vec![]), vec![Expr::int(2)]),
+        "fn () -> fn(int) -> int (y)()(2)",
+    );
+
+    let sum = Expr::binary(x(), BinaryOp::Add, y());
+    assert_prints(Expr::call(sum.clone(), vec![sum]), "(x + y)(x + y)");
+}
+
+#[test]
+fn test_corpus_round_trips() {
+    let mut count = 0;
+    for source in CORPUS {
+        if let Ok(expr) = parse_expr(source) {
+            assert_round_trips(expr);
+            count += 1;
+        }
+    }
+    assert!(count > 100);
+}
+
+/// Names of the variables referred to, in source order, except in the
+/// bodies of functions.
+#[derive(Default)]
+struct VarNames(Vec<String>);
+
+impl Visitor for VarNames {
+    fn visit_var(&mut self, _span: Span, name: &str) {
+        self.0.push(name.to_owned());
+    }
+
+    fn visit_fn(&mut self, _span: Span, _function: &Function) {}
+}
+
+#[test]
+fn test_visitor() {
+    let expr = parse_expr("(let a = b; if c (d(e, fn () -> int (f))) else -g * h)").unwrap();
+    let mut names = VarNames::default();
+    names.visit_expr(&expr);
+    assert_eq!(names.0, vec!["b", "c", "d", "e", "g", "h"]);
+
+    /// Count binary expressions, and everything under them.
+    #[derive(Default)]
+    struct Binaries(usize);
+    impl Visitor for Binaries {
+        fn visit_binary(&mut self, _span: Span, left: &Expr, _op: BinaryOp, right: &Expr) {
+            self.0 += 1;
+            visit::walk_binary(self, left, right);
+        }
+    }
+    let mut binaries = Binaries::default();
+    binaries.visit_expr(&parse_expr("1 + 2 * (3 - f(4 / 5))").unwrap());
+    assert_eq!(binaries.0, 4);
+}
+
+/// Rewrite `e * 2` as `e + e`, and rename every variable `x` to `y`.
+struct Rewriter;
+
+impl Folder for Rewriter {
+    fn fold_binary(&mut self, span: Span, left: Expr, op: BinaryOp, right: Expr) -> Expr {
+        if op == BinaryOp::Mult && right.kind == ExprKind::Literal(Literal::Int(2)) {
+            let left = self.fold_expr(left);
+            return Expr::binary(left.clone(), BinaryOp::Add, left).with_span(span);
+        }
+        fold::walk_binary(self, span, left, op, right)
+    }
+
+    fn fold_var(&mut self, span: Span, name: String) -> Expr {
+        let name = if name == "x" { "y".to_owned() } else { name };
+        Expr::var(name).with_span(span)
+    }
+}
+
+#[test]
+fn test_folder() {
+    let expr = parse_expr("(let z = x * 2; if z (f(x) * 2) else (x; x * 3))").unwrap();
+    let folded = Rewriter.fold_expr(expr.clone());
+    assert_eq!(
+        folded.to_string(),
+        "(let z = y + y; if z (f(y) + f(y)) else (y; y * 3))"
+    );
+    assert_eq!(folded.span, expr.span);
+
+    /// The default methods rebuild the expression unchanged.
+    struct Identity;
+    impl Folder for Identity {}
+    for source in CORPUS {
+        if let Ok(expr) = parse_expr(source) {
+            assert_eq!(Identity.fold_expr(expr.clone()), expr, "for {source:?}");
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_shape() {
+    let expr = parse_expr("x + 1").unwrap();
+    let json = serde_json::json!({
+        "kind": {"binary": {
+            "left": {"kind": {"var": "x"}, "span": {"start": 0, "end": 1, "line": 0, "col": 0}},
+            "op": "add",
+            "right": {"kind": {"literal": {"int": 1}}, "span": {"start": 4, "end": 5, "line": 0, "col": 4}},
+        }},
+        "span": {"start": 0, "end": 5, "line": 0, "col": 0},
+    });
+    assert_eq!(serde_json::to_value(&expr).unwrap(), json);
+
+    let params = vec![Param::new("f", Type::function(vec![Type::Int], Type::Bool))];
+    let lambda = Expr::lambda(params, Type::Unit, Expr::unit_block(vec![]));
+    let json = serde_json::json!({"kind": {"fn": {
+        "name": null,
+        "params": [{"name": {"name": "f"}, "ttype": {"fn": {"params": ["int"], "ret": "bool"}}}],
+        "ret": "unit",
+        "body": {"kind": {"block": {"contents": [], "trailing_semicolon": true}}},
+    }}});
+    assert_eq!(serde_json::from_value::<Expr>(json).unwrap(), lambda);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trips() {
+    let mut count = 0;
+    for source in CORPUS {
+        if let Ok(expr) = parse_expr(source) {
+            let json = serde_json::to_string(&expr).unwrap();
+            let parsed: Expr =
+                serde_json::from_str(&json).unwrap_or_else(|e| panic!("{json}: {e}"));
+            assert_eq!(parsed, expr, "for {source:?}");
+            count += 1;
+        }
+    }
+    assert!(count > 100);
+}
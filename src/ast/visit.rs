@@ -0,0 +1,137 @@
+use super::expr::BinaryOp;
+use super::expr::Expr;
+use super::expr::ExprKind;
+use super::expr::Function;
+use super::expr::Ident;
+use super::expr::Literal;
+use super::expr::StrPart;
+use super::expr::UnaryOp;
+use crate::span::Span;
+
+/// Read-only traversal of an Expr.
+///
+/// visit_expr calls the method for the expression's variant, whose default
+/// visits its subexpressions in source order.  Override the methods for the
+/// nodes of interest, and call the matching walk function from an override
+/// to continue into the subexpressions.
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_literal(&mut self, _span: Span, _literal: &Literal) {}
+
+    fn visit_unary(&mut self, _span: Span, _op: UnaryOp, right: &Expr) {
+        walk_unary(self, right);
+    }
+
+    fn visit_binary(&mut self, _span: Span, left: &Expr, _op: BinaryOp, right: &Expr) {
+        walk_binary(self, left, right);
+    }
+
+    fn visit_block(&mut self, _span: Span, contents: &[Expr], _trailing_semicolon: bool) {
+        walk_block(self, contents);
+    }
+
+    fn visit_var(&mut self, _span: Span, _name: &str) {}
+
+    fn visit_let(&mut self, _span: Span, _name: &Ident, value: &Expr) {
+        walk_let(self, value);
+    }
+
+    fn visit_if(
+        &mut self,
+        _span: Span,
+        condition: &Expr,
+        then_expr: &Expr,
+        else_expr: Option<&Expr>,
+    ) {
+        walk_if(self, condition, then_expr, else_expr);
+    }
+
+    fn visit_fn(&mut self, _span: Span, function: &Function) {
+        walk_fn(self, function);
+    }
+
+    fn visit_call(&mut self, _span: Span, callee: &Expr, args: &[Expr]) {
+        walk_call(self, callee, args);
+    }
+
+    fn visit_interpolate(&mut self, _span: Span, parts: &[StrPart]) {
+        walk_interpolate(self, parts);
+    }
+
+    fn visit_error(&mut self, _span: Span) {}
+}
+
+/// Call the visitor's method for the variant of expr.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    let span = expr.span;
+    match &expr.kind {
+        ExprKind::Literal(literal) => visitor.visit_literal(span, literal),
+        ExprKind::Unary { op, right } => visitor.visit_unary(span, *op, right),
+        ExprKind::Binary { left, op, right } => visitor.visit_binary(span, left, *op, right),
+        ExprKind::Block {
+            contents,
+            trailing_semicolon,
+        } => visitor.visit_block(span, contents, *trailing_semicolon),
+        ExprKind::Var(name) => visitor.visit_var(span, name),
+        ExprKind::Let { name, value } => visitor.visit_let(span, name, value),
+        ExprKind::If {
+            condition,
+            then_expr,
+            else_expr,
+        } => visitor.visit_if(span, condition, then_expr, else_expr.as_deref()),
+        ExprKind::Fn(function) => visitor.visit_fn(span, function),
+        ExprKind::Call { callee, args } => visitor.visit_call(span, callee, args),
+        ExprKind::Interpolate(parts) => visitor.visit_interpolate(span, parts),
+        ExprKind::Error => visitor.visit_error(span),
+    }
+}
+
+pub fn walk_unary<V: Visitor + ?Sized>(visitor: &mut V, right: &Expr) {
+    visitor.visit_expr(right);
+}
+
+pub fn walk_binary<V: Visitor + ?Sized>(visitor: &mut V, left: &Expr, right: &Expr) {
+    visitor.visit_expr(left);
+    visitor.visit_expr(right);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, contents: &[Expr]) {
+    contents.iter().for_each(|expr| visitor.visit_expr(expr));
+}
+
+pub fn walk_let<V: Visitor + ?Sized>(visitor: &mut V, value: &Expr) {
+    visitor.visit_expr(value);
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    condition: &Expr,
+    then_expr: &Expr,
+    else_expr: Option<&Expr>,
+) {
+    visitor.visit_expr(condition);
+    visitor.visit_expr(then_expr);
+    if let Some(else_expr) = else_expr {
+        visitor.visit_expr(else_expr);
+    }
+}
+
+pub fn walk_fn<V: Visitor + ?Sized>(visitor: &mut V, function: &Function) {
+    visitor.visit_expr(&function.body);
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(visitor: &mut V, callee: &Expr, args: &[Expr]) {
+    visitor.visit_expr(callee);
+    args.iter().for_each(|arg| visitor.visit_expr(arg));
+}
+
+pub fn walk_interpolate<V: Visitor + ?Sized>(visitor: &mut V, parts: &[StrPart]) {
+    for part in parts {
+        if let StrPart::Expr(expr) = part {
+            visitor.visit_expr(expr);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Language server for Carbide, speaking LSP over standard input and output.
+
+mod server;
+
+use std::process::ExitCode;
+
+use lsp_server::Connection;
+
+fn main() -> ExitCode {
+    let (connection, io_threads) = Connection::stdio();
+    let result = server::run(connection).and_then(|()| Ok(io_threads.join()?));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("carbide-lsp: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
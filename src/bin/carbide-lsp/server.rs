@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use carbide::analysis::Analysis;
+use carbide::diagnostic;
+use carbide::diagnostic::Severity;
+use carbide::parse::TokenType;
+use carbide::parse::Tokenizer;
+use carbide::span::Span;
+use logos::Logos;
+use lsp_server::Connection;
+use lsp_server::ErrorCode;
+use lsp_server::Message;
+use lsp_server::Notification;
+use lsp_server::Request;
+use lsp_server::Response;
+use lsp_types::notification::DidChangeTextDocument;
+use lsp_types::notification::DidCloseTextDocument;
+use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::Notification as _;
+use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::GotoDefinition;
+use lsp_types::request::HoverRequest;
+use lsp_types::request::Request as _;
+use lsp_types::request::SemanticTokensFullRequest;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::GotoDefinitionParams;
+use lsp_types::GotoDefinitionResponse;
+use lsp_types::Hover;
+use lsp_types::HoverContents;
+use lsp_types::HoverParams;
+use lsp_types::HoverProviderCapability;
+use lsp_types::Location;
+use lsp_types::MarkupContent;
+use lsp_types::MarkupKind;
+use lsp_types::OneOf;
+use lsp_types::Position;
+use lsp_types::PublishDiagnosticsParams;
+use lsp_types::Range;
+use lsp_types::SemanticToken;
+use lsp_types::SemanticTokenType;
+use lsp_types::SemanticTokens;
+use lsp_types::SemanticTokensFullOptions;
+use lsp_types::SemanticTokensLegend;
+use lsp_types::SemanticTokensOptions;
+use lsp_types::SemanticTokensParams;
+use lsp_types::SemanticTokensResult;
+use lsp_types::SemanticTokensServerCapabilities;
+use lsp_types::ServerCapabilities;
+use lsp_types::TextDocumentPositionParams;
+use lsp_types::TextDocumentSyncCapability;
+use lsp_types::TextDocumentSyncKind;
+use lsp_types::Uri;
+
+pub type ServerResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Semantic token types, indexed by the token type numbers sent.
+const TOKEN_TYPES: [SemanticTokenType; 7] = [
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::STRING,
+];
+
+pub fn capabilities() -> ServerCapabilities {
+    let legend = SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: Vec::new(),
+    };
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend,
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    }
+}
+
+/// Serve requests on connection until the client shuts the server down.
+pub fn run(connection: Connection) -> ServerResult<()> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.main_loop()
+}
+
+/// Open source file, with its analysis.
+struct Document {
+    text: String,
+    /// Byte offset of the start of each line.
+    line_starts: Vec<usize>,
+    analysis: Analysis,
+}
+
+impl Document {
+    fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let analysis = Analysis::new(&text);
+        Self {
+            text,
+            line_starts,
+            analysis,
+        }
+    }
+
+    /// LSP position of a byte offset, with the column in UTF-16 code units.
+    fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let prefix = &self.text[self.line_starts[line]..offset];
+        Position::new(line as u32, prefix.encode_utf16().count() as u32)
+    }
+
+    /// Byte offset of an LSP position, clamped to its line.
+    fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let end = self
+            .line_starts
+            .get(position.line as usize + 1)
+            .map_or(self.text.len(), |&next| next - 1);
+        let mut units = 0;
+        for (i, c) in self.text[start..end].char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        end
+    }
+
+    fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+}
+
+struct Server {
+    connection: Connection,
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server {
+    fn main_loop(&mut self) -> ServerResult<()> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.request(request);
+                    self.connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => self.notification(notification)?,
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, Self::hover),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, Self::definition),
+            SemanticTokensFullRequest::METHOD => {
+                self.respond::<SemanticTokensFullRequest>(request, Self::semantic_tokens)
+            }
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request {method}"),
+            ),
+        }
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(error) => Response::new_err(
+                request.id,
+                ErrorCode::InvalidParams as i32,
+                error.to_string(),
+            ),
+        }
+    }
+
+    fn notification(&mut self, notification: Notification) -> ServerResult<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.update(document.uri, document.text)?;
+            }
+            // The server asks for full sync, so the last change is the whole text.
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.update(params.text_document.uri, change.text)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish(uri, Vec::new())?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Analyze the new text of a document, and publish its diagnostics.
+    fn update(&mut self, uri: Uri, text: String) -> ServerResult<()> {
+        let document = Document::new(text);
+        let diagnostics = document
+            .analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(&document, diagnostic))
+            .collect();
+        self.documents.insert(uri.clone(), document);
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&self, uri: Uri, diagnostics: Vec<lsp_types::Diagnostic>) -> ServerResult<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_owned(), params);
+        self.connection.sender.send(notification.into())?;
+        Ok(())
+    }
+
+    /// The document and byte offset at a position.
+    fn locate(&self, position: &TextDocumentPositionParams) -> Option<(&Document, usize)> {
+        let document = self.documents.get(&position.text_document.uri)?;
+        Some((document, document.offset(position.position)))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (document, offset) = self.locate(&params.text_document_position_params)?;
+        let (span, ttype) = document.analysis.type_at(offset)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```carbide\n{ttype}\n```"),
+            }),
+            range: Some(document.range(span)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let (document, offset) = self.locate(&position)?;
+        let (_, definition) = document.analysis.definition_at(offset)?;
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            position.text_document.uri,
+            document.range(definition),
+        )))
+    }
+
+    fn semantic_tokens(&self, params: SemanticTokensParams) -> Option<SemanticTokensResult> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens(document),
+        }))
+    }
+}
+
+fn lsp_diagnostic(
+    document: &Document,
+    diagnostic: &diagnostic::Diagnostic,
+) -> lsp_types::Diagnostic {
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+    };
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message.push_str(&format!("\nnote: {note}"));
+    }
+    if let Some(help) = &diagnostic.help {
+        message.push_str(&format!("\nhelp: {help}"));
+    }
+    lsp_types::Diagnostic {
+        range: document.range(diagnostic.span),
+        severity: Some(severity),
+        source: Some("carbide".to_owned()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// Index in TOKEN_TYPES of the semantic type of a token, if it has one.
+fn token_type_index(token_type: TokenType) -> Option<u32> {
+    let semantic_type = match token_type {
+        TokenType::True
+        | TokenType::False
+        | TokenType::Not
+        | TokenType::And
+        | TokenType::Or
+        | TokenType::Len
+        | TokenType::Let
+        | TokenType::If
+        | TokenType::Then
+        | TokenType::Else
+        | TokenType::Fn => SemanticTokenType::KEYWORD,
+        TokenType::Bool
+        | TokenType::IntType
+        | TokenType::FloatType
+        | TokenType::UnitType
+        | TokenType::StrType => SemanticTokenType::TYPE,
+        TokenType::Identifier => SemanticTokenType::VARIABLE,
+        TokenType::Int | TokenType::Float => SemanticTokenType::NUMBER,
+        TokenType::Plus
+        | TokenType::Minus
+        | TokenType::Star
+        | TokenType::Slash
+        | TokenType::LAngleEq
+        | TokenType::LAngle
+        | TokenType::RAngleEq
+        | TokenType::RAngle
+        | TokenType::EqualEqual
+        | TokenType::BangEqual
+        | TokenType::Equal
+        | TokenType::Arrow => SemanticTokenType::OPERATOR,
+        TokenType::LineComment | TokenType::BlockComment | TokenType::UnterminatedComment => {
+            SemanticTokenType::COMMENT
+        }
+        TokenType::Str | TokenType::UnterminatedString => SemanticTokenType::STRING,
+        _ => return None,
+    };
+    TOKEN_TYPES
+        .iter()
+        .position(|t| *t == semantic_type)
+        .map(|i| i as u32)
+}
+
+/// Semantic tokens of a document, relative to the previous token as LSP
+/// requires.  Tokens spanning lines are split into one token per line.
+fn semantic_tokens(document: &Document) -> Vec<SemanticToken> {
+    let mut tokens = Vec::new();
+    let mut previous = Position::new(0, 0);
+    for token in Tokenizer::new(TokenType::lexer(&document.text)) {
+        let Some(token_type) = token_type_index(token.token_type) else {
+            continue;
+        };
+        let mut start = token.data.span.start;
+        for line in token.data.lexeme.split('\n') {
+            let position = document.position(start);
+            let length = line.encode_utf16().count() as u32;
+            start += line.len() + 1;
+            if length == 0 {
+                continue;
+            }
+            let delta_start = if position.line == previous.line {
+                position.character - previous.character
+            } else {
+                position.character
+            };
+            tokens.push(SemanticToken {
+                delta_line: position.line - previous.line,
+                delta_start,
+                length,
+                token_type,
+                token_modifiers_bitset: 0,
+            });
+            previous = position;
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests;
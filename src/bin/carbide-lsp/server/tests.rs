@@ -0,0 +1,207 @@
+use std::str::FromStr;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use lsp_types::notification::Exit;
+use lsp_types::notification::Initialized;
+use lsp_types::request::Initialize;
+use lsp_types::request::Shutdown;
+use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::InitializeParams;
+use lsp_types::InitializedParams;
+use lsp_types::TextDocumentIdentifier;
+use lsp_types::TextDocumentItem;
+
+use super::*;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// In-process client, talking to a server running on another thread.
+struct Client {
+    connection: Connection,
+    server: JoinHandle<ServerResult<()>>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Self {
+        let (server, connection) = Connection::memory();
+        let server = thread::spawn(move || run(server));
+        let mut client = Self {
+            connection,
+            server,
+            next_id: 0,
+        };
+        client.request::<Initialize>(InitializeParams::default());
+        client.notify::<Initialized>(InitializedParams {});
+        client
+    }
+
+    fn request<R: lsp_types::request::Request>(&mut self, params: R::Params) -> serde_json::Value {
+        self.next_id += 1;
+        let request = Request::new(self.next_id.into(), R::METHOD.to_owned(), params);
+        self.connection.sender.send(request.into()).unwrap();
+        loop {
+            match self.connection.receiver.recv_timeout(TIMEOUT).unwrap() {
+                Message::Response(response) => {
+                    assert_eq!(response.id, self.next_id.into());
+                    assert!(response.error.is_none(), "{:?}", response.error);
+                    return response.result.unwrap();
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) {
+        let notification = Notification::new(N::METHOD.to_owned(), params);
+        self.connection.sender.send(notification.into()).unwrap();
+    }
+
+    /// Open a document, returning the diagnostics published for it.
+    fn open(&self, uri: &Uri, text: &str) -> PublishDiagnosticsParams {
+        let document = TextDocumentItem::new(uri.clone(), "carbide".to_owned(), 1, text.to_owned());
+        self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: document,
+        });
+        match self.connection.receiver.recv_timeout(TIMEOUT).unwrap() {
+            Message::Notification(notification) => {
+                assert_eq!(notification.method, PublishDiagnostics::METHOD);
+                serde_json::from_value(notification.params).unwrap()
+            }
+            message => panic!("expected diagnostics, got {message:?}"),
+        }
+    }
+
+    fn stop(mut self) {
+        self.request::<Shutdown>(());
+        self.notify::<Exit>(());
+        self.server.join().unwrap().unwrap();
+    }
+}
+
+fn uri() -> Uri {
+    Uri::from_str("file:///test.cb").unwrap()
+}
+
+fn position(uri: &Uri, line: u32, character: u32) -> TextDocumentPositionParams {
+    TextDocumentPositionParams::new(
+        TextDocumentIdentifier::new(uri.clone()),
+        Position::new(line, character),
+    )
+}
+
+#[test]
+fn test_diagnostics() {
+    let client = Client::start();
+    let uri = uri();
+
+    let published = client.open(&uri, "(let x = true;\nx +)");
+    assert_eq!(published.uri, uri);
+    assert_eq!(published.diagnostics.len(), 1);
+    let diagnostic = &published.diagnostics[0];
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(diagnostic.source.as_deref(), Some("carbide"));
+    assert_eq!(diagnostic.range.start.line, 1);
+
+    let published = client.open(&uri, "1 + true");
+    assert_eq!(published.diagnostics.len(), 1);
+
+    let published = client.open(&uri, "1 + 2");
+    assert!(published.diagnostics.is_empty());
+
+    client.notify::<DidCloseTextDocument>(lsp_types::DidCloseTextDocumentParams {
+        text_document: TextDocumentIdentifier::new(uri.clone()),
+    });
+    client.stop();
+}
+
+#[test]
+fn test_hover_and_definition() {
+    let mut client = Client::start();
+    let uri = uri();
+    client.open(&uri, "(let x = 1;\n x + 2)");
+
+    let hover = client.request::<HoverRequest>(HoverParams {
+        text_document_position_params: position(&uri, 1, 1),
+        work_done_progress_params: Default::default(),
+    });
+    let hover: Hover = serde_json::from_value(hover).unwrap();
+    let HoverContents::Markup(contents) = hover.contents else {
+        panic!("expected markup, got {:?}", hover.contents);
+    };
+    assert_eq!(contents.value, "```carbide\nint\n```");
+    assert_eq!(
+        hover.range,
+        Some(Range::new(Position::new(1, 1), Position::new(1, 2)))
+    );
+
+    let definition = client.request::<GotoDefinition>(GotoDefinitionParams {
+        text_document_position_params: position(&uri, 1, 1),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    });
+    let definition: GotoDefinitionResponse = serde_json::from_value(definition).unwrap();
+    assert_eq!(
+        definition,
+        GotoDefinitionResponse::Scalar(Location::new(
+            uri.clone(),
+            Range::new(Position::new(0, 5), Position::new(0, 6))
+        ))
+    );
+
+    let definition = client.request::<GotoDefinition>(GotoDefinitionParams {
+        text_document_position_params: position(&uri, 0, 9),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    });
+    assert_eq!(definition, serde_json::Value::Null);
+    client.stop();
+}
+
+#[test]
+fn test_semantic_tokens() {
+    let mut client = Client::start();
+    let uri = uri();
+    client.open(&uri, "let x = 1 /* a\nb */ x");
+
+    let tokens = client.request::<SemanticTokensFullRequest>(SemanticTokensParams {
+        text_document: TextDocumentIdentifier::new(uri.clone()),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    });
+    let SemanticTokensResult::Tokens(tokens) = serde_json::from_value(tokens).unwrap() else {
+        panic!("expected full tokens");
+    };
+    let data: Vec<_> = tokens
+        .data
+        .iter()
+        .map(|t| (t.delta_line, t.delta_start, t.length, t.token_type))
+        .collect();
+    assert_eq!(
+        data,
+        vec![
+            (0, 0, 3, 0),
+            (0, 4, 1, 2),
+            (0, 2, 1, 4),
+            (0, 2, 1, 3),
+            (0, 2, 4, 5),
+            (1, 0, 4, 5),
+            (0, 5, 1, 2),
+        ]
+    );
+    client.stop();
+}
+
+#[test]
+fn test_positions() {
+    let document = Document::new("a\n\u{e9}\u{1f600}b\n".to_owned());
+    assert_eq!(document.position(0), Position::new(0, 0));
+    assert_eq!(document.position(2), Position::new(1, 0));
+    assert_eq!(document.position(8), Position::new(1, 3));
+    assert_eq!(document.position(10), Position::new(2, 0));
+    assert_eq!(document.offset(Position::new(1, 3)), 8);
+    assert_eq!(document.offset(Position::new(1, 10)), 9);
+    assert_eq!(document.offset(Position::new(5, 0)), 10);
+}
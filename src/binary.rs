@@ -0,0 +1,894 @@
+//! Compact binary format for expressions and compiled chunks, so they can
+//! be cached on disk and loaded without parsing or compiling again.
+//!
+//! A file is a header followed by a payload.  The header is
+//!
+//! - the magic bytes `CRBD`,
+//! - the format version, a little-endian u16,
+//! - what the payload holds: 0 for an Expr, 1 for a Chunk,
+//! - the CRC-32 checksum of the payload, a little-endian u32.
+//!
+//! In the payload, unsigned integers, including lengths and counts, are
+//! LEB128 varints, and signed integers are zigzag-encoded varints.  Strings
+//! are a length and UTF-8 bytes, floats are their IEEE 754 bits as a
+//! little-endian u64, and each enum is a one-byte tag followed
+//! by its fields, in declaration order.  Expressions, types and functions
+//! nest at most MAX_NESTING deep.
+//!
+//! Files from a newer version of the format are rejected rather than read.
+//! Version 2 added strings and `len`, and version 3 floats, each with new tags after
+//! those of the version before, so older files read as before.  A file
+//! using tags newer than its version is malformed.
+
+mod error;
+
+use std::rc::Rc;
+
+pub use error::DecodeError;
+
+use crate::ast::expr::BinaryOp;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Function;
+use crate::ast::expr::Ident;
+use crate::ast::expr::Literal;
+use crate::ast::expr::Param;
+use crate::ast::expr::StrPart;
+use crate::ast::expr::UnaryOp;
+use crate::compile::Capture;
+use crate::compile::Chunk;
+use crate::compile::Constant;
+use crate::compile::Op;
+use crate::compile::Prototype;
+use crate::span::Span;
+use crate::ttype::Type;
+
+pub type DecodeResult<T> = Result<T, DecodeError>;
+
+pub const MAGIC: [u8; 4] = *b"CRBD";
+
+/// Version of the format written, and the newest version read.
+pub const FORMAT_VERSION: u16 = 3;
+
+const HEADER_LEN: usize = 11;
+const VERSION_OFFSET: usize = 4;
+const CONTENTS_OFFSET: usize = 6;
+
+/// How deeply expressions, types and functions may nest in a payload.
+/// A file nesting deeper is malformed, rather than overflowing the stack.
+pub const MAX_NESTING: usize = 256;
+
+/// What a file's payload holds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Contents {
+    Expr,
+    Chunk,
+}
+
+impl Contents {
+    fn tag(self) -> u8 {
+        match self {
+            Contents::Expr => 0,
+            Contents::Chunk => 1,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Contents::Expr => "an expression",
+            Contents::Chunk => "bytecode",
+        }
+    }
+}
+
+pub fn encode_expr(expr: &Expr) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.expr(expr);
+    encoder.finish(Contents::Expr)
+}
+
+pub fn decode_expr(bytes: &[u8]) -> DecodeResult<Expr> {
+    let mut decoder = Decoder::new(bytes, Contents::Expr)?;
+    let expr = decoder.expr()?;
+    decoder.finish()?;
+    Ok(expr)
+}
+
+pub fn encode_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.chunk(chunk);
+    encoder.finish(Contents::Chunk)
+}
+
+/// Decode a chunk, checking that each instruction's operands refer to
+/// constants of the right kind, locals, captures and stack values that
+/// exist, and jump within the chunk, so the vm can run it.
+pub fn decode_chunk(bytes: &[u8]) -> DecodeResult<Chunk> {
+    let mut decoder = Decoder::new(bytes, Contents::Chunk)?;
+    let chunk = decoder.chunk(0, 0)?;
+    decoder.finish()?;
+    Ok(chunk)
+}
+
+/// CRC-32 (IEEE) of bytes.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Operators in tag order.
+const UNARY_OPS: [UnaryOp; 4] = [UnaryOp::Not, UnaryOp::Neg, UnaryOp::Pos, UnaryOp::Len];
+const BINARY_OPS: [BinaryOp; 12] = [
+    BinaryOp::And,
+    BinaryOp::Or,
+    BinaryOp::Less,
+    BinaryOp::LessEq,
+    BinaryOp::Great,
+    BinaryOp::GreatEq,
+    BinaryOp::Equal,
+    BinaryOp::NotEqual,
+    BinaryOp::Add,
+    BinaryOp::Sub,
+    BinaryOp::Mult,
+    BinaryOp::Div,
+];
+
+/// Number of tags in each version, from 1, of the enums that gained variants.
+const TYPE_TAGS: [u8; FORMAT_VERSION as usize] = [4, 5, 6];
+const UNARY_TAGS: [u8; FORMAT_VERSION as usize] = [3, 4, 4];
+const EXPR_TAGS: [u8; FORMAT_VERSION as usize] = [11, 13, 14];
+const CONSTANT_TAGS: [u8; FORMAT_VERSION as usize] = [3, 4, 5];
+const OP_TAGS: [u8; FORMAT_VERSION as usize] = [32, 37, 38];
+
+fn tag_of<T: PartialEq>(ops: &[T], op: T) -> u8 {
+    ops.iter().position(|o| *o == op).unwrap() as u8
+}
+
+#[derive(Default)]
+struct Encoder {
+    payload: Vec<u8>,
+}
+
+impl Encoder {
+    fn finish(self, contents: Contents) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.payload.len());
+        bytes.extend(MAGIC);
+        bytes.extend(FORMAT_VERSION.to_le_bytes());
+        bytes.push(contents.tag());
+        bytes.extend(crc32(&self.payload).to_le_bytes());
+        bytes.extend(self.payload);
+        bytes
+    }
+
+    fn byte(&mut self, byte: u8) {
+        self.payload.push(byte);
+    }
+
+    fn uint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.byte(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.byte(n as u8);
+    }
+
+    fn int(&mut self, i: i64) {
+        self.uint(((i << 1) ^ (i >> 63)) as u64);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.uint(len as u64);
+    }
+
+    fn float(&mut self, x: f64) {
+        self.payload.extend(x.to_bits().to_le_bytes());
+    }
+
+    fn string(&mut self, s: &str) {
+        self.len(s.len());
+        self.payload.extend(s.as_bytes());
+    }
+
+    fn span(&mut self, span: Span) {
+        self.len(span.start);
+        self.len(span.end);
+        self.len(span.line);
+        self.len(span.col);
+    }
+
+    fn ident(&mut self, ident: &Ident) {
+        self.string(&ident.name);
+        self.span(ident.span);
+    }
+
+    fn option<T>(&mut self, value: Option<T>, mut some: impl FnMut(&mut Self, T)) {
+        match value {
+            Some(value) => {
+                self.byte(1);
+                some(self, value);
+            }
+            None => self.byte(0),
+        }
+    }
+
+    fn ttype(&mut self, ttype: &Type) {
+        match ttype {
+            Type::Bool => self.byte(0),
+            Type::Int => self.byte(1),
+            Type::Unit => self.byte(2),
+            Type::Fn { params, ret } => {
+                self.byte(3);
+                self.len(params.len());
+                params.iter().for_each(|param| self.ttype(param));
+                self.ttype(ret);
+            }
+            Type::Str => self.byte(4),
+            Type::Float => self.byte(5),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Bool(b)) => {
+                self.byte(0);
+                self.byte(*b as u8);
+            }
+            ExprKind::Literal(Literal::Int(i)) => {
+                self.byte(1);
+                self.int(*i);
+            }
+            ExprKind::Unary { op, right } => {
+                self.byte(2);
+                self.byte(tag_of(&UNARY_OPS, *op));
+                self.expr(right);
+            }
+            ExprKind::Binary { left, op, right } => {
+                self.byte(3);
+                self.expr(left);
+                self.byte(tag_of(&BINARY_OPS, *op));
+                self.expr(right);
+            }
+            ExprKind::Block {
+                contents,
+                trailing_semicolon,
+            } => {
+                self.byte(4);
+                self.len(contents.len());
+                contents.iter().for_each(|expr| self.expr(expr));
+                self.byte(*trailing_semicolon as u8);
+            }
+            ExprKind::Var(name) => {
+                self.byte(5);
+                self.string(name);
+            }
+            ExprKind::Let { name, value } => {
+                self.byte(6);
+                self.ident(name);
+                self.expr(value);
+            }
+            ExprKind::If {
+                condition,
+                then_expr,
+                else_expr,
+            } => {
+                self.byte(7);
+                self.expr(condition);
+                self.expr(then_expr);
+                self.option(else_expr.as_deref(), Self::expr);
+            }
+            ExprKind::Fn(function) => {
+                self.byte(8);
+                self.option(function.name.as_ref(), Self::ident);
+                self.len(function.params.len());
+                for param in &function.params {
+                    self.ident(&param.name);
+                    self.ttype(&param.ttype);
+                }
+                self.ttype(&function.ret);
+                self.expr(&function.body);
+            }
+            ExprKind::Call { callee, args } => {
+                self.byte(9);
+                self.expr(callee);
+                self.len(args.len());
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Error => self.byte(10),
+            ExprKind::Literal(Literal::Str(s)) => {
+                self.byte(11);
+                self.string(s);
+            }
+            ExprKind::Interpolate(parts) => {
+                self.byte(12);
+                self.len(parts.len());
+                for part in parts {
+                    match part {
+                        StrPart::Text(text) => {
+                            self.byte(0);
+                            self.string(text);
+                        }
+                        StrPart::Expr(expr) => {
+                            self.byte(1);
+                            self.expr(expr);
+                        }
+                    }
+                }
+            }
+            ExprKind::Literal(Literal::Float(x)) => {
+                self.byte(13);
+                self.float(*x);
+            }
+        }
+        self.span(expr.span);
+    }
+
+    fn chunk(&mut self, chunk: &Chunk) {
+        self.len(chunk.code.len());
+        for (op, span) in chunk.code.iter().zip(&chunk.spans) {
+            self.op(*op);
+            self.span(*span);
+        }
+        self.len(chunk.constants.len());
+        for constant in &chunk.constants {
+            match constant {
+                Constant::Int(i) => {
+                    self.byte(0);
+                    self.int(*i);
+                }
+                Constant::Name(name) => {
+                    self.byte(1);
+                    self.string(name);
+                }
+                Constant::Function(prototype) => {
+                    self.byte(2);
+                    self.prototype(prototype);
+                }
+                Constant::Str(s) => {
+                    self.byte(3);
+                    self.string(s);
+                }
+                Constant::Float(x) => {
+                    self.byte(4);
+                    self.float(*x);
+                }
+            }
+        }
+    }
+
+    fn prototype(&mut self, prototype: &Prototype) {
+        self.option(prototype.name.as_deref(), Self::string);
+        self.len(prototype.params.len());
+        prototype.params.iter().for_each(|param| self.ttype(param));
+        self.ttype(&prototype.ret);
+        self.len(prototype.captures.len());
+        for capture in &prototype.captures {
+            let (tag, index) = match capture {
+                Capture::Local(index) => (0, index),
+                Capture::Capture(index) => (1, index),
+            };
+            self.byte(tag);
+            self.uint(*index as u64);
+        }
+        self.chunk(&prototype.chunk);
+    }
+
+    fn op(&mut self, op: Op) {
+        let (tag, operand) = match op {
+            Op::Constant(index) => (0, Some(index)),
+            Op::True => (1, None),
+            Op::False => (2, None),
+            Op::Unit => (3, None),
+            Op::Pop => (4, None),
+            Op::PopUnder(count) => (5, Some(count)),
+            Op::GetLocal(slot) => (6, Some(slot)),
+            Op::GetCapture(index) => (7, Some(index)),
+            Op::Undefined(index) => (8, Some(index)),
+            Op::Unparsed => (9, None),
+            Op::CheckBool => (10, None),
+            Op::CheckInt => (11, None),
+            Op::Not => (12, None),
+            Op::Neg => (13, None),
+            Op::Add => (14, None),
+            Op::Sub => (15, None),
+            Op::Mult => (16, None),
+            Op::Div => (17, None),
+            Op::Less => (18, None),
+            Op::LessEq => (19, None),
+            Op::Great => (20, None),
+            Op::GreatEq => (21, None),
+            Op::Equal => (22, None),
+            Op::Jump(offset) => (23, Some(offset)),
+            Op::JumpIfFalse(offset) => (24, Some(offset)),
+            Op::JumpIfTrue(offset) => (25, Some(offset)),
+            Op::Closure(index) => (26, Some(index)),
+            Op::CheckCallable => (27, None),
+            Op::CheckArity(count) => (28, Some(count as u16)),
+            Op::CheckArg(index) => (29, Some(index as u16)),
+            Op::Call(argc) => (30, Some(argc as u16)),
+            Op::Return => (31, None),
+            Op::CheckStr => (32, None),
+            Op::CheckNumberOrStr => (33, None),
+            Op::CheckSame => (34, None),
+            Op::Len => (35, None),
+            Op::Interpolate(count) => (36, Some(count)),
+            Op::CheckNumber => (37, None),
+        };
+        self.byte(tag);
+        if let Some(operand) = operand {
+            self.uint(operand as u64);
+        }
+    }
+}
+
+struct Decoder<'bytes> {
+    bytes: &'bytes [u8],
+    /// Offset of the next byte to read.
+    pos: usize,
+    /// Format version of the file.
+    version: u16,
+    /// Expressions, types and functions being decoded.
+    depth: usize,
+}
+
+impl<'bytes> Decoder<'bytes> {
+    /// Check the header of bytes, and start decoding its payload.
+    fn new(bytes: &'bytes [u8], expected: Contents) -> DecodeResult<Self> {
+        if !bytes.starts_with(&MAGIC) {
+            return Err(DecodeError::BadMagic);
+        }
+        let mut decoder = Self {
+            bytes,
+            pos: VERSION_OFFSET,
+            version: 0,
+            depth: 0,
+        };
+        decoder.version = u16::from_le_bytes(decoder.array()?);
+        if decoder.version == 0 || decoder.version > FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion {
+                version: decoder.version,
+                supported: FORMAT_VERSION,
+            });
+        }
+        let found = match decoder.byte()? {
+            0 => Contents::Expr,
+            1 => Contents::Chunk,
+            _ => return Err(decoder.malformed(CONTENTS_OFFSET, "unknown contents")),
+        };
+        if found != expected {
+            return Err(DecodeError::WrongContents {
+                expected: expected.name(),
+                found: found.name(),
+            });
+        }
+        let expected = u32::from_le_bytes(decoder.array()?);
+        let found = crc32(&bytes[HEADER_LEN..]);
+        if found != expected {
+            return Err(DecodeError::ChecksumMismatch { expected, found });
+        }
+        Ok(decoder)
+    }
+
+    fn finish(&self) -> DecodeResult<()> {
+        if self.pos < self.bytes.len() {
+            return Err(DecodeError::TrailingData { offset: self.pos });
+        }
+        Ok(())
+    }
+
+    fn malformed(&self, offset: usize, message: &'static str) -> DecodeError {
+        DecodeError::Malformed { offset, message }
+    }
+
+    fn byte(&mut self) -> DecodeResult<u8> {
+        let byte = *self
+            .bytes
+            .get(self.pos)
+            .ok_or(DecodeError::Truncated { offset: self.pos })?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn array<const N: usize>(&mut self) -> DecodeResult<[u8; N]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or(DecodeError::Truncated {
+                offset: self.bytes.len(),
+            })?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    /// Read a byte, which must be less than limit.
+    fn tag(&mut self, limit: u8) -> DecodeResult<u8> {
+        let start = self.pos;
+        match self.byte()? {
+            tag if tag < limit => Ok(tag),
+            _ => Err(self.malformed(start, "unknown tag")),
+        }
+    }
+
+    /// Read a tag, given the number of tags in each version.
+    fn versioned_tag(&mut self, limits: [u8; FORMAT_VERSION as usize]) -> DecodeResult<u8> {
+        self.tag(limits[self.version as usize - 1])
+    }
+
+    fn bool(&mut self) -> DecodeResult<bool> {
+        Ok(self.tag(2)? == 1)
+    }
+
+    fn uint(&mut self) -> DecodeResult<u64> {
+        let start = self.pos;
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = (byte & 0x7f) as u64;
+            if bits << shift >> shift != bits {
+                break;
+            }
+            n |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(self.malformed(start, "integer too large"))
+    }
+
+    fn int(&mut self) -> DecodeResult<i64> {
+        let n = self.uint()?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+
+    fn float(&mut self) -> DecodeResult<f64> {
+        Ok(f64::from_bits(u64::from_le_bytes(self.array()?)))
+    }
+
+    fn usize(&mut self) -> DecodeResult<usize> {
+        let start = self.pos;
+        usize::try_from(self.uint()?).map_err(|_| self.malformed(start, "integer too large"))
+    }
+
+    fn u16(&mut self) -> DecodeResult<u16> {
+        let start = self.pos;
+        u16::try_from(self.uint()?).map_err(|_| self.malformed(start, "operand too large"))
+    }
+
+    fn u8(&mut self) -> DecodeResult<u8> {
+        let start = self.pos;
+        u8::try_from(self.uint()?).map_err(|_| self.malformed(start, "operand too large"))
+    }
+
+    /// Read a count of items, each taking at least one byte.
+    fn len(&mut self) -> DecodeResult<usize> {
+        let start = self.pos;
+        let len = self.usize()?;
+        if len > self.bytes.len() - self.pos {
+            return Err(self.malformed(start, "count exceeds the remaining data"));
+        }
+        Ok(len)
+    }
+
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> DecodeResult<T>,
+    ) -> DecodeResult<Vec<T>> {
+        let len = self.len()?;
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    fn string(&mut self) -> DecodeResult<String> {
+        let start = self.pos;
+        let len = self.len()?;
+        let bytes = self.bytes[self.pos..self.pos + len].to_vec();
+        self.pos += len;
+        String::from_utf8(bytes).map_err(|_| self.malformed(start, "invalid UTF-8"))
+    }
+
+    /// Start decoding a nested expression, type or function, which ends
+    /// with leave.  An error ends all decoding, so needs no leave.
+    fn enter(&mut self) -> DecodeResult<()> {
+        if self.depth == MAX_NESTING {
+            return Err(self.malformed(self.pos, "nesting too deep"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn span(&mut self) -> DecodeResult<Span> {
+        let start = self.pos;
+        let span = Span {
+            start: self.usize()?,
+            end: self.usize()?,
+            line: self.usize()?,
+            col: self.usize()?,
+        };
+        if span.start > span.end {
+            return Err(self.malformed(start, "span ends before it starts"));
+        }
+        Ok(span)
+    }
+
+    fn ident(&mut self) -> DecodeResult<Ident> {
+        let name = self.string()?;
+        Ok(Ident::new(name, self.span()?))
+    }
+
+    fn option<T>(
+        &mut self,
+        some: impl FnOnce(&mut Self) -> DecodeResult<T>,
+    ) -> DecodeResult<Option<T>> {
+        match self.bool()? {
+            true => some(self).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    fn ttype(&mut self) -> DecodeResult<Type> {
+        self.enter()?;
+        let ttype = match self.versioned_tag(TYPE_TAGS)? {
+            0 => Type::Bool,
+            1 => Type::Int,
+            2 => Type::Unit,
+            3 => {
+                let params = self.list(Self::ttype)?;
+                Type::function(params, self.ttype()?)
+            }
+            4 => Type::Str,
+            _ => Type::Float,
+        };
+        self.leave();
+        Ok(ttype)
+    }
+
+    fn boxed(&mut self) -> DecodeResult<Box<Expr>> {
+        self.expr().map(Box::new)
+    }
+
+    fn expr(&mut self) -> DecodeResult<Expr> {
+        self.enter()?;
+        let kind = match self.versioned_tag(EXPR_TAGS)? {
+            0 => ExprKind::Literal(Literal::Bool(self.bool()?)),
+            1 => ExprKind::Literal(Literal::Int(self.int()?)),
+            2 => ExprKind::Unary {
+                op: UNARY_OPS[self.versioned_tag(UNARY_TAGS)? as usize],
+                right: self.boxed()?,
+            },
+            3 => ExprKind::Binary {
+                left: self.boxed()?,
+                op: BINARY_OPS[self.tag(BINARY_OPS.len() as u8)? as usize],
+                right: self.boxed()?,
+            },
+            4 => ExprKind::Block {
+                contents: self.list(Self::expr)?,
+                trailing_semicolon: self.bool()?,
+            },
+            5 => ExprKind::Var(self.string()?),
+            6 => ExprKind::Let {
+                name: self.ident()?,
+                value: self.boxed()?,
+            },
+            7 => ExprKind::If {
+                condition: self.boxed()?,
+                then_expr: self.boxed()?,
+                else_expr: self.option(Self::boxed)?,
+            },
+            8 => ExprKind::Fn(Function {
+                name: self.option(Self::ident)?,
+                params: self.list(|decoder| {
+                    Ok(Param {
+                        name: decoder.ident()?,
+                        ttype: decoder.ttype()?,
+                    })
+                })?,
+                ret: self.ttype()?,
+                body: self.boxed()?,
+            }),
+            9 => ExprKind::Call {
+                callee: self.boxed()?,
+                args: self.list(Self::expr)?,
+            },
+            10 => ExprKind::Error,
+            11 => ExprKind::Literal(Literal::Str(self.string()?)),
+            12 => ExprKind::Interpolate(self.list(|decoder| {
+                Ok(match decoder.tag(2)? {
+                    0 => StrPart::Text(decoder.string()?),
+                    _ => StrPart::Expr(decoder.expr()?),
+                })
+            })?),
+            _ => ExprKind::Literal(Literal::Float(self.float()?)),
+        };
+        let span = self.span()?;
+        self.leave();
+        Ok(Expr::new(kind, span))
+    }
+
+    /// Read a chunk, which starts with height values on the stack and
+    /// runs in a closure with this many captures.
+    fn chunk(&mut self, height: usize, captures: usize) -> DecodeResult<Chunk> {
+        let start = self.pos;
+        let mut chunk = Chunk::new();
+        let mut operands = Vec::new();
+        for _ in 0..self.len()? {
+            operands.push(self.pos);
+            let op = self.op()?;
+            chunk.emit(op, self.span()?);
+        }
+        chunk.constants = self.list(Self::constant)?;
+
+        for (i, (op, offset)) in chunk.code.iter().zip(&operands).enumerate() {
+            let constant = |index: u16| chunk.constants.get(index as usize);
+            let valid = match *op {
+                Op::Constant(index) => {
+                    matches!(
+                        constant(index),
+                        Some(Constant::Int(_) | Constant::Float(_) | Constant::Str(_))
+                    )
+                }
+                Op::Undefined(index) => matches!(constant(index), Some(Constant::Name(_))),
+                Op::Closure(index) => matches!(constant(index), Some(Constant::Function(_))),
+                Op::Jump(jump) | Op::JumpIfFalse(jump) | Op::JumpIfTrue(jump) => {
+                    i + 1 + (jump as usize) < chunk.code.len()
+                }
+                _ => true,
+            };
+            if !valid {
+                return Err(self.malformed(*offset, "invalid operand"));
+            }
+        }
+        if chunk.code.is_empty() {
+            return Err(self.malformed(start, "missing return"));
+        }
+        self.check_stack(&chunk, &operands, height, captures)?;
+        Ok(chunk)
+    }
+
+    /// Check the height of the stack before each reachable instruction of
+    /// chunk, which must be the same however it is reached, and that each
+    /// instruction's stack values, locals and captures exist.
+    fn check_stack(
+        &self,
+        chunk: &Chunk,
+        operands: &[usize],
+        height: usize,
+        captures: usize,
+    ) -> DecodeResult<()> {
+        let mut heights = vec![None; chunk.code.len()];
+        let mut pending = vec![(0, height)];
+        while let Some((i, height)) = pending.pop() {
+            match heights[i] {
+                Some(known) if known == height => continue,
+                Some(_) => return Err(self.malformed(operands[i], "inconsistent stack height")),
+                None => heights[i] = Some(height),
+            }
+            let op = chunk.code[i];
+            if height < op.stack_inputs() {
+                return Err(self.malformed(operands[i], "stack underflow"));
+            }
+            let valid = match op {
+                Op::GetLocal(slot) => (slot as usize) < height,
+                Op::GetCapture(index) => (index as usize) < captures,
+                Op::Closure(index) => match &chunk.constants[index as usize] {
+                    Constant::Function(prototype) => {
+                        prototype.captures.iter().all(|capture| match *capture {
+                            Capture::Local(slot) => (slot as usize) < height,
+                            Capture::Capture(index) => (index as usize) < captures,
+                        })
+                    }
+                    _ => false,
+                },
+                _ => true,
+            };
+            if !valid {
+                return Err(self.malformed(operands[i], "invalid operand"));
+            }
+            let after = height.wrapping_add_signed(op.stack_effect());
+            let next = match op {
+                Op::Return | Op::Undefined(_) | Op::Unparsed => continue,
+                Op::Jump(jump) => i + 1 + jump as usize,
+                Op::JumpIfFalse(jump) | Op::JumpIfTrue(jump) => {
+                    pending.push((i + 1 + jump as usize, after));
+                    i + 1
+                }
+                _ => i + 1,
+            };
+            if next == chunk.code.len() {
+                return Err(self.malformed(operands[i], "missing return"));
+            }
+            pending.push((next, after));
+        }
+        Ok(())
+    }
+
+    fn constant(&mut self) -> DecodeResult<Constant> {
+        Ok(match self.versioned_tag(CONSTANT_TAGS)? {
+            0 => Constant::Int(self.int()?),
+            1 => Constant::Name(self.string()?),
+            2 => Constant::Function(Rc::new(self.prototype()?)),
+            3 => Constant::Str(self.string()?.into()),
+            _ => Constant::Float(self.float()?),
+        })
+    }
+
+    fn prototype(&mut self) -> DecodeResult<Prototype> {
+        self.enter()?;
+        let name = self.option(Self::string)?;
+        let params = self.list(Self::ttype)?;
+        let ret = self.ttype()?;
+        let captures = self.list(|decoder| {
+            Ok(match decoder.tag(2)? {
+                0 => Capture::Local(decoder.u16()?),
+                _ => Capture::Capture(decoder.u16()?),
+            })
+        })?;
+        // The callee's frame starts with the closure and its arguments.
+        let chunk = self.chunk(1 + params.len(), captures.len())?;
+        self.leave();
+        Ok(Prototype {
+            name,
+            params,
+            ret,
+            captures,
+            chunk: Rc::new(chunk),
+        })
+    }
+
+    fn op(&mut self) -> DecodeResult<Op> {
+        Ok(match self.versioned_tag(OP_TAGS)? {
+            0 => Op::Constant(self.u16()?),
+            1 => Op::True,
+            2 => Op::False,
+            3 => Op::Unit,
+            4 => Op::Pop,
+            5 => Op::PopUnder(self.u16()?),
+            6 => Op::GetLocal(self.u16()?),
+            7 => Op::GetCapture(self.u16()?),
+            8 => Op::Undefined(self.u16()?),
+            9 => Op::Unparsed,
+            10 => Op::CheckBool,
+            11 => Op::CheckInt,
+            12 => Op::Not,
+            13 => Op::Neg,
+            14 => Op::Add,
+            15 => Op::Sub,
+            16 => Op::Mult,
+            17 => Op::Div,
+            18 => Op::Less,
+            19 => Op::LessEq,
+            20 => Op::Great,
+            21 => Op::GreatEq,
+            22 => Op::Equal,
+            23 => Op::Jump(self.u16()?),
+            24 => Op::JumpIfFalse(self.u16()?),
+            25 => Op::JumpIfTrue(self.u16()?),
+            26 => Op::Closure(self.u16()?),
+            27 => Op::CheckCallable,
+            28 => Op::CheckArity(self.u8()?),
+            29 => Op::CheckArg(self.u8()?),
+            30 => Op::Call(self.u8()?),
+            31 => Op::Return,
+            32 => Op::CheckStr,
+            33 => Op::CheckNumberOrStr,
+            34 => Op::CheckSame,
+            35 => Op::Len,
+            36 => Op::Interpolate(self.u16()?),
+            _ => Op::CheckNumber,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
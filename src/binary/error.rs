@@ -0,0 +1,26 @@
+use thiserror::Error;
+
+/// Failure to decode a file in the binary format.
+#[derive(Debug, Error, PartialEq)]
+pub enum DecodeError {
+    #[error("not a carbide binary file")]
+    BadMagic,
+    #[error("unsupported format version {version}; this build reads versions up to {supported}")]
+    UnsupportedVersion { version: u16, supported: u16 },
+    #[error("expected {expected}, but the file holds {found}")]
+    WrongContents {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("checksum mismatch: expected {expected:#010x}, but the data has {found:#010x}")]
+    ChecksumMismatch { expected: u32, found: u32 },
+    #[error("file ends unexpectedly at byte {offset}")]
+    Truncated { offset: usize },
+    #[error("{message} at byte {offset}")]
+    Malformed {
+        offset: usize,
+        message: &'static str,
+    },
+    #[error("unexpected data after the end of the contents at byte {offset}")]
+    TrailingData { offset: usize },
+}
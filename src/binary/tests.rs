@@ -0,0 +1,296 @@
+use std::rc::Rc;
+
+use super::*;
+use crate::compile::compile;
+use crate::eval::RuntimeError;
+use crate::parse::parse_expr;
+use crate::parse::parse_expr_recovering;
+use crate::test_util::CORPUS;
+use crate::vm;
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc32(b""), 0);
+}
+
+#[test]
+fn test_header() {
+    let bytes = encode_expr(&Expr::int(-3));
+    assert_eq!(bytes[..4], MAGIC);
+    assert_eq!(bytes[4..6], FORMAT_VERSION.to_le_bytes());
+    assert_eq!(bytes[6], 0);
+    // Int tag, zigzag -3, and the default span.
+    assert_eq!(bytes[HEADER_LEN..], [1, 5, 0, 0, 0, 0]);
+}
+
+#[test]
+fn test_expr_round_trips() {
+    for source in CORPUS {
+        let (expr, _) = parse_expr_recovering(source);
+        let decoded = decode_expr(&encode_expr(&expr));
+        assert_eq!(decoded.as_ref(), Ok(&expr), "for {source:?}");
+    }
+
+    for i in [0, 1, -1, 63, -64, 64, i64::MAX, i64::MIN] {
+        let expr = Expr::int(i);
+        assert_eq!(decode_expr(&encode_expr(&expr)), Ok(expr));
+    }
+}
+
+#[test]
+fn test_chunk_round_trips() {
+    let mut count = 0;
+    for source in CORPUS {
+        let Ok(chunk) = parse_expr(source).map(|expr| compile(&expr).unwrap()) else {
+            continue;
+        };
+        let decoded = decode_chunk(&encode_chunk(&chunk)).unwrap();
+        assert_eq!(decoded, chunk, "for {source:?}");
+        // Closures compare by identity, so compare values as printed.
+        let show = |chunk| vm::run(&Rc::new(chunk)).map(|value| value.to_string());
+        assert_eq!(show(decoded), show(chunk), "for {source:?}");
+        count += 1;
+    }
+    assert!(count > 100);
+}
+
+#[test]
+fn test_header_errors() {
+    let bytes = encode_expr(&parse_expr("1 + x").unwrap());
+    assert_eq!(decode_expr(b"carbide"), Err(DecodeError::BadMagic));
+    assert_eq!(
+        decode_chunk(&bytes),
+        Err(DecodeError::WrongContents {
+            expected: "bytecode",
+            found: "an expression"
+        })
+    );
+
+    let mut newer = bytes.clone();
+    newer[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    let error = decode_expr(&newer).unwrap_err();
+    assert_eq!(
+        error,
+        DecodeError::UnsupportedVersion {
+            version: FORMAT_VERSION + 1,
+            supported: FORMAT_VERSION
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "unsupported format version 4; this build reads versions up to 3"
+    );
+
+    let mut corrupt = bytes.clone();
+    *corrupt.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        decode_expr(&corrupt),
+        Err(DecodeError::ChecksumMismatch { .. })
+    ));
+    assert_eq!(
+        decode_expr(&bytes[..8]),
+        Err(DecodeError::Truncated { offset: 8 })
+    );
+}
+
+/// Encode a payload with a valid header.
+fn with_header(contents: Contents, payload: Vec<u8>) -> Vec<u8> {
+    Encoder { payload }.finish(contents)
+}
+
+#[test]
+fn test_payload_errors() {
+    assert_eq!(
+        decode_expr(&with_header(Contents::Expr, vec![1, 2])),
+        Err(DecodeError::Truncated { offset: 13 })
+    );
+    assert_eq!(
+        decode_expr(&with_header(Contents::Expr, vec![14])),
+        Err(DecodeError::Malformed {
+            offset: 11,
+            message: "unknown tag"
+        })
+    );
+    assert_eq!(
+        decode_expr(&with_header(Contents::Expr, vec![10, 0, 0, 0, 0, 0])),
+        Err(DecodeError::TrailingData { offset: 16 })
+    );
+    assert_eq!(
+        decode_expr(&with_header(Contents::Expr, vec![5, 100, b'x'])),
+        Err(DecodeError::Malformed {
+            offset: 12,
+            message: "count exceeds the remaining data"
+        })
+    );
+    assert_eq!(
+        decode_expr(&with_header(
+            Contents::Expr,
+            vec![1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]
+        )),
+        Err(DecodeError::Malformed {
+            offset: 12,
+            message: "integer too large"
+        })
+    );
+    assert_eq!(
+        decode_expr(&with_header(Contents::Expr, vec![10, 5, 3, 0, 0])),
+        Err(DecodeError::Malformed {
+            offset: 12,
+            message: "span ends before it starts"
+        })
+    );
+
+    // Negations of 0, nested depth deep.
+    let negations = |depth| {
+        let mut payload = [2, 1].repeat(depth - 1);
+        payload.extend([1, 0]);
+        payload.extend([0, 0, 0, 0].repeat(depth));
+        with_header(Contents::Expr, payload)
+    };
+    assert!(decode_expr(&negations(MAX_NESTING)).is_ok());
+    for depth in [MAX_NESTING + 1, 100_000] {
+        assert_eq!(
+            decode_expr(&negations(depth)),
+            Err(DecodeError::Malformed {
+                offset: 11 + 2 * MAX_NESTING,
+                message: "nesting too deep"
+            })
+        );
+    }
+
+    // Constant(0), with no constants.
+    let mut chunk = Chunk::new();
+    chunk.emit(Op::Constant(0), Span::default());
+    chunk.emit(Op::Return, Span::default());
+    assert_eq!(
+        decode_chunk(&encode_chunk(&chunk)),
+        Err(DecodeError::Malformed {
+            offset: 12,
+            message: "invalid operand"
+        })
+    );
+    chunk.code[0] = Op::Jump(1);
+    assert_eq!(
+        decode_chunk(&encode_chunk(&chunk)),
+        Err(DecodeError::Malformed {
+            offset: 12,
+            message: "invalid operand"
+        })
+    );
+    chunk.code[0] = Op::Jump(0);
+    assert_eq!(
+        decode_chunk(&encode_chunk(&chunk)),
+        Err(DecodeError::Malformed {
+            offset: 18,
+            message: "stack underflow"
+        })
+    );
+}
+
+#[test]
+fn test_version_tags() {
+    let mut bytes = encode_expr(&Expr::new(
+        ExprKind::Literal(Literal::Str("s".to_owned())),
+        Span::default(),
+    ));
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    assert_eq!(
+        decode_expr(&bytes),
+        Err(DecodeError::Malformed {
+            offset: 11,
+            message: "unknown tag"
+        })
+    );
+
+    let mut bytes = encode_expr(&Expr::float(1.5));
+    bytes[4..6].copy_from_slice(&2u16.to_le_bytes());
+    assert_eq!(
+        decode_expr(&bytes),
+        Err(DecodeError::Malformed {
+            offset: 11,
+            message: "unknown tag"
+        })
+    );
+    let mut bytes = encode_expr(&Expr::unary(UnaryOp::Len, Expr::var("s")));
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    assert_eq!(
+        decode_expr(&bytes),
+        Err(DecodeError::Malformed {
+            offset: 12,
+            message: "unknown tag"
+        })
+    );
+    let mut bytes = encode_expr(&Expr::unary(UnaryOp::Neg, Expr::var("x")));
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    assert_eq!(
+        decode_expr(&bytes),
+        Ok(Expr::unary(UnaryOp::Neg, Expr::var("x")))
+    );
+    // Ints read the same in every version.
+    let mut bytes = encode_expr(&Expr::int(7));
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    assert_eq!(decode_expr(&bytes), Ok(Expr::int(7)));
+
+    let mut chunk = Chunk::new();
+    chunk.emit(Op::Unit, Span::default());
+    chunk.emit(Op::CheckStr, Span::default());
+    chunk.emit(Op::Return, Span::default());
+    let mut bytes = encode_chunk(&chunk);
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    assert_eq!(
+        decode_chunk(&bytes),
+        Err(DecodeError::Malformed {
+            offset: 17,
+            message: "unknown tag"
+        })
+    );
+}
+
+/// Encode and decode a chunk of ops with no constants.
+fn decode_ops(ops: &[Op]) -> DecodeResult<Chunk> {
+    let mut chunk = Chunk::new();
+    for op in ops {
+        chunk.emit(*op, Span::default());
+    }
+    decode_chunk(&encode_chunk(&chunk))
+}
+
+#[test]
+fn test_chunk_errors() {
+    let malformed = |offset, message| Err(DecodeError::Malformed { offset, message });
+    assert_eq!(
+        decode_ops(&[Op::GetLocal(5), Op::Return]),
+        malformed(12, "invalid operand")
+    );
+    assert!(decode_ops(&[Op::Unit, Op::GetLocal(0), Op::Return]).is_ok());
+    assert_eq!(
+        decode_ops(&[Op::GetCapture(0), Op::Return]),
+        malformed(12, "invalid operand")
+    );
+    assert_eq!(
+        decode_ops(&[Op::Unit, Op::PopUnder(1), Op::Return]),
+        malformed(17, "stack underflow")
+    );
+    assert_eq!(
+        decode_ops(&[Op::Unit, Op::Interpolate(2), Op::Return]),
+        malformed(17, "stack underflow")
+    );
+    // The two ways to reach the Return leave different heights.
+    assert_eq!(
+        decode_ops(&[Op::True, Op::JumpIfFalse(1), Op::Unit, Op::Return]),
+        malformed(28, "inconsistent stack height")
+    );
+    assert_eq!(decode_ops(&[Op::Unit]), malformed(12, "missing return"));
+    assert_eq!(decode_ops(&[]), malformed(11, "missing return"));
+
+    // Calls without the checks compiled code makes fail when run.
+    let chunk = decode_ops(&[Op::Unit, Op::Call(0), Op::Return]).unwrap();
+    assert_eq!(
+        vm::run(&Rc::new(chunk)).map(|value| value.to_string()),
+        Err(RuntimeError::NotCallable {
+            found: Type::Unit,
+            span: Span::default(),
+        })
+    );
+}
@@ -0,0 +1,460 @@
+mod chunk;
+mod disassemble;
+mod error;
+
+use std::rc::Rc;
+
+pub use chunk::Capture;
+pub use chunk::Chunk;
+pub use chunk::Constant;
+pub use chunk::Op;
+pub use chunk::Prototype;
+pub use disassemble::disassemble;
+pub use error::CompileError;
+
+use crate::ast::expr::BinaryOp;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Function;
+use crate::ast::expr::Literal;
+use crate::ast::expr::StrPart;
+use crate::ast::expr::UnaryOp;
+use crate::span::Span;
+use crate::ttype::Type;
+
+pub type CompileResult<T> = Result<T, CompileError>;
+
+/// Compile an expression to bytecode for the vm.
+/// The chunk returns the value of the expression.
+pub fn compile(expr: &Expr) -> CompileResult<Chunk> {
+    let mut compiler = Compiler {
+        functions: vec![FunctionState::default()],
+    };
+    compiler.expr(expr)?;
+    compiler.emit(Op::Return, expr.span);
+    Ok(compiler.functions.pop().unwrap().chunk)
+}
+
+/// Binding in scope, with the stack slot holding its value.
+struct Local {
+    name: String,
+    slot: u16,
+}
+
+/// Compilation state of a function literal, or the top-level expression.
+#[derive(Default)]
+struct FunctionState {
+    chunk: Chunk,
+    /// Bindings in scope, innermost last.
+    locals: Vec<Local>,
+    /// Variables captured from enclosing functions, in capture index order.
+    captures: Vec<Capture>,
+    /// Height of the stack, relative to the base of the function's frame.
+    depth: usize,
+}
+
+/// Where the compiled code finds the value of a variable.
+enum Access {
+    Local(u16),
+    Capture(u16),
+}
+
+/// Compiler from Expr to Chunk.
+/// The checks the evaluator makes as it goes are compiled to check
+/// instructions, so that the vm fails with the same RuntimeError.
+struct Compiler {
+    /// Functions being compiled, innermost last.
+    functions: Vec<FunctionState>,
+}
+
+impl Compiler {
+    /// Compile expr to instructions that push its value.
+    fn expr(&mut self, expr: &Expr) -> CompileResult<()> {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Literal(Literal::Bool(b)) => {
+                self.emit(if *b { Op::True } else { Op::False }, span);
+            }
+            ExprKind::Literal(Literal::Int(i)) => {
+                let index = self.chunk().add_constant(Constant::Int(*i), span)?;
+                self.emit(Op::Constant(index), span);
+            }
+            ExprKind::Literal(Literal::Float(x)) => {
+                let index = self.chunk().add_constant(Constant::Float(*x), span)?;
+                self.emit(Op::Constant(index), span);
+            }
+            ExprKind::Literal(Literal::Str(s)) => self.string(span, s)?,
+            ExprKind::Unary { op, right } => self.unary(span, *op, right)?,
+            ExprKind::Binary { left, op, right } => self.binary(span, left, *op, right)?,
+            ExprKind::Block {
+                contents,
+                trailing_semicolon,
+            } => self.block(span, contents, *trailing_semicolon)?,
+            ExprKind::Var(name) => self.variable(span, name)?,
+            // Only a let directly in a block binds a name for later expressions.
+            ExprKind::Let { value, .. } => {
+                self.expr(value)?;
+                self.emit(Op::Pop, span);
+                self.emit(Op::Unit, span);
+            }
+            ExprKind::If {
+                condition,
+                then_expr,
+                else_expr,
+            } => self.if_(span, condition, then_expr, else_expr.as_deref())?,
+            ExprKind::Fn(function) => self.function(span, function)?,
+            ExprKind::Call { callee, args } => self.call(span, callee, args)?,
+            ExprKind::Interpolate(parts) => self.interpolate(span, parts)?,
+            ExprKind::Error => {
+                self.emit(Op::Unparsed, span);
+            }
+        };
+        Ok(())
+    }
+
+    fn unary(&mut self, span: Span, op: UnaryOp, right: &Expr) -> CompileResult<()> {
+        match op {
+            UnaryOp::Not => {
+                self.operand(right, Type::Bool)?;
+                self.emit(Op::Not, span);
+            }
+            UnaryOp::Neg => {
+                self.number(right)?;
+                self.emit(Op::Neg, span);
+            }
+            UnaryOp::Pos => self.number(right)?,
+            UnaryOp::Len => {
+                self.operand(right, Type::Str)?;
+                self.emit(Op::Len, span);
+            }
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, span: Span, left: &Expr, op: BinaryOp, right: &Expr) -> CompileResult<()> {
+        let op = match op {
+            // And/Or short-circuit, by jumping over the right side with the left value.
+            BinaryOp::And | BinaryOp::Or => {
+                self.operand(left, Type::Bool)?;
+                let jump = match op {
+                    BinaryOp::And => self.emit(Op::JumpIfFalse(0), span),
+                    _ => self.emit(Op::JumpIfTrue(0), span),
+                };
+                self.emit(Op::Pop, span);
+                self.operand(right, Type::Bool)?;
+                return self.chunk().patch_jump(jump);
+            }
+            // A type mismatch in an equality is reported at the right side.
+            BinaryOp::Equal | BinaryOp::NotEqual => {
+                self.expr(left)?;
+                self.expr(right)?;
+                self.emit(Op::Equal, right.span);
+                if op == BinaryOp::NotEqual {
+                    self.emit(Op::Not, span);
+                }
+                return Ok(());
+            }
+            BinaryOp::Less => Op::Less,
+            BinaryOp::LessEq => Op::LessEq,
+            BinaryOp::Great => Op::Great,
+            BinaryOp::GreatEq => Op::GreatEq,
+            BinaryOp::Add => Op::Add,
+            BinaryOp::Sub => Op::Sub,
+            BinaryOp::Mult => Op::Mult,
+            BinaryOp::Div => Op::Div,
+        };
+        // The left side is a number, or a str for `+` and comparisons, and
+        // the right the same.
+        self.expr(left)?;
+        let left_type = static_type(left);
+        match op {
+            Op::Sub | Op::Mult | Op::Div => {
+                if !matches!(left_type, Some(Type::Int | Type::Float)) {
+                    self.emit(Op::CheckNumber, left.span);
+                }
+            }
+            _ => {
+                if !matches!(left_type, Some(Type::Int | Type::Float | Type::Str)) {
+                    self.emit(Op::CheckNumberOrStr, left.span);
+                }
+            }
+        }
+        self.expr(right)?;
+        if left_type.is_none() || static_type(right) != left_type {
+            self.emit(Op::CheckSame, right.span);
+        }
+        self.emit(op, span);
+        Ok(())
+    }
+
+    /// Compile expr, checking that its value is a number unless that is
+    /// evident from the expression itself.
+    fn number(&mut self, expr: &Expr) -> CompileResult<()> {
+        self.expr(expr)?;
+        if !matches!(static_type(expr), Some(Type::Int | Type::Float)) {
+            self.emit(Op::CheckNumber, expr.span);
+        }
+        Ok(())
+    }
+
+    /// Compile expr, checking that its value has the given type unless that
+    /// is evident from the expression itself.
+    fn operand(&mut self, expr: &Expr, ttype: Type) -> CompileResult<()> {
+        self.expr(expr)?;
+        if static_type(expr).as_ref() != Some(&ttype) {
+            let check = match ttype {
+                Type::Bool => Op::CheckBool,
+                Type::Str => Op::CheckStr,
+                _ => Op::CheckInt,
+            };
+            self.emit(check, expr.span);
+        }
+        Ok(())
+    }
+
+    fn string(&mut self, span: Span, s: &str) -> CompileResult<()> {
+        let index = self.chunk().add_constant(Constant::Str(s.into()), span)?;
+        self.emit(Op::Constant(index), span);
+        Ok(())
+    }
+
+    fn interpolate(&mut self, span: Span, parts: &[StrPart]) -> CompileResult<()> {
+        let count =
+            u16::try_from(parts.len()).map_err(|_| CompileError::TooManyInterpolations { span })?;
+        for part in parts {
+            match part {
+                StrPart::Text(text) => self.string(span, text)?,
+                StrPart::Expr(expr) => self.expr(expr)?,
+            }
+        }
+        self.emit(Op::Interpolate(count), span);
+        Ok(())
+    }
+
+    // Each let leaves its value on the stack, in the slot for the binding.
+    // Bindings go out of scope at the end of the block, and are popped from
+    // under the block's value.
+    fn block(
+        &mut self,
+        span: Span,
+        contents: &[Expr],
+        trailing_semicolon: bool,
+    ) -> CompileResult<()> {
+        let scope = self.current().locals.len();
+        let mut unit = true;
+        for (i, expr) in contents.iter().enumerate() {
+            if i > 0 {
+                self.emit(Op::Pop, span);
+            }
+            match &expr.kind {
+                ExprKind::Let { name, value } => {
+                    self.expr(value)?;
+                    self.bind(&name.name, name.span)?;
+                    self.emit(Op::Unit, expr.span);
+                    unit = true;
+                }
+                _ => {
+                    self.expr(expr)?;
+                    unit = false;
+                }
+            }
+        }
+        if contents.is_empty() {
+            self.emit(Op::Unit, span);
+        } else if trailing_semicolon && !unit {
+            self.emit(Op::Pop, span);
+            self.emit(Op::Unit, span);
+        }
+
+        let function = self.current();
+        let count = function.locals.len() - scope;
+        function.locals.truncate(scope);
+        if count > 0 {
+            // There are fewer bindings than slots, so the count fits.
+            self.emit(Op::PopUnder(count as u16), span);
+        }
+        Ok(())
+    }
+
+    /// Bind name to the value on top of the stack.
+    fn bind(&mut self, name: &str, span: Span) -> CompileResult<()> {
+        let function = self.current();
+        let slot =
+            u16::try_from(function.depth - 1).map_err(|_| CompileError::TooManyLocals { span })?;
+        function.locals.push(Local {
+            name: name.to_owned(),
+            slot,
+        });
+        Ok(())
+    }
+
+    // An undefined variable is not an error until it is evaluated.
+    fn variable(&mut self, span: Span, name: &str) -> CompileResult<()> {
+        let level = self.functions.len() - 1;
+        let op = match self.resolve(level, name, span)? {
+            Some(Access::Local(slot)) => Op::GetLocal(slot),
+            Some(Access::Capture(index)) => Op::GetCapture(index),
+            None => {
+                let name = Constant::Name(name.to_owned());
+                Op::Undefined(self.chunk().add_constant(name, span)?)
+            }
+        };
+        self.emit(op, span);
+        Ok(())
+    }
+
+    /// Find the binding for name in the function at level, capturing it
+    /// from enclosing functions if needed.
+    fn resolve(&mut self, level: usize, name: &str, span: Span) -> CompileResult<Option<Access>> {
+        let function = &self.functions[level];
+        if let Some(local) = function
+            .locals
+            .iter()
+            .rev()
+            .find(|local| local.name == name)
+        {
+            return Ok(Some(Access::Local(local.slot)));
+        }
+        if level == 0 {
+            return Ok(None);
+        }
+        let capture = match self.resolve(level - 1, name, span)? {
+            Some(Access::Local(slot)) => Capture::Local(slot),
+            Some(Access::Capture(index)) => Capture::Capture(index),
+            None => return Ok(None),
+        };
+        let captures = &mut self.functions[level].captures;
+        let index = match captures.iter().position(|c| *c == capture) {
+            Some(index) => index,
+            None => {
+                captures.push(capture);
+                captures.len() - 1
+            }
+        };
+        let index = u16::try_from(index).map_err(|_| CompileError::TooManyCaptures { span })?;
+        Ok(Some(Access::Capture(index)))
+    }
+
+    // The condition is left on the stack by the jumps, and popped in each branch.
+    fn if_(
+        &mut self,
+        span: Span,
+        condition: &Expr,
+        then_expr: &Expr,
+        else_expr: Option<&Expr>,
+    ) -> CompileResult<()> {
+        self.operand(condition, Type::Bool)?;
+        let else_jump = self.emit(Op::JumpIfFalse(0), span);
+        self.emit(Op::Pop, span);
+        self.expr(then_expr)?;
+        if else_expr.is_none() {
+            self.emit(Op::Pop, span);
+            self.emit(Op::Unit, span);
+        }
+        let end_jump = self.emit(Op::Jump(0), span);
+
+        // The else branch starts with the condition in place of the then value.
+        self.chunk().patch_jump(else_jump)?;
+        self.emit(Op::Pop, span);
+        match else_expr {
+            Some(else_expr) => self.expr(else_expr)?,
+            None => {
+                self.emit(Op::Unit, span);
+            }
+        }
+        self.chunk().patch_jump(end_jump)
+    }
+
+    // Slot 0 of the function's frame holds the closure being called, so that
+    // a named function can refer to itself.  The arguments follow.
+    fn function(&mut self, span: Span, function: &Function) -> CompileResult<()> {
+        if function.params.len() > u8::MAX as usize {
+            return Err(CompileError::TooManyArguments { span });
+        }
+        let name = function.name.as_ref().map(|name| name.name.clone());
+        let mut state = FunctionState {
+            depth: 1,
+            ..FunctionState::default()
+        };
+        state.locals.push(Local {
+            name: name.clone().unwrap_or_default(),
+            slot: 0,
+        });
+        self.functions.push(state);
+        for param in &function.params {
+            self.current().depth += 1;
+            self.bind(&param.name.name, param.name.span)?;
+        }
+        self.expr(&function.body)?;
+        self.emit(Op::Return, function.body.span);
+        let state = self.functions.pop().unwrap();
+
+        let prototype = Prototype {
+            name,
+            params: function.params.iter().map(|p| p.ttype.clone()).collect(),
+            ret: function.ret.clone(),
+            captures: state.captures,
+            chunk: Rc::new(state.chunk),
+        };
+        let constant = Constant::Function(Rc::new(prototype));
+        let index = self.chunk().add_constant(constant, span)?;
+        self.emit(Op::Closure(index), span);
+        Ok(())
+    }
+
+    // The callee is checked before the arguments are evaluated, and each
+    // argument as it is evaluated, as in the evaluator.
+    fn call(&mut self, span: Span, callee: &Expr, args: &[Expr]) -> CompileResult<()> {
+        let argc = u8::try_from(args.len()).map_err(|_| CompileError::TooManyArguments { span })?;
+        self.expr(callee)?;
+        self.emit(Op::CheckCallable, callee.span);
+        self.emit(Op::CheckArity(argc), span);
+        for (i, arg) in args.iter().enumerate() {
+            self.expr(arg)?;
+            self.emit(Op::CheckArg(i as u8), arg.span);
+        }
+        self.emit(Op::Call(argc), span);
+        Ok(())
+    }
+
+    /// Append an instruction to the current function, tracking the stack height.
+    fn emit(&mut self, op: Op, span: Span) -> usize {
+        let function = self.current();
+        function.depth = function.depth.wrapping_add_signed(op.stack_effect());
+        function.chunk.emit(op, span)
+    }
+
+    fn current(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().unwrap()
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.current().chunk
+    }
+}
+
+/// Type of expr if evident without checking its subexpressions, since
+/// the operator would fail otherwise.
+fn static_type(expr: &Expr) -> Option<Type> {
+    match &expr.kind {
+        ExprKind::Literal(Literal::Bool(_)) => Some(Type::Bool),
+        ExprKind::Literal(Literal::Int(_)) => Some(Type::Int),
+        ExprKind::Literal(Literal::Float(_)) => Some(Type::Float),
+        ExprKind::Literal(Literal::Str(_)) | ExprKind::Interpolate(_) => Some(Type::Str),
+        ExprKind::Unary { op, right } => match op {
+            UnaryOp::Not => Some(Type::Bool),
+            UnaryOp::Len => Some(Type::Int),
+            // The type of the operand.
+            UnaryOp::Neg | UnaryOp::Pos => static_type(right),
+        },
+        ExprKind::Binary { left, op, .. } => match op {
+            // The type of the left operand, which the right must share.
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mult | BinaryOp::Div => static_type(left),
+            _ => Some(Type::Bool),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,293 @@
+use std::rc::Rc;
+
+use super::error::CompileError;
+use super::CompileResult;
+use crate::span::Span;
+use crate::ttype::Type;
+
+/// Bytecode instruction for the vm.
+/// Operands are stack slots relative to the current frame, constant and
+/// capture indexes, argument counts, and forward jump offsets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// Push the constant at the index, which must be an Int, Float or Str.
+    Constant(u16),
+    True,
+    False,
+    Unit,
+    Pop,
+    /// Remove this many values from under the top of the stack.
+    /// This drops a block's bindings, keeping its value.
+    PopUnder(u16),
+    GetLocal(u16),
+    GetCapture(u16),
+    /// Fail with an undefined variable, named by the constant at the index.
+    Undefined(u16),
+    /// Fail on an expression that could not be parsed.
+    Unparsed,
+
+    /// Fail unless the top of the stack is a Bool.
+    CheckBool,
+    /// Fail unless the top of the stack is an Int.
+    CheckInt,
+    /// Fail unless the top of the stack is a Str.
+    CheckStr,
+    /// Fail unless the top of the stack is an Int or a Float, as the
+    /// operand of a negation or the left operand of `-`, `*` or `/` must be.
+    CheckNumber,
+    /// Fail unless the top of the stack is an Int, Float or Str, as the
+    /// left operand of `+` or a comparison must be.
+    CheckNumberOrStr,
+    /// Fail unless the top of the stack has the type of the value under it.
+    CheckSame,
+    Not,
+    Neg,
+    Add,
+    Sub,
+    Mult,
+    Div,
+    Less,
+    LessEq,
+    Great,
+    GreatEq,
+    Equal,
+    /// Replace the Str on top of the stack with its number of characters.
+    Len,
+    /// Replace this many values with the concatenation of their text, as
+    /// they display.
+    Interpolate(u16),
+
+    /// Jump forward by the offset, from the next instruction.
+    Jump(u16),
+    /// Jump if the top of the stack is false.  This does not pop the condition.
+    JumpIfFalse(u16),
+    /// Jump if the top of the stack is true.  This does not pop the condition.
+    JumpIfTrue(u16),
+
+    /// Push a closure over the function prototype constant at the index.
+    Closure(u16),
+    /// Fail unless the top of the stack is a closure.
+    CheckCallable,
+    /// Fail unless the closure on top of the stack takes this many arguments.
+    CheckArity(u8),
+    /// Fail unless the top of the stack has the type of the parameter at the
+    /// index, for the closure under the arguments.
+    CheckArg(u8),
+    /// Call the closure under this many arguments.
+    Call(u8),
+    /// Return the top of the stack from the current function.
+    Return,
+}
+
+impl Op {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Constant(_) => "Constant",
+            Op::True => "True",
+            Op::False => "False",
+            Op::Unit => "Unit",
+            Op::Pop => "Pop",
+            Op::PopUnder(_) => "PopUnder",
+            Op::GetLocal(_) => "GetLocal",
+            Op::GetCapture(_) => "GetCapture",
+            Op::Undefined(_) => "Undefined",
+            Op::Unparsed => "Unparsed",
+            Op::CheckBool => "CheckBool",
+            Op::CheckInt => "CheckInt",
+            Op::CheckStr => "CheckStr",
+            Op::CheckNumber => "CheckNumber",
+            Op::CheckNumberOrStr => "CheckNumberOrStr",
+            Op::CheckSame => "CheckSame",
+            Op::Not => "Not",
+            Op::Neg => "Neg",
+            Op::Add => "Add",
+            Op::Sub => "Sub",
+            Op::Mult => "Mult",
+            Op::Div => "Div",
+            Op::Less => "Less",
+            Op::LessEq => "LessEq",
+            Op::Great => "Great",
+            Op::GreatEq => "GreatEq",
+            Op::Equal => "Equal",
+            Op::Len => "Len",
+            Op::Interpolate(_) => "Interpolate",
+            Op::Jump(_) => "Jump",
+            Op::JumpIfFalse(_) => "JumpIfFalse",
+            Op::JumpIfTrue(_) => "JumpIfTrue",
+            Op::Closure(_) => "Closure",
+            Op::CheckCallable => "CheckCallable",
+            Op::CheckArity(_) => "CheckArity",
+            Op::CheckArg(_) => "CheckArg",
+            Op::Call(_) => "Call",
+            Op::Return => "Return",
+        }
+    }
+
+    /// Values the instruction reads from the top of the stack, which must
+    /// be at least this high.
+    pub fn stack_inputs(&self) -> usize {
+        match self {
+            Op::Constant(_)
+            | Op::True
+            | Op::False
+            | Op::Unit
+            | Op::GetLocal(_)
+            | Op::GetCapture(_)
+            | Op::Closure(_)
+            | Op::Undefined(_)
+            | Op::Unparsed
+            | Op::Jump(_) => 0,
+            Op::Pop
+            | Op::CheckBool
+            | Op::CheckInt
+            | Op::CheckStr
+            | Op::CheckNumber
+            | Op::CheckNumberOrStr
+            | Op::Len
+            | Op::Not
+            | Op::Neg
+            | Op::JumpIfFalse(_)
+            | Op::JumpIfTrue(_)
+            | Op::CheckCallable
+            | Op::CheckArity(_)
+            | Op::Return => 1,
+            Op::CheckSame
+            | Op::Add
+            | Op::Sub
+            | Op::Mult
+            | Op::Div
+            | Op::Less
+            | Op::LessEq
+            | Op::Great
+            | Op::GreatEq
+            | Op::Equal => 2,
+            Op::PopUnder(count) => *count as usize + 1,
+            Op::Interpolate(count) => *count as usize,
+            // The closure under the arguments up to this one.
+            Op::CheckArg(index) => *index as usize + 2,
+            Op::Call(argc) => *argc as usize + 1,
+        }
+    }
+
+    /// Net change in the height of the stack from executing this instruction.
+    pub fn stack_effect(&self) -> isize {
+        match self {
+            Op::Constant(_)
+            | Op::True
+            | Op::False
+            | Op::Unit
+            | Op::GetLocal(_)
+            | Op::GetCapture(_)
+            | Op::Closure(_) => 1,
+            // These always fail, but stand in for the value of an expression.
+            Op::Undefined(_) | Op::Unparsed => 1,
+            Op::Pop
+            | Op::Add
+            | Op::Sub
+            | Op::Mult
+            | Op::Div
+            | Op::Less
+            | Op::LessEq
+            | Op::Great
+            | Op::GreatEq
+            | Op::Equal => -1,
+            Op::PopUnder(count) => -(*count as isize),
+            Op::Call(argc) => -(*argc as isize),
+            Op::Interpolate(count) => 1 - *count as isize,
+            Op::CheckBool
+            | Op::CheckInt
+            | Op::CheckStr
+            | Op::CheckNumber
+            | Op::CheckNumberOrStr
+            | Op::CheckSame
+            | Op::Len
+            | Op::Not
+            | Op::Neg
+            | Op::Jump(_)
+            | Op::JumpIfFalse(_)
+            | Op::JumpIfTrue(_)
+            | Op::CheckCallable
+            | Op::CheckArity(_)
+            | Op::CheckArg(_)
+            | Op::Return => 0,
+        }
+    }
+}
+
+/// Constant referred to by an instruction operand.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Str(Rc<str>),
+    Name(String),
+    Function(Rc<Prototype>),
+}
+
+/// Compiled function literal, from which the vm makes closures.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prototype {
+    pub name: Option<String>,
+    pub params: Vec<Type>,
+    pub ret: Type,
+    /// Where each captured value comes from, in the enclosing frame.
+    pub captures: Vec<Capture>,
+    pub chunk: Rc<Chunk>,
+}
+
+impl Prototype {
+    pub fn ttype(&self) -> Type {
+        Type::function(self.params.clone(), self.ret.clone())
+    }
+}
+
+/// Source of a value captured by a closure when it is made.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Capture {
+    Local(u16),
+    Capture(u16),
+}
+
+/// Instructions for one function, with the span of the expression each
+/// instruction was compiled from and the constants they refer to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub spans: Vec<Span>,
+    pub constants: Vec<Constant>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an instruction, returning its index.
+    pub fn emit(&mut self, op: Op, span: Span) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    pub fn add_constant(&mut self, constant: Constant, span: Span) -> CompileResult<u16> {
+        let index = self.constants.len();
+        let index = u16::try_from(index).map_err(|_| CompileError::TooManyConstants { span })?;
+        self.constants.push(constant);
+        Ok(index)
+    }
+
+    /// Set the offset of the jump at index so that it jumps to the next
+    /// instruction emitted.
+    pub fn patch_jump(&mut self, index: usize) -> CompileResult<()> {
+        let span = self.spans[index];
+        let offset = self.code.len() - index - 1;
+        let offset = u16::try_from(offset).map_err(|_| CompileError::JumpTooFar { span })?;
+        self.code[index] = match self.code[index] {
+            Op::Jump(_) => Op::Jump(offset),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(offset),
+            Op::JumpIfTrue(_) => Op::JumpIfTrue(offset),
+            op => panic!("cannot patch non-jump instruction {op:?}"),
+        };
+        Ok(())
+    }
+}
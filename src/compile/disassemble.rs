@@ -0,0 +1,81 @@
+use std::fmt::Write;
+
+use super::Capture;
+use super::Chunk;
+use super::Constant;
+use super::Op;
+use crate::parse::string::escape_text;
+
+/// List the instructions of chunk, one per line, with the source position
+/// each was compiled from.  The chunks of functions defined in chunk follow.
+pub fn disassemble(chunk: &Chunk, name: &str) -> String {
+    let mut output = String::new();
+    write_chunk(&mut output, chunk, name);
+    output
+}
+
+fn write_chunk(output: &mut String, chunk: &Chunk, name: &str) {
+    writeln!(output, "== {name} ==").unwrap();
+    for (index, (op, span)) in chunk.code.iter().zip(&chunk.spans).enumerate() {
+        let position = span.to_string();
+        write!(output, "{index:04} {position:>6}  ").unwrap();
+        writeln!(output, "{}", describe(chunk, index, *op)).unwrap();
+    }
+
+    for constant in &chunk.constants {
+        if let Constant::Function(prototype) = constant {
+            output.push('\n');
+            let name = prototype.name.as_deref().unwrap_or("<fn>");
+            write_chunk(output, &prototype.chunk, name);
+        }
+    }
+}
+
+/// Instruction name and operands, with the constants and jump targets they refer to.
+fn describe(chunk: &Chunk, index: usize, op: Op) -> String {
+    let name = op.name();
+    match op {
+        Op::Constant(i) | Op::Undefined(i) | Op::Closure(i) => {
+            let constant = describe_constant(&chunk.constants[i as usize]);
+            format!("{name:<14}{i} ({constant})")
+        }
+        Op::Jump(offset) | Op::JumpIfFalse(offset) | Op::JumpIfTrue(offset) => {
+            let target = index + 1 + offset as usize;
+            format!("{name:<14}{offset} -> {target:04}")
+        }
+        Op::PopUnder(operand)
+        | Op::GetLocal(operand)
+        | Op::GetCapture(operand)
+        | Op::Interpolate(operand) => {
+            format!("{name:<14}{operand}")
+        }
+        Op::CheckArity(operand) | Op::CheckArg(operand) | Op::Call(operand) => {
+            format!("{name:<14}{operand}")
+        }
+        _ => name.to_owned(),
+    }
+}
+
+/// Function constants list where each of their captures comes from.
+fn describe_constant(constant: &Constant) -> String {
+    match constant {
+        Constant::Int(value) => value.to_string(),
+        Constant::Float(value) => format!("{value:?}"),
+        Constant::Str(text) => format!("\"{}\"", escape_text(text)),
+        Constant::Name(name) => format!("'{name}'"),
+        Constant::Function(prototype) => {
+            let mut description = match &prototype.name {
+                Some(name) => format!("<fn {name}>"),
+                None => "<fn>".to_owned(),
+            };
+            for capture in &prototype.captures {
+                match capture {
+                    Capture::Local(slot) => write!(description, " local {slot}"),
+                    Capture::Capture(i) => write!(description, " capture {i}"),
+                }
+                .unwrap();
+            }
+            description
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::span::Span;
+use thiserror::Error;
+
+/// Limit of the bytecode format exceeded while compiling.
+#[derive(Debug, Error, PartialEq)]
+pub enum CompileError {
+    #[error("too many constants in one function at {span}")]
+    TooManyConstants { span: Span },
+    #[error("too many bindings in one function at {span}")]
+    TooManyLocals { span: Span },
+    #[error("too many captured variables in one function at {span}")]
+    TooManyCaptures { span: Span },
+    #[error("too many arguments in call at {span}")]
+    TooManyArguments { span: Span },
+    #[error("too many interpolations in string at {span}")]
+    TooManyInterpolations { span: Span },
+    #[error("too much code to jump over at {span}")]
+    JumpTooFar { span: Span },
+}
+
+impl CompileError {
+    /// Source span of the expression that could not be compiled.
+    pub fn span(&self) -> Span {
+        match self {
+            CompileError::TooManyConstants { span }
+            | CompileError::TooManyLocals { span }
+            | CompileError::TooManyCaptures { span }
+            | CompileError::TooManyArguments { span }
+            | CompileError::TooManyInterpolations { span }
+            | CompileError::JumpTooFar { span } => *span,
+        }
+    }
+}
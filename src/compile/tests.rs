@@ -0,0 +1,123 @@
+use super::compile;
+use super::disassemble;
+use super::Op;
+use crate::parse::parse_expr;
+
+fn disassemble_str(source: &str) -> String {
+    let chunk = compile(&parse_expr(source).unwrap()).unwrap();
+    disassemble(&chunk, "test")
+}
+
+fn ops(source: &str) -> Vec<Op> {
+    compile(&parse_expr(source).unwrap()).unwrap().code
+}
+
+#[test]
+fn test_checks() {
+    // Operand types evident from the expression are not checked.
+    assert_eq!(
+        ops("1 + 2 * 3"),
+        vec![
+            Op::Constant(0),
+            Op::Constant(1),
+            Op::Constant(2),
+            Op::Mult,
+            Op::Add,
+            Op::Return
+        ]
+    );
+    assert_eq!(
+        ops("(let x = 1; -x)"),
+        vec![
+            Op::Constant(0),
+            Op::Unit,
+            Op::Pop,
+            Op::GetLocal(0),
+            Op::CheckNumber,
+            Op::Neg,
+            Op::PopUnder(1),
+            Op::Return
+        ]
+    );
+    assert_eq!(
+        ops("-1.5 * 2.0 < 1.0"),
+        vec![
+            Op::Constant(0),
+            Op::Neg,
+            Op::Constant(1),
+            Op::Mult,
+            Op::Constant(2),
+            Op::Less,
+            Op::Return
+        ]
+    );
+    // The right operand of `+` is checked against the left.
+    assert_eq!(
+        ops("(let x = \"a\"; x + \"b\")"),
+        vec![
+            Op::Constant(0),
+            Op::Unit,
+            Op::Pop,
+            Op::GetLocal(0),
+            Op::CheckNumberOrStr,
+            Op::Constant(1),
+            Op::CheckSame,
+            Op::Add,
+            Op::PopUnder(1),
+            Op::Return
+        ]
+    );
+    assert_eq!(
+        ops("\"a{1 + 2}\""),
+        vec![
+            Op::Constant(0),
+            Op::Constant(1),
+            Op::Constant(2),
+            Op::Add,
+            Op::Interpolate(2),
+            Op::Return
+        ]
+    );
+}
+
+#[test]
+fn test_disassemble() {
+    assert_eq!(
+        disassemble_str("if true and false (1) else (2)"),
+        "\
+== test ==
+0000    0:3  True
+0001    0:3  JumpIfFalse   2 -> 0004
+0002    0:3  Pop
+0003   0:12  False
+0004    0:0  JumpIfFalse   3 -> 0008
+0005    0:0  Pop
+0006   0:18  Constant      0 (1)
+0007    0:0  Jump          2 -> 0010
+0008    0:0  Pop
+0009   0:27  Constant      1 (2)
+0010    0:0  Return
+"
+    );
+    assert_eq!(
+        disassemble_str("(let x = 1; fn f(y: int) -> int (f(x)))"),
+        "\
+== test ==
+0000    0:9  Constant      0 (1)
+0001    0:1  Unit
+0002    0:0  Pop
+0003   0:12  Closure       1 (<fn f> local 0)
+0004    0:0  PopUnder      1
+0005    0:0  Return
+
+== f ==
+0000   0:33  GetLocal      0
+0001   0:33  CheckCallable
+0002   0:32  CheckArity    1
+0003   0:35  GetCapture    0
+0004   0:35  CheckArg      0
+0005   0:32  Call          1
+0006   0:32  Return
+"
+    );
+}
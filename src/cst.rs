@@ -0,0 +1,289 @@
+//! Lossless concrete syntax tree.
+//!
+//! The green tree holds every token of the source, trivia included, nested
+//! in nodes for the parser's productions.  Green nodes know only their kind,
+//! length and children, so identical subtrees can be shared.  The red tree
+//! of SyntaxNodes wraps it with parents and byte offsets, built as it is
+//! walked.  The text of the tree is exactly the source it was parsed from,
+//! and lower turns it into the Expr the parser returns for that source.
+
+mod lower;
+
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::parse::TokenType;
+
+pub use lower::lower;
+
+/// Kind of a node of the syntax tree.
+///
+/// Trivia before or after a node's first or last token belong to the
+/// enclosing node.  Tokens the parser skipped to recover from an error are
+/// gathered in Skipped nodes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    /// The whole source.
+    Root,
+    Literal,
+    Var,
+    Unary,
+    Binary,
+    /// A single expression in parentheses.
+    Paren,
+    Block,
+    Let,
+    If,
+    Fn,
+    /// A function's parenthesized parameters.
+    ParamList,
+    /// A parameter, `name: type`.
+    Param,
+    Type,
+    Call,
+    /// A call's parenthesized arguments.
+    ArgList,
+    /// A string with interpolations: pieces of its token around the trees of
+    /// the expressions interpolated.
+    Interpolate,
+    /// An expression that could not be parsed.
+    Error,
+    Skipped,
+}
+
+impl NodeKind {
+    /// Whether the node is an expression, which lowers to an Expr.
+    pub fn is_expr(self) -> bool {
+        !matches!(
+            self,
+            NodeKind::Root
+                | NodeKind::ParamList
+                | NodeKind::Param
+                | NodeKind::Type
+                | NodeKind::ArgList
+                | NodeKind::Skipped
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreenToken {
+    kind: TokenType,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: TokenType, text: impl Into<String>) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> TokenType {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(Arc<GreenToken>),
+}
+
+impl GreenElement {
+    /// Length in bytes of the element's text.
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len(),
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreenNode {
+    kind: NodeKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: NodeKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        Self {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// Length in bytes of the node's text.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+/// The node's text, which is the source it was parsed from.
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => write!(f, "{node}")?,
+                GreenElement::Token(token) => f.write_str(&token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Node of the red tree: a green node at a byte offset in the source,
+/// with its parent.  Cloning is cheap.
+#[derive(Clone, Debug)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Debug)]
+struct NodeData {
+    green: Arc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Arc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    /// Byte range of the node in the source.
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len
+    }
+
+    /// Child nodes and tokens, in source order.
+    pub fn children(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+        self.0.green.children.iter().map(move |child| {
+            let start = offset;
+            offset += child.len();
+            match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    offset: start,
+                    parent: Some(self.clone()),
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    offset: start,
+                    parent: self.clone(),
+                }),
+            }
+        })
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Direct child tokens, trivia included.
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken> + '_ {
+        self.children().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+
+    /// All tokens in the node, trivia included, in source order.
+    pub fn descendant_tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.descendant_tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.green.fmt(f)
+    }
+}
+
+/// Token of the red tree, at a byte offset in the source.
+#[derive(Clone, Debug)]
+pub struct SyntaxToken {
+    green: Arc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> TokenType {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+
+    /// Byte range of the token in the source.
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,333 @@
+use super::NodeKind;
+use super::SyntaxElement;
+use super::SyntaxNode;
+use super::SyntaxToken;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Function;
+use crate::ast::expr::Ident;
+use crate::ast::expr::Param;
+use crate::ast::expr::StrPart;
+use crate::parse::number::float_value;
+use crate::parse::number::int_value;
+use crate::parse::operator::BinaryOperator;
+use crate::parse::operator::UnaryOperator;
+use crate::parse::string::str_segments;
+use crate::parse::string::StrSegment;
+use crate::parse::TokenType as ToT;
+use crate::span::Span;
+use crate::ttype::Type;
+
+/// Lower the syntax tree of a source, from parse_cst, to the Expr the
+/// parser returns for it.  Spans are computed from the tokens as the
+/// parser computes them, including for unparseable expressions.
+pub fn lower(root: &SyntaxNode) -> Expr {
+    let lowerer = Lowerer::new(&root.to_string());
+    let expr = exprs(root)
+        .into_iter()
+        .next()
+        .expect("syntax tree has an expression");
+    lowerer.expr(&expr)
+}
+
+struct Lowerer {
+    /// Byte index of the start of each line.
+    line_starts: Vec<usize>,
+}
+
+impl Lowerer {
+    fn new(source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { line_starts }
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        let line = self
+            .line_starts
+            .partition_point(|&line_start| line_start <= start)
+            - 1;
+        Span::new(start, end, line, start - self.line_starts[line])
+    }
+
+    fn token_span(&self, token: &SyntaxToken) -> Span {
+        let range = token.text_range();
+        self.span(range.start, range.end)
+    }
+
+    fn ident(&self, token: &SyntaxToken) -> Ident {
+        Ident::new(token.text(), self.token_span(token))
+    }
+
+    fn expr(&self, node: &SyntaxNode) -> Expr {
+        match node.kind() {
+            NodeKind::Literal => {
+                let token = significant_tokens(node).next().unwrap();
+                let expr = match token.kind() {
+                    ToT::True => Expr::bool(true),
+                    ToT::False => Expr::bool(false),
+                    ToT::Str => match &str_segments(token.text()).unwrap()[..] {
+                        [StrSegment::Text(text)] => Expr::str(text.clone()),
+                        _ => unreachable!("string literal has no interpolations"),
+                    },
+                    ToT::Float => Expr::float(float_value(token.text()).unwrap()),
+                    _ => Expr::int(int_value(token.text()).unwrap()),
+                };
+                expr.with_span(self.token_span(&token))
+            }
+            NodeKind::Var => {
+                let token = significant_tokens(node).next().unwrap();
+                Expr::var(token.text()).with_span(self.token_span(&token))
+            }
+            NodeKind::Unary => {
+                let token = significant_tokens(node).next().unwrap();
+                let operator = UnaryOperator::from_token(token.kind()).unwrap();
+                let right = self.expr(&exprs(node)[0]);
+                let span = self.token_span(&token).to(right.span);
+                Expr::unary(operator.op, right).with_span(span)
+            }
+            NodeKind::Binary => {
+                let token = significant_tokens(node).next().unwrap();
+                let operator = BinaryOperator::from_token(token.kind()).unwrap();
+                let exprs = exprs(node);
+                let left = self.expr(&exprs[0]);
+                let right = self.expr(&exprs[1]);
+                let span = left.span.to(right.span);
+                Expr::binary(left, operator.op, right).with_span(span)
+            }
+            NodeKind::Paren | NodeKind::Block => self.block(node),
+            NodeKind::Let => self.let_(node),
+            NodeKind::If => self.if_(node),
+            NodeKind::Fn => self.fn_(node),
+            NodeKind::Call => {
+                let callee = self.expr(&exprs(node)[0]);
+                let args_node = child(node, NodeKind::ArgList).unwrap();
+                let args: Vec<_> = exprs(&args_node).iter().map(|arg| self.expr(arg)).collect();
+                let close_span = token(&args_node, ToT::CloseParen).map(|t| self.token_span(&t));
+                let last_span = close_span.or(args.last().map(|arg| arg.span));
+                let span = callee.span.to(last_span.unwrap_or(callee.span));
+                Expr::call(callee, args).with_span(span)
+            }
+            NodeKind::Interpolate => self.interpolate(node),
+            NodeKind::Error => Expr::new(ExprKind::Error, self.extent(node)),
+            kind => unreachable!("{kind:?} node is not an expression"),
+        }
+    }
+
+    /// Span from the start of the node's first token to the end of its
+    /// last, or empty at the node if it has none.
+    fn extent(&self, node: &SyntaxNode) -> Span {
+        let tokens = node.descendant_tokens();
+        let mut significant = tokens.iter().filter(|t| !t.kind().is_trivia());
+        match (significant.next(), significant.next_back()) {
+            (Some(first), last) => {
+                let end = last.unwrap_or(first).text_range().end;
+                self.span(first.text_range().start, end)
+            }
+            (None, _) => {
+                let start = node.text_range().start;
+                self.span(start, start)
+            }
+        }
+    }
+
+    /// A string's interpolations are its expression nodes, in order.
+    fn interpolate(&self, node: &SyntaxNode) -> Expr {
+        let mut exprs = exprs(node).into_iter();
+        let parts = str_segments(&node.to_string())
+            .unwrap()
+            .into_iter()
+            .map(|segment| match segment {
+                StrSegment::Text(text) => StrPart::Text(text),
+                StrSegment::Interpolation(_) => StrPart::Expr(self.expr(&exprs.next().unwrap())),
+            })
+            .collect();
+        let range = node.text_range();
+        Expr::interpolate(parts).with_span(self.span(range.start, range.end))
+    }
+
+    fn block(&self, node: &SyntaxNode) -> Expr {
+        let open_span = self.token_span(&token(node, ToT::OpenParen).unwrap());
+        let mut contents = Vec::new();
+        let mut trailing_semicolon = false;
+        for child in node.children() {
+            match child {
+                SyntaxElement::Node(child) if child.kind().is_expr() => {
+                    contents.push(self.expr(&child));
+                    trailing_semicolon = false;
+                }
+                SyntaxElement::Token(token) if token.kind() == ToT::Semicolon => {
+                    trailing_semicolon = !contents.is_empty();
+                }
+                _ => (),
+            }
+        }
+        let close_span = token(node, ToT::CloseParen).map(|t| self.token_span(&t));
+        let span = open_span.to(close_span.unwrap_or(contents[contents.len() - 1].span));
+        let single = contents.len() == 1 && !trailing_semicolon;
+        if single && !matches!(contents[0].kind, ExprKind::Let { .. }) {
+            return contents.pop().unwrap().with_span(span);
+        }
+        let block = ExprKind::Block {
+            contents,
+            trailing_semicolon,
+        };
+        Expr::new(block, span)
+    }
+
+    fn let_(&self, node: &SyntaxNode) -> Expr {
+        let let_span = self.token_span(&token(node, ToT::Let).unwrap());
+        let name = token(node, ToT::Identifier).map(|t| self.ident(&t));
+        match (name, token(node, ToT::Equal)) {
+            (Some(name), Some(_)) => {
+                let value = self.expr(&exprs(node)[0]);
+                let span = let_span.to(value.span);
+                let value = Box::new(value);
+                Expr::new(ExprKind::Let { name, value }, span)
+            }
+            (Some(name), None) => Expr::new(ExprKind::Error, let_span.to(name.span)),
+            (None, _) => Expr::new(ExprKind::Error, self.extent(node)),
+        }
+    }
+
+    fn if_(&self, node: &SyntaxNode) -> Expr {
+        let if_span = self.token_span(&token(node, ToT::If).unwrap());
+        let exprs = exprs(node);
+        let condition = self.expr(&exprs[0]);
+        // A then branch missing its parens is empty at the token after the
+        // condition, before any tokens skipped to recover.
+        let then_expr = match exprs[1].kind() {
+            NodeKind::Error => {
+                let at = node
+                    .children()
+                    .filter(|child| !is_trivia(child))
+                    .skip_while(|child| !is_node(child, &exprs[0]))
+                    .nth(1)
+                    .map_or(node.text_range().end, |child| match child {
+                        SyntaxElement::Node(node) => node.text_range().start,
+                        SyntaxElement::Token(token) => token.text_range().start,
+                    });
+                Expr::new(ExprKind::Error, self.span(at, at))
+            }
+            _ => self.expr(&exprs[1]),
+        };
+        // An Else token may be the one skipped in place of the then branch's
+        // `(`, so only a third expression is an else branch.
+        let else_expr = exprs.get(2).map(|else_expr| self.expr(else_expr));
+        let span = if_span.to(else_expr.as_ref().unwrap_or(&then_expr).span);
+        Expr::ifthen(condition, then_expr, else_expr).with_span(span)
+    }
+
+    /// A function, or an Error spanning `fn` if it could not be parsed.
+    fn fn_(&self, node: &SyntaxNode) -> Expr {
+        let fn_span = self.token_span(&token(node, ToT::Fn).unwrap());
+        let params = child(node, NodeKind::ParamList).and_then(|params| self.params(&params));
+        let arrow = token(node, ToT::Arrow);
+        let ret = child(node, NodeKind::Type).and_then(|ret| self.ttype(&ret));
+        let body = exprs(node).first().map(|body| self.expr(body));
+        let (Some(params), Some(_), Some(ret), Some(body)) = (params, arrow, ret, body) else {
+            return Expr::new(ExprKind::Error, fn_span);
+        };
+        // The name directly follows `fn`; an identifier anywhere else was
+        // skipped in recovery.
+        let name = node
+            .children()
+            .filter(|child| !is_trivia(child))
+            .nth(1)
+            .and_then(|child| match child {
+                SyntaxElement::Token(token) if token.kind() == ToT::Identifier => {
+                    Some(self.ident(&token))
+                }
+                _ => None,
+            });
+        let span = fn_span.to(body.span);
+        let function = Function {
+            name,
+            params,
+            ret,
+            body: Box::new(body),
+        };
+        Expr::new(ExprKind::Fn(function), span)
+    }
+
+    /// The parameters of a ParamList, or None if they could not be parsed.
+    fn params(&self, node: &SyntaxNode) -> Option<Vec<Param>> {
+        token(node, ToT::OpenParen)?;
+        token(node, ToT::CloseParen)?;
+        node.child_nodes()
+            .filter(|child| child.kind() == NodeKind::Param)
+            .map(|param| {
+                let name = self.ident(&token(&param, ToT::Identifier)?);
+                token(&param, ToT::Colon)?;
+                let ttype = self.ttype(&child(&param, NodeKind::Type)?)?;
+                Some(Param { name, ttype })
+            })
+            .collect()
+    }
+
+    /// The type of a Type node, or None if it could not be parsed.
+    fn ttype(&self, node: &SyntaxNode) -> Option<Type> {
+        match significant_tokens(node).next()?.kind() {
+            ToT::Bool => Some(Type::Bool),
+            ToT::IntType => Some(Type::Int),
+            ToT::FloatType => Some(Type::Float),
+            ToT::UnitType => Some(Type::Unit),
+            ToT::StrType => Some(Type::Str),
+            ToT::Fn => {
+                token(node, ToT::OpenParen)?;
+                token(node, ToT::CloseParen)?;
+                let mut params = Vec::new();
+                let mut ret = None;
+                for child in node.children() {
+                    match child {
+                        SyntaxElement::Token(token) if token.kind() == ToT::Arrow => {
+                            ret = Some(None);
+                        }
+                        SyntaxElement::Node(child) if child.kind() == NodeKind::Type => {
+                            let ttype = self.ttype(&child)?;
+                            match &mut ret {
+                                None => params.push(ttype),
+                                Some(ret) => *ret = Some(ttype),
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                Some(Type::function(params, ret??))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The node's child expression nodes.
+fn exprs(node: &SyntaxNode) -> Vec<SyntaxNode> {
+    node.child_nodes()
+        .filter(|child| child.kind().is_expr())
+        .collect()
+}
+
+/// The node's first child node of kind.
+fn child(node: &SyntaxNode, kind: NodeKind) -> Option<SyntaxNode> {
+    node.child_nodes().find(|child| child.kind() == kind)
+}
+
+/// The node's first direct token of kind.
+fn token(node: &SyntaxNode, kind: ToT) -> Option<SyntaxToken> {
+    node.child_tokens().find(|token| token.kind() == kind)
+}
+
+/// The node's direct tokens other than trivia.
+fn significant_tokens(node: &SyntaxNode) -> impl Iterator<Item = SyntaxToken> + '_ {
+    node.child_tokens()
+        .filter(|token| !token.kind().is_trivia())
+}
+
+fn is_trivia(element: &SyntaxElement) -> bool {
+    matches!(element, SyntaxElement::Token(token) if token.kind().is_trivia())
+}
+
+fn is_node(element: &SyntaxElement, node: &SyntaxNode) -> bool {
+    matches!(element, SyntaxElement::Node(child) if child.text_range() == node.text_range())
+}
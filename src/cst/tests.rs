@@ -0,0 +1,161 @@
+use super::*;
+use crate::parse::parse_cst;
+use crate::parse::parse_expr_recovering;
+use crate::test_util::CORPUS;
+
+/// Assert that the tree of source has its text, and lowers to the Expr
+/// and errors of parsing it.
+fn assert_lossless(source: &str) {
+    let (root, errors) = parse_cst(source);
+    assert_eq!(root.kind(), NodeKind::Root);
+    assert_eq!(root.to_string(), source);
+    assert_eq!(root.text_range(), 0..source.len());
+    let (expr, expected_errors) = parse_expr_recovering(source);
+    assert_eq!(lower(&root), expr, "lowering {source:?}");
+    assert_eq!(errors, expected_errors, "errors of {source:?}");
+}
+
+/// Kinds of the nodes in the tree, in preorder, with their depth.
+fn kinds(node: &SyntaxNode, depth: usize, out: &mut Vec<(usize, NodeKind)>) {
+    out.push((depth, node.kind()));
+    for child in node.child_nodes() {
+        kinds(&child, depth + 1, out);
+    }
+}
+
+#[test]
+fn test_structure() {
+    let source = "( let x = -1 ; // one\n  f(x, 2) * x )  ";
+    assert_lossless(source);
+    let (root, errors) = parse_cst(source);
+    assert!(errors.is_empty());
+    let mut out = Vec::new();
+    kinds(&root, 0, &mut out);
+    use NodeKind::*;
+    assert_eq!(
+        out,
+        vec![
+            (0, Root),
+            (1, Block),
+            (2, Let),
+            (3, Unary),
+            (4, Literal),
+            (2, Binary),
+            (3, Call),
+            (4, Var),
+            (4, ArgList),
+            (5, Var),
+            (5, Literal),
+            (3, Var),
+        ]
+    );
+
+    // Trivia between tokens belong to the innermost node containing both.
+    let block = root.child_nodes().next().unwrap();
+    let texts: Vec<_> = block
+        .child_tokens()
+        .map(|token| (token.kind(), token.text().to_owned()))
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            (TokenType::OpenParen, "(".to_owned()),
+            (TokenType::Whitespace, " ".to_owned()),
+            (TokenType::Whitespace, " ".to_owned()),
+            (TokenType::Semicolon, ";".to_owned()),
+            (TokenType::Whitespace, " ".to_owned()),
+            (TokenType::LineComment, "// one".to_owned()),
+            (TokenType::Newline, "\n".to_owned()),
+            (TokenType::Whitespace, "  ".to_owned()),
+            (TokenType::Whitespace, " ".to_owned()),
+            (TokenType::CloseParen, ")".to_owned()),
+        ]
+    );
+    let trailing: Vec<_> = root.child_tokens().map(|token| token.kind()).collect();
+    assert_eq!(trailing, vec![TokenType::Whitespace]);
+
+    let open = source.find("(x").unwrap();
+    let tokens = root.descendant_tokens();
+    let paren = tokens.iter().find(|token| token.text_range().start == open);
+    let args = paren.unwrap().parent().clone();
+    assert_eq!(args.kind(), ArgList);
+    assert_eq!(&source[args.text_range()], "(x, 2)");
+    assert_eq!(args.parent().unwrap().kind(), Call);
+}
+
+#[test]
+fn test_skipped() {
+    let source = "(1 + ) 2 3";
+    assert_lossless(source);
+    let (root, errors) = parse_cst(source);
+    assert!(!errors.is_empty());
+    let skipped: Vec<_> = root
+        .child_nodes()
+        .filter(|node| node.kind() == NodeKind::Skipped)
+        .map(|node| node.to_string())
+        .collect();
+    assert_eq!(skipped, vec!["2 3"]);
+}
+
+#[test]
+fn test_errors() {
+    for source in [
+        "",
+        "  // nothing\n",
+        "(",
+        ")",
+        "1 +",
+        "(let x = ; x)",
+        "(let = 1; 2)",
+        "(let x; x)",
+        "if true 1 else 2",
+        "if (true) (1) else",
+        "fn (x: int -> int (x)",
+        "fn f(x: foo) -> int (x)",
+        "fn (x: fn(int, bool -> int) -> int (1)",
+        "fn (x: int) int (x)",
+        "f(1, 2",
+        "f(1 2)",
+        "(1; 2;; /* a */ 3)",
+        "-(",
+        "1 ) ) 2",
+        "(1 + /* unterminated",
+        "if 1 else 2",
+        "if else 1",
+        "if 1 else 2 else 3",
+        "fn (a: int) -> int f\n(1)",
+        "fn f f(a: int) -> int (a)",
+    ] {
+        assert_lossless(source);
+    }
+}
+
+/// Sequences of random tokens, mostly malformed, lower as they parse.
+#[test]
+fn test_random_token_soups() {
+    const TOKENS: [&str; 24] = [
+        "if", "else", "fn", "let", "(", ")", "1", "x", "f", "+", "-", "<", "not", "=", ";", ",",
+        ":", "int", "->", "\"a{x}\"", "/* c */", " ", "\n", "1.5",
+    ];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut random = |n: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n as u64) as usize
+    };
+    for _ in 0..5000 {
+        let len = 1 + random(10);
+        let source: String = (0..len)
+            .map(|_| format!("{} ", TOKENS[random(TOKENS.len())]))
+            .collect();
+        assert_lossless(&source);
+    }
+}
+
+#[test]
+fn test_corpus() {
+    for source in CORPUS {
+        assert_lossless(source);
+    }
+}
@@ -0,0 +1,526 @@
+use std::fmt::Write;
+
+use crate::analysis::Analysis;
+use crate::compile::CompileError;
+use crate::eval::RuntimeError;
+use crate::eval::MAX_CALL_DEPTH;
+use crate::parse::ParseError;
+use crate::resolve::ResolveError;
+use crate::span::Span;
+use crate::typecheck::TypeError;
+use crate::vm::MAX_FRAMES;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+/// How to style rendered diagnostics.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    /// Color with ANSI escape codes, for terminals.
+    Ansi,
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// A message about a region of source, renderable with a snippet of that source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// Short text shown under the span.
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+/// Errors which can be described by a Diagnostic.
+pub trait ToDiagnostic {
+    /// Describe self as a Diagnostic.  Source is the text that was parsed,
+    /// for errors which do not record byte spans.
+    fn to_diagnostic(&self, source: &str) -> Diagnostic;
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            span,
+            label: None,
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Error, message, span)
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Warning, message, span)
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Render the diagnostic with the offending line of source, and the span underlined.
+    /// Lines and columns are shown 1-indexed, as in editors.
+    /// Spans covering several lines are underlined to the end of their first line.
+    pub fn render(&self, source: &str, style: Style) -> String {
+        self.render_location(None, source, style)
+    }
+
+    /// Render as with render, naming the file source was read from.
+    pub fn render_file(&self, path: &str, source: &str, style: Style) -> String {
+        self.render_location(Some(path), source, style)
+    }
+
+    fn render_location(&self, path: Option<&str>, source: &str, style: Style) -> String {
+        let paint = |color: &'static str, text: &str| match style {
+            Style::Plain => text.to_owned(),
+            Style::Ansi => format!("{color}{text}{RESET}"),
+        };
+        let color = self.severity.color();
+        let line_text = source.lines().nth(self.span.line).unwrap_or("");
+        let line_number = (self.span.line + 1).to_string();
+        let gutter = " ".repeat(line_number.len());
+        let bar = paint(BLUE, "|");
+
+        // Pad to the span start, keeping tabs so the underline lines up.
+        let prefix = line_text.get(..self.span.col).unwrap_or(line_text);
+        let padding: String = prefix
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let rest = &line_text[prefix.len()..];
+        let width = rest
+            .get(..self.span.len().min(rest.len()))
+            .unwrap_or(rest)
+            .chars()
+            .count()
+            .max(1);
+        let mut underline = paint(color, &"^".repeat(width));
+        if let Some(label) = &self.label {
+            underline = format!("{underline} {}", paint(color, label));
+        }
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{}{}",
+            paint(color, self.severity.name()),
+            paint(BOLD, &format!(": {}", self.message)),
+        );
+        let path = path.map(|path| format!("{path}:")).unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "{gutter}{} {path}{}:{}",
+            paint(BLUE, "-->"),
+            self.span.line + 1,
+            self.span.col + 1
+        );
+        let _ = writeln!(out, "{gutter} {bar}");
+        let _ = writeln!(out, "{} {bar} {line_text}", paint(BLUE, &line_number));
+        let _ = writeln!(out, "{gutter} {bar} {padding}{underline}");
+        for note in &self.notes {
+            let _ = writeln!(out, "{gutter} {} note: {note}", paint(BLUE, "="));
+        }
+        if let Some(help) = &self.help {
+            let _ = writeln!(out, "{gutter} {} help: {help}", paint(BLUE, "="));
+        }
+        out
+    }
+}
+
+/// Diagnose source without running it: every parse error, or if it parses,
+/// its resolve errors and warnings, then its first type error if its
+/// variables are all defined.
+pub fn check(source: &str) -> Vec<Diagnostic> {
+    Analysis::new(source).diagnostics
+}
+
+impl ToDiagnostic for ParseError {
+    fn to_diagnostic(&self, source: &str) -> Diagnostic {
+        match self {
+            ParseError::UnknownToken { lexeme, line, col } => Diagnostic::error(
+                format!("unknown token '{lexeme}'"),
+                Span::locate(source, *line, *col, lexeme.len()),
+            )
+            .with_label("not a valid token"),
+            ParseError::Eof { line, col } => Diagnostic::error(
+                "unexpected end of input",
+                Span::locate(source, *line, *col, 0),
+            )
+            .with_label("expected more input"),
+            ParseError::UnexpectedToken {
+                expected,
+                actual,
+                lexeme,
+                line,
+                col,
+            } => Diagnostic::error(
+                format!("expected {expected}, but found {actual:?} '{lexeme}'"),
+                Span::locate(source, *line, *col, lexeme.len()),
+            )
+            .with_label(format!("expected {expected}")),
+            ParseError::ChainedComparison { lexeme, line, col } => Diagnostic::error(
+                "comparison operators cannot be chained",
+                Span::locate(source, *line, *col, lexeme.len()),
+            )
+            .with_label("second comparison")
+            .with_help("combine comparisons with `and`, as in `a < b and b < c`"),
+            ParseError::UnterminatedComment { line, col } => Diagnostic::error(
+                "unterminated block comment",
+                Span::locate(source, *line, *col, 2),
+            )
+            .with_label("comment starts here")
+            .with_help("block comments nest, so each `/*` needs its own `*/`"),
+            ParseError::UnterminatedString { line, col } => {
+                Diagnostic::error("unterminated string", Span::locate(source, *line, *col, 1))
+                    .with_label("string starts here")
+                    .with_help("a string ends with `\"` on the line it starts on")
+            }
+            ParseError::BadEscape { escape, line, col } => Diagnostic::error(
+                format!("invalid escape '{escape}' in string"),
+                Span::locate(source, *line, *col, escape.len()),
+            )
+            .with_label("not a valid escape")
+            .with_help(r#"valid escapes are \n \t \r \0 \\ \" \{ \} and \u{...}"#),
+            ParseError::UnfinishedInterpolation { line, col } => Diagnostic::error(
+                "unexpected end of interpolated expression",
+                Span::locate(source, *line, *col, 1),
+            )
+            .with_label("expected more of the expression before this")
+            .with_help("an interpolation holds one expression, as in `\"x = {x}\"`"),
+            ParseError::IntegerOverflow { lexeme, line, col } => Diagnostic::error(
+                format!("integer literal '{lexeme}' out of range"),
+                Span::locate(source, *line, *col, lexeme.len()),
+            )
+            .with_label("does not fit in an int")
+            .with_note("ints are 64-bit signed integers, at most 9223372036854775807"),
+            ParseError::FloatOverflow { lexeme, line, col } => Diagnostic::error(
+                format!("float literal '{lexeme}' out of range"),
+                Span::locate(source, *line, *col, lexeme.len()),
+            )
+            .with_label("too large for a float")
+            .with_note("floats are 64-bit IEEE 754 numbers, at most about 1.8e308"),
+            ParseError::StraySemicolon { line, col } => {
+                Diagnostic::error("unexpected ';'", Span::locate(source, *line, *col, 1))
+                    .with_label("expected an expression before this")
+                    .with_help("`;` separates the expressions of a block, as in `(a; b)`")
+            }
+            ParseError::Unclassified(message) => {
+                Diagnostic::error(message.clone(), Span::default())
+            }
+        }
+    }
+}
+
+impl ToDiagnostic for TypeError {
+    fn to_diagnostic(&self, _source: &str) -> Diagnostic {
+        match self {
+            TypeError::Mismatch {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error("mismatched types", *span)
+                .with_label(format!("expected {expected}, found {found}")),
+            TypeError::BranchMismatch {
+                then_type,
+                else_type,
+                span,
+            } => Diagnostic::error("if and else have incompatible types", *span)
+                .with_label(format!("then is {then_type}, but else is {else_type}"))
+                .with_note("both branches of an if/else must have the same type"),
+            TypeError::UndefinedVariable { name, span } => {
+                Diagnostic::error(format!("undefined variable '{name}'"), *span)
+                    .with_label("not found in this scope")
+            }
+            TypeError::NotComparable { ttype, span } => {
+                Diagnostic::error(format!("values of type {ttype} cannot be compared"), *span)
+                    .with_label("not comparable")
+                    .with_note("only bool, int, float, str and unit values can be compared")
+            }
+            TypeError::NotCallable { found, span } => {
+                Diagnostic::error("call of a non-function", *span)
+                    .with_label(format!("this is {found}, not a function"))
+            }
+            TypeError::ArgumentCount {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error("wrong number of arguments", *span)
+                .with_label(format!("expected {expected} arguments, found {found}")),
+            TypeError::Unparsed { span } => {
+                Diagnostic::error("cannot type check an expression with parse errors", *span)
+                    .with_label("unparsed")
+            }
+        }
+    }
+}
+
+impl ToDiagnostic for RuntimeError {
+    fn to_diagnostic(&self, _source: &str) -> Diagnostic {
+        match self {
+            RuntimeError::DivisionByZero { span } => {
+                Diagnostic::error("division by zero", *span).with_label("divisor is zero")
+            }
+            RuntimeError::IntegerOverflow { op, span } => {
+                Diagnostic::error(format!("integer overflow in '{op}'"), *span)
+                    .with_label("result does not fit in an int")
+                    .with_note("ints are 64-bit signed integers")
+            }
+            RuntimeError::TypeMismatch {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error("mismatched types", *span)
+                .with_label(format!("expected {expected}, found {found}")),
+            RuntimeError::UndefinedVariable { name, span } => {
+                Diagnostic::error(format!("undefined variable '{name}'"), *span)
+                    .with_label("not found in this scope")
+            }
+            RuntimeError::NotCallable { found, span } => {
+                Diagnostic::error("call of a non-function", *span)
+                    .with_label(format!("this is {found}, not a function"))
+            }
+            RuntimeError::ArgumentCount {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error("wrong number of arguments", *span)
+                .with_label(format!("expected {expected} arguments, found {found}")),
+            RuntimeError::CallDepth { span } => {
+                Diagnostic::error("maximum call depth exceeded", *span)
+                    .with_label("too many calls in progress")
+                    .with_note(format!(
+                "the evaluator allows {MAX_CALL_DEPTH} calls in progress, and the vm {MAX_FRAMES}"
+            ))
+            }
+            RuntimeError::Unparsed { span } => {
+                Diagnostic::error("cannot evaluate an expression with parse errors", *span)
+                    .with_label("unparsed")
+            }
+        }
+    }
+}
+
+impl ToDiagnostic for CompileError {
+    fn to_diagnostic(&self, _source: &str) -> Diagnostic {
+        let label = match self {
+            CompileError::TooManyConstants { .. } => "too many constants",
+            CompileError::TooManyLocals { .. } => "too many bindings",
+            CompileError::TooManyCaptures { .. } => "too many captured variables",
+            CompileError::TooManyArguments { .. } => "too many arguments",
+            CompileError::TooManyInterpolations { .. } => "too many interpolations",
+            CompileError::JumpTooFar { .. } => "too much code to jump over",
+        };
+        Diagnostic::error("expression is too large to compile", self.span())
+            .with_label(label)
+            .with_note("split it into smaller functions")
+    }
+}
+
+impl ToDiagnostic for ResolveError {
+    fn to_diagnostic(&self, _source: &str) -> Diagnostic {
+        match self {
+            ResolveError::Undefined { name, span } => {
+                Diagnostic::error(format!("undefined variable '{name}'"), *span)
+                    .with_label("not found in this scope")
+            }
+            ResolveError::Shadowed {
+                name,
+                span,
+                previous,
+            } => Diagnostic::warning(format!("'{name}' shadows an earlier binding"), *span)
+                .with_label("shadowing binding")
+                .with_note(format!(
+                    "'{name}' was previously bound at {}:{}",
+                    previous.line + 1,
+                    previous.col + 1
+                )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::eval;
+    use crate::parse::parse_expr;
+    use crate::typecheck::typecheck;
+
+    #[test]
+    fn test_check() {
+        let messages =
+            |source| -> Vec<String> { check(source).into_iter().map(|d| d.message).collect() };
+        assert!(check("(let x = 1; x + 2)").is_empty());
+        assert_eq!(
+            messages("(1 +; 2 <)"),
+            vec![
+                "expected primary expression, but found Semicolon ';'",
+                "expected primary expression, but found CloseParen ')'",
+            ]
+        );
+        assert_eq!(
+            messages("(let x = 1; let x = y; z)"),
+            vec![
+                "undefined variable 'y'",
+                "'x' shadows an earlier binding",
+                "undefined variable 'z'",
+            ]
+        );
+        assert_eq!(
+            messages("(let x = 1; let x = true; x + 1)"),
+            vec!["'x' shadows an earlier binding", "mismatched types"]
+        );
+        assert!(!check("(let x = 1; let x = 2; x)")[0].is_error());
+    }
+
+    #[test]
+    fn test_render_parse_error() {
+        let source = "1 +\n  then";
+        let error = parse_expr(source).unwrap_err();
+        let diagnostic = error.to_diagnostic(source);
+        assert_eq!(diagnostic.span, Span::new(6, 10, 1, 2));
+        assert_eq!(
+            diagnostic.render(source, Style::Plain),
+            "\
+error: expected primary expression, but found Then 'then'
+ --> 2:3
+  |
+2 |   then
+  |   ^^^^ expected primary expression
+"
+        );
+    }
+
+    #[test]
+    fn test_render_eof() {
+        let source = "(1 + ";
+        let error = parse_expr(source).unwrap_err();
+        assert_eq!(
+            error.to_diagnostic(source).render(source, Style::Plain),
+            "\
+error: unexpected end of input
+ --> 1:6
+  |
+1 | (1 + 
+  |      ^ expected more input
+"
+        );
+    }
+
+    #[test]
+    fn test_render_file() {
+        let source = "(1 + ";
+        let error = parse_expr(source).unwrap_err();
+        let rendered = error
+            .to_diagnostic(source)
+            .render_file("main.cb", source, Style::Plain);
+        assert!(rendered.contains(" --> main.cb:1:6\n"), "{rendered}");
+    }
+
+    #[test]
+    fn test_render_notes_and_help() {
+        let source = "1 < 2 < 3";
+        let error = parse_expr(source).unwrap_err();
+        let diagnostic = error.to_diagnostic(source).with_note("a note");
+        assert_eq!(
+            diagnostic.render(source, Style::Plain),
+            "\
+error: comparison operators cannot be chained
+ --> 1:7
+  |
+1 | 1 < 2 < 3
+  |       ^ second comparison
+  = note: a note
+  = help: combine comparisons with `and`, as in `a < b and b < c`
+"
+        );
+    }
+
+    #[test]
+    fn test_render_type_error() {
+        let source = "if true (1)\n\telse true and 2";
+        let error = typecheck(&parse_expr(source).unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_diagnostic(source).render(source, Style::Plain),
+            "\
+error: mismatched types
+ --> 2:16
+  |
+2 | \telse true and 2
+  | \t              ^ expected bool, found int
+"
+        );
+    }
+
+    #[test]
+    fn test_render_runtime_error_multiline() {
+        let source = "1 + (9223372036854775807 +\n 1)";
+        let error = eval(&parse_expr(source).unwrap()).unwrap_err();
+        let rendered = error.to_diagnostic(source).render(source, Style::Plain);
+        assert_eq!(
+            rendered,
+            "\
+error: integer overflow in '+'
+ --> 1:5
+  |
+1 | 1 + (9223372036854775807 +
+  |     ^^^^^^^^^^^^^^^^^^^^^^ result does not fit in an int
+  = note: ints are 64-bit signed integers
+"
+        );
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let source = "not 3";
+        let error = typecheck(&parse_expr(source).unwrap()).unwrap_err();
+        let rendered = error.to_diagnostic(source).render(source, Style::Ansi);
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: mismatched types\x1b[0m\n"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m \x1b[1;31mexpected bool, found int\x1b[0m"));
+    }
+}
@@ -0,0 +1,351 @@
+mod error;
+mod value;
+
+use std::cmp::Ordering;
+use std::mem;
+use std::panic;
+use std::rc::Rc;
+use std::thread;
+
+pub use error::RuntimeError;
+pub use value::Closure;
+pub use value::Value;
+
+use crate::ast::expr::BinaryOp;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Literal;
+use crate::ast::expr::StrPart;
+use crate::ast::expr::UnaryOp;
+use crate::span::Span;
+use crate::ttype::Type;
+
+pub type EvalResult<T> = Result<T, RuntimeError>;
+
+/// Calls that may be in progress at once, beyond which a call fails
+/// rather than overflowing the stack.
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Stack an Evaluator needs to reach MAX_CALL_DEPTH, even in debug builds,
+/// unless function bodies nest unusually deeply.  This is far more than a
+/// thread has by default, so evaluate inside with_eval_stack.
+pub const EVAL_STACK_SIZE: usize = 256 << 20;
+
+/// Evaluate an expression with a fresh Evaluator.
+pub fn eval(expr: &Expr) -> EvalResult<Value> {
+    Evaluator::new().eval(expr)
+}
+
+/// Run f on a thread with EVAL_STACK_SIZE of stack, and return its result.
+pub fn with_eval_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(EVAL_STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("evaluation thread spawns")
+            .join()
+            .unwrap_or_else(|panic| panic::resume_unwind(panic))
+    })
+}
+
+/// Tree-walking evaluator for Expr.
+#[derive(Default)]
+pub struct Evaluator {
+    /// Bindings in scope, innermost last.
+    env: Vec<(String, Value)>,
+    /// Calls in progress.
+    depth: usize,
+}
+
+impl Evaluator {
+    pub fn new() -> Self {
+        Self {
+            env: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn eval(&mut self, expr: &Expr) -> EvalResult<Value> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => Ok(self.eval_literal(literal)),
+            ExprKind::Unary { op, right } => self.eval_unary(expr.span, *op, right),
+            ExprKind::Binary { left, op, right } => self.eval_binary(expr.span, left, *op, right),
+            ExprKind::Block {
+                contents,
+                trailing_semicolon,
+            } => self.eval_block(contents, *trailing_semicolon),
+            ExprKind::Var(name) => self.lookup(name, expr.span),
+            ExprKind::Let { name, value } => {
+                let value = self.eval(value)?;
+                self.env.push((name.name.clone(), value));
+                Ok(Value::Unit)
+            }
+            ExprKind::If {
+                condition,
+                then_expr,
+                else_expr,
+            } => self.eval_if(condition, then_expr, else_expr.as_deref()),
+            ExprKind::Fn(function) => Ok(Value::Fn(Rc::new(Closure {
+                function: function.clone(),
+                env: self.env.clone(),
+            }))),
+            ExprKind::Call { callee, args } => self.eval_call(expr.span, callee, args),
+            ExprKind::Interpolate(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        StrPart::Text(part) => text.push_str(part),
+                        StrPart::Expr(expr) => text.push_str(&self.eval(expr)?.to_string()),
+                    }
+                }
+                Ok(Value::Str(text.into()))
+            }
+            ExprKind::Error => Err(RuntimeError::Unparsed { span: expr.span }),
+        }
+    }
+
+    fn eval_literal(&mut self, literal: &Literal) -> Value {
+        match literal {
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::Int(i) => Value::Int(*i),
+            Literal::Float(x) => Value::Float(*x),
+            Literal::Str(s) => Value::Str(s.as_str().into()),
+        }
+    }
+
+    fn eval_unary(&mut self, span: Span, op: UnaryOp, right: &Expr) -> EvalResult<Value> {
+        match op {
+            UnaryOp::Not => Ok(Value::Bool(!self.eval_bool(right)?)),
+            UnaryOp::Neg => match self.eval(right)? {
+                Value::Int(i) => checked(i.checked_neg(), "-", span),
+                Value::Float(x) => Ok(Value::Float(-x)),
+                value => Err(RuntimeError::type_mismatch(Type::Int, &value, right.span)),
+            },
+            UnaryOp::Pos => match self.eval(right)? {
+                value @ (Value::Int(_) | Value::Float(_)) => Ok(value),
+                value => Err(RuntimeError::type_mismatch(Type::Int, &value, right.span)),
+            },
+            UnaryOp::Len => Ok(Value::Int(self.eval_str(right)?.chars().count() as i64)),
+        }
+    }
+
+    fn eval_binary(
+        &mut self,
+        span: Span,
+        left: &Expr,
+        op: BinaryOp,
+        right: &Expr,
+    ) -> EvalResult<Value> {
+        match op {
+            // And/Or short-circuit, so the right side is only evaluated if needed.
+            BinaryOp::And => Ok(Value::Bool(self.eval_bool(left)? && self.eval_bool(right)?)),
+            BinaryOp::Or => Ok(Value::Bool(self.eval_bool(left)? || self.eval_bool(right)?)),
+            BinaryOp::Equal => Ok(Value::Bool(self.eval_equals(left, right)?)),
+            BinaryOp::NotEqual => Ok(Value::Bool(!self.eval_equals(left, right)?)),
+            BinaryOp::Less => self.eval_compare(left, right, Ordering::is_lt),
+            BinaryOp::LessEq => self.eval_compare(left, right, Ordering::is_le),
+            BinaryOp::Great => self.eval_compare(left, right, Ordering::is_gt),
+            BinaryOp::GreatEq => self.eval_compare(left, right, Ordering::is_ge),
+            // Strings are concatenated by `+`.
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mult | BinaryOp::Div => {
+                match self.eval(left)? {
+                    Value::Int(i) => int_arithmetic(span, i, op, self.eval_int(right)?),
+                    Value::Float(x) => Ok(Value::Float(float_arithmetic(
+                        x,
+                        op,
+                        self.eval_float(right)?,
+                    ))),
+                    Value::Str(s) if op == BinaryOp::Add => {
+                        Ok(Value::Str(format!("{s}{}", self.eval_str(right)?).into()))
+                    }
+                    value => Err(RuntimeError::type_mismatch(Type::Int, &value, left.span)),
+                }
+            }
+        }
+    }
+
+    // Bindings made in the block go out of scope at its end.
+    fn eval_block(&mut self, contents: &[Expr], trailing_semicolon: bool) -> EvalResult<Value> {
+        let depth = self.env.len();
+        let mut value = Ok(Value::Unit);
+        for expr in contents {
+            value = self.eval(expr);
+            if value.is_err() {
+                break;
+            }
+        }
+        self.env.truncate(depth);
+        match value {
+            Ok(_) if trailing_semicolon => Ok(Value::Unit),
+            value => value,
+        }
+    }
+
+    fn lookup(&self, name: &str, span: Span) -> EvalResult<Value> {
+        self.env
+            .iter()
+            .rev()
+            .find(|(bound, _)| bound == name)
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| RuntimeError::UndefinedVariable {
+                name: name.to_owned(),
+                span,
+            })
+    }
+
+    // An If without an else evaluates to unit, whichever branch is taken.
+    fn eval_if(
+        &mut self,
+        condition: &Expr,
+        then_expr: &Expr,
+        else_expr: Option<&Expr>,
+    ) -> EvalResult<Value> {
+        let condition = self.eval_bool(condition)?;
+        match (condition, else_expr) {
+            (true, Some(_)) => self.eval(then_expr),
+            (true, None) => self.eval(then_expr).map(|_| Value::Unit),
+            (false, Some(else_expr)) => self.eval(else_expr),
+            (false, None) => Ok(Value::Unit),
+        }
+    }
+
+    // The body is evaluated in the closure's environment, extended with the
+    // function's name (if any) and arguments.
+    fn eval_call(&mut self, span: Span, callee: &Expr, args: &[Expr]) -> EvalResult<Value> {
+        let closure = match self.eval(callee)? {
+            Value::Fn(closure) => closure,
+            value => {
+                return Err(RuntimeError::NotCallable {
+                    found: value.ttype(),
+                    span: callee.span,
+                })
+            }
+        };
+        let function = &closure.function;
+        if function.params.len() != args.len() {
+            return Err(RuntimeError::ArgumentCount {
+                expected: function.params.len(),
+                found: args.len(),
+                span,
+            });
+        }
+        let mut env = closure.env.clone();
+        if let Some(name) = &function.name {
+            env.push((name.name.clone(), Value::Fn(closure.clone())));
+        }
+        for (param, arg) in function.params.iter().zip(args) {
+            let value = self.eval(arg)?;
+            if value.ttype() != param.ttype {
+                return Err(RuntimeError::type_mismatch(
+                    param.ttype.clone(),
+                    &value,
+                    arg.span,
+                ));
+            }
+            env.push((param.name.name.clone(), value));
+        }
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(RuntimeError::CallDepth { span });
+        }
+        let caller_env = mem::replace(&mut self.env, env);
+        self.depth += 1;
+        let value = self.eval(&function.body);
+        self.depth -= 1;
+        self.env = caller_env;
+        value
+    }
+
+    /// Evaluate two expressions of the same type and compare them for equality.
+    fn eval_equals(&mut self, left: &Expr, right: &Expr) -> EvalResult<bool> {
+        let left_value = self.eval(left)?;
+        let right_value = self.eval(right)?;
+        if left_value.ttype() != right_value.ttype() {
+            return Err(RuntimeError::type_mismatch(
+                left_value.ttype(),
+                &right_value,
+                right.span,
+            ));
+        }
+        Ok(left_value == right_value)
+    }
+
+    /// Evaluate two numbers or two strs, which compare lexicographically,
+    /// and whether their ordering is accepted.  No ordering is accepted for
+    /// a NaN float.
+    fn eval_compare(
+        &mut self,
+        left: &Expr,
+        right: &Expr,
+        accept: fn(Ordering) -> bool,
+    ) -> EvalResult<Value> {
+        let ordering = match self.eval(left)? {
+            Value::Int(i) => Some(i.cmp(&self.eval_int(right)?)),
+            Value::Float(x) => x.partial_cmp(&self.eval_float(right)?),
+            Value::Str(s) => Some(s.cmp(&self.eval_str(right)?)),
+            value => return Err(RuntimeError::type_mismatch(Type::Int, &value, left.span)),
+        };
+        Ok(Value::Bool(ordering.is_some_and(accept)))
+    }
+
+    fn eval_bool(&mut self, expr: &Expr) -> EvalResult<bool> {
+        match self.eval(expr)? {
+            Value::Bool(b) => Ok(b),
+            value => Err(RuntimeError::type_mismatch(Type::Bool, &value, expr.span)),
+        }
+    }
+
+    fn eval_int(&mut self, expr: &Expr) -> EvalResult<i64> {
+        match self.eval(expr)? {
+            Value::Int(i) => Ok(i),
+            value => Err(RuntimeError::type_mismatch(Type::Int, &value, expr.span)),
+        }
+    }
+
+    fn eval_float(&mut self, expr: &Expr) -> EvalResult<f64> {
+        match self.eval(expr)? {
+            Value::Float(x) => Ok(x),
+            value => Err(RuntimeError::type_mismatch(Type::Float, &value, expr.span)),
+        }
+    }
+
+    fn eval_str(&mut self, expr: &Expr) -> EvalResult<Rc<str>> {
+        match self.eval(expr)? {
+            Value::Str(s) => Ok(s),
+            value => Err(RuntimeError::type_mismatch(Type::Str, &value, expr.span)),
+        }
+    }
+}
+
+/// Apply an arithmetic operator to ints, which may overflow or divide by zero.
+fn int_arithmetic(span: Span, left: i64, op: BinaryOp, right: i64) -> EvalResult<Value> {
+    let (result, symbol) = match op {
+        BinaryOp::Add => (left.checked_add(right), "+"),
+        BinaryOp::Sub => (left.checked_sub(right), "-"),
+        BinaryOp::Mult => (left.checked_mul(right), "*"),
+        BinaryOp::Div if right == 0 => return Err(RuntimeError::DivisionByZero { span }),
+        BinaryOp::Div => (left.checked_div(right), "/"),
+        _ => unreachable!("{op:?} is not arithmetic"),
+    };
+    checked(result, symbol, span)
+}
+
+/// Apply an arithmetic operator to floats.  Following IEEE 754, division
+/// by zero gives an infinity or NaN.
+fn float_arithmetic(left: f64, op: BinaryOp, right: f64) -> f64 {
+    match op {
+        BinaryOp::Add => left + right,
+        BinaryOp::Sub => left - right,
+        BinaryOp::Mult => left * right,
+        BinaryOp::Div => left / right,
+        _ => unreachable!("{op:?} is not arithmetic"),
+    }
+}
+
+fn checked(result: Option<i64>, op: &'static str, span: Span) -> EvalResult<Value> {
+    result
+        .map(Value::Int)
+        .ok_or(RuntimeError::IntegerOverflow { op, span })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,56 @@
+use super::Value;
+use crate::span::Span;
+use crate::ttype::Type;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RuntimeError {
+    #[error("division by zero at {span}")]
+    DivisionByZero { span: Span },
+    #[error("integer overflow in '{op}' at {span}")]
+    IntegerOverflow { op: &'static str, span: Span },
+    #[error("type mismatch at {span}: expected {expected}, but found {found}")]
+    TypeMismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    #[error("undefined variable '{name}' at {span}")]
+    UndefinedVariable { name: String, span: Span },
+    #[error("expected a function at {span}, but found {found}")]
+    NotCallable { found: Type, span: Span },
+    #[error("expected {expected} arguments at {span}, but found {found}")]
+    ArgumentCount {
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    #[error("maximum call depth exceeded at {span}")]
+    CallDepth { span: Span },
+    #[error("cannot evaluate unparsed expression at {span}")]
+    Unparsed { span: Span },
+}
+
+impl RuntimeError {
+    pub fn type_mismatch(expected: Type, found: &Value, span: Span) -> Self {
+        RuntimeError::TypeMismatch {
+            expected,
+            found: found.ttype(),
+            span,
+        }
+    }
+
+    /// Source span of the expression that failed.
+    pub fn span(&self) -> Span {
+        match self {
+            RuntimeError::DivisionByZero { span }
+            | RuntimeError::IntegerOverflow { span, .. }
+            | RuntimeError::TypeMismatch { span, .. }
+            | RuntimeError::UndefinedVariable { span, .. }
+            | RuntimeError::NotCallable { span, .. }
+            | RuntimeError::ArgumentCount { span, .. }
+            | RuntimeError::CallDepth { span }
+            | RuntimeError::Unparsed { span } => *span,
+        }
+    }
+}
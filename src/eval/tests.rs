@@ -0,0 +1,365 @@
+use super::eval;
+use super::with_eval_stack;
+use super::RuntimeError;
+use super::Value;
+use super::MAX_CALL_DEPTH;
+use crate::parse::parse_expr;
+use crate::span::Span;
+use crate::ttype::Type;
+
+fn eval_str(source: &str) -> Result<Value, RuntimeError> {
+    eval(&parse_expr(source).unwrap())
+}
+
+#[test]
+fn test_eval_bools() {
+    assert_eq!(eval_str("true"), Ok(Value::Bool(true)));
+    assert_eq!(eval_str("not true"), Ok(Value::Bool(false)));
+    assert_eq!(eval_str("not not false"), Ok(Value::Bool(false)));
+    assert_eq!(eval_str("true and false"), Ok(Value::Bool(false)));
+    assert_eq!(eval_str("true or false"), Ok(Value::Bool(true)));
+    assert_eq!(
+        eval_str("true != false or true == true"),
+        Ok(Value::Bool(true))
+    );
+}
+
+#[test]
+fn test_eval_short_circuit() {
+    // The right side would fail if it were evaluated.
+    assert_eq!(eval_str("false and 1 / 0 == 1"), Ok(Value::Bool(false)));
+    assert_eq!(eval_str("true or 1 / 0 == 1"), Ok(Value::Bool(true)));
+}
+
+#[test]
+fn test_eval_ints() {
+    assert_eq!(eval_str("12"), Ok(Value::Int(12)));
+    assert_eq!(eval_str("-12"), Ok(Value::Int(-12)));
+    assert_eq!(eval_str("+12"), Ok(Value::Int(12)));
+    assert_eq!(eval_str("1 + -2"), Ok(Value::Int(-1)));
+    assert_eq!(eval_str("1 * 2 + 3"), Ok(Value::Int(5)));
+    assert_eq!(eval_str("7 / 2"), Ok(Value::Int(3)));
+    assert_eq!(eval_str("10 - 3 - 2"), Ok(Value::Int(5)));
+    assert_eq!(eval_str("8 / 4 / 2"), Ok(Value::Int(1)));
+    assert_eq!(eval_str("1 + 2 >= 3 * 4"), Ok(Value::Bool(false)));
+    assert_eq!(eval_str("1 <= 2 != 3 > 4"), Ok(Value::Bool(true)));
+}
+
+#[test]
+fn test_eval_floats() {
+    assert_eq!(eval_str("1.5 + 2.25"), Ok(Value::Float(3.75)));
+    assert_eq!(eval_str("-1.5 * 2.0"), Ok(Value::Float(-3.0)));
+    assert_eq!(eval_str("+0.5 - 1e-1"), Ok(Value::Float(0.4)));
+    assert_eq!(eval_str("0x10 + 0o10 + 0b10"), Ok(Value::Int(26)));
+    assert_eq!(eval_str("1.0 / 0.0 > 1e308"), Ok(Value::Bool(true)));
+    // NaN is unordered, and not equal to itself.
+    assert_eq!(
+        eval_str("(let nan = 0.0 / 0.0; nan < 1.0 or nan >= 1.0 or nan == nan)"),
+        Ok(Value::Bool(false))
+    );
+    assert_eq!(
+        eval_str("\"{1.0} {2.5e-7} {1e20} {-0.0}\""),
+        Ok(Value::Str("1.0 2.5e-7 1e20 -0.0".into()))
+    );
+    assert_eq!(
+        eval_str("1.5 + 1"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Float,
+            found: Type::Int,
+            span: Span::new(6, 7, 0, 6)
+        })
+    );
+    assert_eq!(
+        eval_str("7 / 2.0"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Int,
+            found: Type::Float,
+            span: Span::new(4, 7, 0, 4)
+        })
+    );
+}
+
+#[test]
+fn test_eval_strings() {
+    let text = |s: &str| Ok(Value::Str(s.into()));
+    assert_eq!(eval_str("\"ab\" + \"\" + \"c\""), text("abc"));
+    assert_eq!(eval_str("len \"caf\\u{e9}\""), Ok(Value::Int(4)));
+    assert_eq!(eval_str("\"ab\" < \"b\""), Ok(Value::Bool(true)));
+    assert_eq!(eval_str("\"b\" <= \"ab\""), Ok(Value::Bool(false)));
+    assert_eq!(eval_str("\"ab\" == \"a\" + \"b\""), Ok(Value::Bool(true)));
+    assert_eq!(
+        eval_str("(let x = 2; \"{x} * {x} = {x * x}, {x > 1}\")"),
+        text("2 * 2 = 4, true")
+    );
+    assert_eq!(
+        eval_str("\"[{\"\\{{1}\\}\"}] {if false (1)} {fn f() -> int (1)}\""),
+        text("[{1}] () <fn f>")
+    );
+    assert_eq!(
+        eval_str("(let s = \"a\"; s + 1)"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Str,
+            found: Type::Int,
+            span: Span::new(18, 19, 0, 18)
+        })
+    );
+    assert_eq!(
+        eval_str("\"{1 / 0}\""),
+        Err(RuntimeError::DivisionByZero {
+            span: Span::new(2, 7, 0, 2)
+        })
+    );
+}
+
+#[test]
+fn test_eval_blocks_and_ifs() {
+    assert_eq!(eval_str("(true or false)"), Ok(Value::Bool(true)));
+    assert_eq!(eval_str("2 * (3 + 4)"), Ok(Value::Int(14)));
+    assert_eq!(eval_str("if true (1) else 2"), Ok(Value::Int(1)));
+    assert_eq!(eval_str("if 1 > 2 (1) else 2"), Ok(Value::Int(2)));
+    assert_eq!(
+        eval_str("if false (1) else if true (2) else 3"),
+        Ok(Value::Int(2))
+    );
+    assert_eq!(eval_str("if true (1)"), Ok(Value::Unit));
+    assert_eq!(eval_str("if false (1)"), Ok(Value::Unit));
+}
+
+#[test]
+fn test_eval_let_bindings() {
+    assert_eq!(
+        eval_str("(let x = 2; let y = x + 1; x * y)"),
+        Ok(Value::Int(6))
+    );
+    assert_eq!(
+        eval_str("(let x = 1; (let x = x + 1; x) + x)"),
+        Ok(Value::Int(3))
+    );
+    assert_eq!(
+        eval_str("(let b = true; if b (1) else 2)"),
+        Ok(Value::Int(1))
+    );
+}
+
+#[test]
+fn test_eval_semicolons() {
+    assert_eq!(eval_str("(1; 2)"), Ok(Value::Int(2)));
+    assert_eq!(eval_str("(1; 2;)"), Ok(Value::Unit));
+    assert_eq!(eval_str("(let x = 1)"), Ok(Value::Unit));
+    assert_eq!(eval_str("(let x = 1;)"), Ok(Value::Unit));
+    // Earlier expressions are still evaluated.
+    assert_eq!(
+        eval_str("(1 / 0; 2)"),
+        Err(RuntimeError::DivisionByZero {
+            span: Span::new(1, 6, 0, 1)
+        })
+    );
+}
+
+#[test]
+fn test_eval_functions() {
+    assert_eq!(
+        eval_str("(let add = fn (x: int, y: int) -> int (x + y); add(1, 2))"),
+        Ok(Value::Int(3))
+    );
+    assert_eq!(eval_str("fn () -> int (1)()"), Ok(Value::Int(1)));
+    assert_eq!(
+        eval_str("(let f = fn (b: bool) -> unit (b;); f(true))"),
+        Ok(Value::Unit)
+    );
+    assert_eq!(
+        eval_str("(fn f(x: int) -> int (x)) == (fn f(x: int) -> int (x))"),
+        Ok(Value::Bool(false))
+    );
+    assert_eq!(
+        eval_str("(let f = fn (x: int) -> int (x); f == f)"),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(
+        eval_str("fn named() -> int (1)").unwrap().to_string(),
+        "<fn named>"
+    );
+}
+
+#[test]
+fn test_eval_closures() {
+    // Closures capture the bindings in scope where they are evaluated.
+    assert_eq!(
+        eval_str(
+            "(
+            let adder = fn (x: int) -> fn(int) -> int (fn (y: int) -> int (x + y));
+            let add2 = adder(2);
+            let x = 10;
+            add2(3) + x
+        )"
+        ),
+        Ok(Value::Int(15))
+    );
+    assert_eq!(
+        eval_str(
+            "(
+            let twice = fn (f: fn(int) -> int, x: int) -> int (f(f(x)));
+            let k = 3;
+            twice(fn (x: int) -> int (x * k), 2)
+        )"
+        ),
+        Ok(Value::Int(18))
+    );
+    // Arguments do not leak into the caller's scope.
+    assert_eq!(
+        eval_str("(let f = fn (y: int) -> int (y); f(1); y)"),
+        Err(RuntimeError::UndefinedVariable {
+            name: "y".to_owned(),
+            span: Span::new(39, 40, 0, 39),
+        })
+    );
+}
+
+#[test]
+fn test_eval_recursion() {
+    assert_eq!(
+        eval_str(
+            "(
+            let fact = fn fact(n: int) -> int (if n <= 1 (1) else (n * fact(n - 1)));
+            fact(10)
+        )"
+        ),
+        Ok(Value::Int(3628800))
+    );
+    assert_eq!(
+        eval_str(
+            "fn fib(n: int) -> int (
+                if n < 2 (n) else (fib(n - 1) + fib(n - 2))
+            )(15)"
+        ),
+        Ok(Value::Int(610))
+    );
+}
+
+#[test]
+fn test_eval_call_errors() {
+    assert_eq!(
+        eval_str("(let x = 1; x(2))"),
+        Err(RuntimeError::NotCallable {
+            found: Type::Int,
+            span: Span::new(12, 13, 0, 12),
+        })
+    );
+    assert_eq!(
+        eval_str("(let f = fn (x: int) -> int (x); f(1, 2))"),
+        Err(RuntimeError::ArgumentCount {
+            expected: 1,
+            found: 2,
+            span: Span::new(33, 40, 0, 33),
+        })
+    );
+    assert_eq!(
+        eval_str("(let f = fn (x: int) -> int (x); f(true))"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(35, 39, 0, 35),
+        })
+    );
+}
+
+#[test]
+fn test_eval_call_depth() {
+    with_eval_stack(|| {
+        assert_eq!(
+            eval_str("fn sum(n: int) -> int (if n == 0 (0) else (n + sum(n - 1)))(3000)"),
+            Ok(Value::Int(4_501_500))
+        );
+        // The deepest recursion allowed, in calls of a closure with captures.
+        let count =
+            "(let one = 1; fn count(n: int) -> int (if n <= one (one) else (one + count(n - 1))))";
+        assert_eq!(
+            eval_str(&format!("{count}({MAX_CALL_DEPTH})")),
+            Ok(Value::Int(MAX_CALL_DEPTH as i64))
+        );
+        assert_eq!(
+            eval_str("fn f(n: int) -> int (f(n))(1)"),
+            Err(RuntimeError::CallDepth {
+                span: Span::new(20, 26, 0, 20),
+            })
+        );
+    });
+}
+
+#[test]
+fn test_eval_errors() {
+    assert_eq!(
+        eval_str("1 / 0"),
+        Err(RuntimeError::DivisionByZero {
+            span: Span::new(0, 5, 0, 0)
+        })
+    );
+    assert_eq!(
+        eval_str("1 + (9223372036854775807 + 1)"),
+        Err(RuntimeError::IntegerOverflow {
+            op: "+",
+            span: Span::new(4, 29, 0, 4)
+        })
+    );
+    assert_eq!(
+        eval_str("-(-9223372036854775807 - 1)"),
+        Err(RuntimeError::IntegerOverflow {
+            op: "-",
+            span: Span::new(0, 27, 0, 0)
+        })
+    );
+    assert_eq!(
+        eval_str("(-9223372036854775807 - 1) / -1"),
+        Err(RuntimeError::IntegerOverflow {
+            op: "/",
+            span: Span::new(0, 31, 0, 0)
+        })
+    );
+    assert_eq!(
+        eval_str("1 and true"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Bool,
+            found: Type::Int,
+            span: Span::new(0, 1, 0, 0)
+        })
+    );
+    assert_eq!(
+        eval_str("not 3"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Bool,
+            found: Type::Int,
+            span: Span::new(4, 5, 0, 4)
+        })
+    );
+    assert_eq!(
+        eval_str("true + 1"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(0, 4, 0, 0)
+        })
+    );
+    assert_eq!(
+        eval_str("1 == true"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(5, 9, 0, 5)
+        })
+    );
+    assert_eq!(
+        eval_str("if 1 (2)"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Bool,
+            found: Type::Int,
+            span: Span::new(3, 4, 0, 3)
+        })
+    );
+    assert_eq!(
+        eval_str("(let x = 1; x) + x"),
+        Err(RuntimeError::UndefinedVariable {
+            name: "x".to_owned(),
+            span: Span::new(17, 18, 0, 17)
+        })
+    );
+}
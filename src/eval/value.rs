@@ -0,0 +1,70 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::expr::Function;
+use crate::ttype::Type;
+
+/// Runtime value produced by the Evaluator.
+#[derive(Clone, Debug)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(Rc<str>),
+    Unit,
+    Fn(Rc<Closure>),
+}
+
+/// Function literal together with the bindings in scope where it was evaluated.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Function,
+    pub env: Vec<(String, Value)>,
+}
+
+impl Value {
+    pub fn ttype(&self) -> Type {
+        match self {
+            Value::Bool(_) => Type::Bool,
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Str(_) => Type::Str,
+            Value::Unit => Type::Unit,
+            Value::Fn(closure) => closure.function.ttype(),
+        }
+    }
+}
+
+/// Closures are equal only if they are the same closure, and floats follow
+/// IEEE 754, so NaN is not equal to itself.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Bool(left), Value::Bool(right)) => left == right,
+            (Value::Int(left), Value::Int(right)) => left == right,
+            (Value::Float(left), Value::Float(right)) => left == right,
+            (Value::Str(left), Value::Str(right)) => left == right,
+            (Value::Unit, Value::Unit) => true,
+            (Value::Fn(left), Value::Fn(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Int(i) => write!(f, "{i}"),
+            // As a literal, with the `.0` of whole floats.
+            Value::Float(x) => write!(f, "{x:?}"),
+            // The text itself, as a string interpolates it.
+            Value::Str(s) => f.write_str(s),
+            Value::Unit => write!(f, "()"),
+            Value::Fn(closure) => match &closure.function.name {
+                Some(name) => write!(f, "<fn {}>", name.name),
+                None => write!(f, "<fn>"),
+            },
+        }
+    }
+}
@@ -0,0 +1,548 @@
+mod doc;
+mod trivia;
+
+use std::cell::Cell;
+
+use doc::Doc;
+use trivia::Comment;
+use trivia::Trivia;
+
+use crate::ast::display::binding;
+use crate::ast::display::operand_bindings;
+use crate::ast::display::unary_operand_binding;
+use crate::ast::display::unary_separator;
+use crate::ast::display::Binding;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Function;
+use crate::ast::expr::Literal;
+use crate::parse::parse_expr;
+use crate::parse::string::str_segments;
+use crate::parse::string::StrSegment;
+use crate::parse::ParseError;
+use crate::parse::TokenType;
+use logos::Logos;
+
+/// Layout settings for format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Columns to fit lines within, where possible.
+    pub width: usize,
+    /// Columns to indent each nested level by.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            indent: 4,
+        }
+    }
+}
+
+/// Reformat source in the standard style.  Each group of parentheses, if
+/// chain or binary operator chain goes on one line if it fits, and otherwise
+/// puts each of its parts on its own line.  Comments are kept in order, and
+/// blank lines between the items of a block are kept.  A string with
+/// comments in its interpolations is kept as written.  Formatting the
+/// result again leaves it unchanged.
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, ParseError> {
+    let expr = parse_expr(source)?;
+    let formatter = Formatter {
+        source,
+        indent: options.indent,
+        trivia: Trivia::new(source),
+        next_comment: Cell::new(0),
+    };
+    let mut output = doc::render(&formatter.file(&expr), options.width);
+    output.push('\n');
+    Ok(output)
+}
+
+struct Formatter<'source> {
+    source: &'source str,
+    indent: usize,
+    trivia: Trivia,
+    /// Index of the first comment not yet laid out.  Nodes are laid out in
+    /// source order, each taking the comments before it.
+    next_comment: Cell<usize>,
+}
+
+impl Formatter<'_> {
+    fn file(&self, expr: &Expr) -> Doc {
+        let mut docs = self.leading_comments(self.first_token_start(expr), true);
+        docs.push(self.expr(expr));
+        let mut end = expr.span.end;
+        for comment in self.take_comments(usize::MAX) {
+            if self.trivia.same_line(end, comment.start) {
+                docs.push(Doc::Suffix(format!(" {}", comment.text)));
+            } else {
+                docs.push(Doc::HardLine);
+                if self.trivia.blank_line_between(end, comment.start) {
+                    docs.push(Doc::HardLine);
+                }
+                docs.push(Doc::text(&comment.text));
+            }
+            end = comment.end;
+        }
+        Doc::Concat(docs)
+    }
+
+    fn expr(&self, expr: &Expr) -> Doc {
+        let mut docs = self.leading_comments(self.first_token_start(expr), false);
+        docs.push(match &expr.kind {
+            // Numbers keep the form they are written in, as in `0xff`.
+            ExprKind::Literal(Literal::Int(_) | Literal::Float(_)) => {
+                Doc::text(self.token_text(self.first_token_start(expr)))
+            }
+            // A string stays on one line, with its interpolations, and as
+            // written if they hold comments.
+            ExprKind::Interpolate(_) => {
+                let text = self.token_text(self.first_token_start(expr));
+                if has_comments(text) {
+                    Doc::text(text)
+                } else {
+                    Doc::text(expr.to_string())
+                }
+            }
+            ExprKind::Literal(_) | ExprKind::Var(_) | ExprKind::Error => {
+                Doc::text(expr.to_string())
+            }
+            ExprKind::Unary { op, right } => {
+                // A comment after the operator stays apart from it.
+                let right_start = self.first_token_start(right);
+                let separator = if self.next_comment_start(right_start) < right_start {
+                    " "
+                } else {
+                    unary_separator(*op, right)
+                };
+                Doc::Concat(vec![
+                    Doc::text(format!("{op}{separator}")),
+                    self.operand(right, unary_operand_binding(*op)),
+                ])
+            }
+            ExprKind::Binary { .. } => self.binary(expr),
+            ExprKind::Block {
+                contents,
+                trailing_semicolon,
+            } => Doc::group(self.block(contents, *trailing_semicolon, expr.span.end)),
+            ExprKind::Let { name, value } => Doc::Concat(vec![
+                Doc::text(format!("let {} = ", name.name)),
+                self.operand(value, Binding::If),
+            ]),
+            ExprKind::If { .. } => self.if_chain(expr),
+            ExprKind::Fn(function) => self.function(function, self.first_token_start(expr)),
+            ExprKind::Call { callee, args } => Doc::Concat(vec![
+                self.operand(callee, Binding::Primary),
+                self.list(
+                    callee.span.end,
+                    args.iter().map(|arg| arg.span.start),
+                    |i| self.operand(&args[i], Binding::If),
+                ),
+            ]),
+        });
+        if docs.len() == 1 {
+            docs.pop().unwrap()
+        } else {
+            Doc::Concat(docs)
+        }
+    }
+
+    /// Expr in parentheses if it binds looser than min.
+    fn operand(&self, expr: &Expr, min: Binding) -> Doc {
+        if binding(expr) < min {
+            Doc::Concat(vec![Doc::text("("), self.expr(expr), Doc::text(")")])
+        } else {
+            self.expr(expr)
+        }
+    }
+
+    /// Operators of one precedence, as in `a + b - c`, break before each operator together.
+    fn binary(&self, expr: &Expr) -> Doc {
+        // Operands from the right, down the left side of the chain.
+        let mut operands = Vec::new();
+        let mut current = expr;
+        let (head, head_min) = loop {
+            let ExprKind::Binary { left, op, right } = &current.kind else {
+                unreachable!("binary chains are made of binary expressions");
+            };
+            let (left_min, right_min) = operand_bindings(*op);
+            operands.push((*op, right, right_min));
+            if binding(left) == binding(current) && binding(left) >= left_min {
+                current = left;
+            } else {
+                break (left, left_min);
+            }
+        };
+        let head = self.operand(head, head_min);
+        let tail = operands
+            .into_iter()
+            .rev()
+            .map(|(op, right, min)| {
+                Doc::Concat(vec![
+                    Doc::Line,
+                    Doc::text(format!("{op} ")),
+                    self.operand(right, min),
+                ])
+            })
+            .collect();
+        Doc::group(Doc::Concat(vec![
+            head,
+            Doc::nest(self.indent, Doc::Concat(tail)),
+        ]))
+    }
+
+    /// Items of a block in parentheses, one per line if the enclosing group
+    /// breaks.  End is the offset just past the closing parenthesis.
+    fn block(&self, contents: &[Expr], trailing_semicolon: bool, end: usize) -> Doc {
+        let mut items = vec![Doc::SoftLine];
+        for (i, item) in contents.iter().enumerate() {
+            if i > 0 {
+                items.push(Doc::text(";"));
+                let previous = contents[i - 1].span.end;
+                let ends_line = self.trailing_comments(&mut items, previous, item.span.start);
+                let next = self.next_comment_start(self.first_token_start(item));
+                let after = self.previous_comment_end(previous);
+                items.push(if ends_line { Doc::HardLine } else { Doc::Line });
+                if self.trivia.blank_line_between(after, next) {
+                    items.push(Doc::HardLine);
+                }
+            }
+            items.extend(self.leading_comments(self.first_token_start(item), true));
+            items.push(self.expr(item));
+        }
+        if trailing_semicolon {
+            items.push(Doc::text(";"));
+        }
+        if let Some(last) = contents.last() {
+            if self.trailing_comments(&mut items, last.span.end, end) {
+                items.push(Doc::BreakParent);
+            }
+        }
+        for comment in self.take_comments(end) {
+            items.push(Doc::HardLine);
+            items.push(Doc::text(&comment.text));
+        }
+        Doc::Concat(vec![
+            Doc::text("("),
+            Doc::nest(self.indent, Doc::Concat(items)),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ])
+    }
+
+    /// Expr in the parentheses the parser requires, as for a then branch,
+    /// breaking with the enclosing group.
+    fn body(&self, expr: &Expr) -> Doc {
+        match &expr.kind {
+            ExprKind::Block {
+                contents,
+                trailing_semicolon,
+            } => self.block(contents, *trailing_semicolon, expr.span.end),
+            _ => self.block(std::slice::from_ref(expr), false, expr.span.end),
+        }
+    }
+
+    /// An if and the ifs in its else branches, which break together.
+    /// A broken final else branch gets parentheses like the then branches.
+    fn if_chain(&self, expr: &Expr) -> Doc {
+        let mut docs = Vec::new();
+        let mut current = expr;
+        loop {
+            let ExprKind::If {
+                condition,
+                then_expr,
+                else_expr,
+            } = &current.kind
+            else {
+                unreachable!("if chains are made of if expressions");
+            };
+            docs.push(Doc::text("if "));
+            docs.push(self.operand(condition, Binding::If));
+            // Comments stay on their side of the then branch's `(` and of
+            // the `else`.
+            let open = self.find_token(condition.span.end, TokenType::OpenParen);
+            if self.trailing_comments(&mut docs, condition.span.end, open) {
+                docs.push(Doc::HardLine);
+            } else {
+                docs.push(Doc::text(" "));
+            }
+            docs.push(self.body(then_expr));
+            let Some(else_expr) = else_expr else {
+                break;
+            };
+            let else_start = self.find_token(then_expr.span.end, TokenType::Else);
+            if self.trailing_comments(&mut docs, then_expr.span.end, else_start) {
+                docs.push(Doc::HardLine);
+                docs.push(Doc::text("else"));
+            } else {
+                docs.push(Doc::text(" else"));
+            }
+            let else_end = else_start + "else".len();
+            if self.trailing_comments(&mut docs, else_end, self.first_token_start(else_expr)) {
+                docs.push(Doc::HardLine);
+            } else {
+                docs.push(Doc::text(" "));
+            }
+            match else_expr.kind {
+                ExprKind::If { .. } => {
+                    let start = self.first_token_start(else_expr);
+                    docs.extend(self.leading_comments(start, false));
+                    current = else_expr;
+                }
+                ExprKind::Block { .. } => {
+                    docs.push(self.body(else_expr));
+                    break;
+                }
+                _ => {
+                    // The parenthesized layout may take comments from just
+                    // inside the closing parenthesis, which then must stay.
+                    let start = self.next_comment.get();
+                    let flat = self.operand(else_expr, Binding::If);
+                    let flat_end = self.next_comment.replace(start);
+                    let broken = self.body(else_expr);
+                    if self.next_comment.get() == flat_end {
+                        docs.push(Doc::if_break(broken, flat));
+                    } else {
+                        docs.push(broken);
+                    }
+                    break;
+                }
+            }
+        }
+        Doc::group(Doc::Concat(docs))
+    }
+
+    /// A function whose `fn` starts at offset.
+    fn function(&self, function: &Function, offset: usize) -> Doc {
+        let name = match &function.name {
+            Some(name) => format!("fn {}", name.name),
+            None => "fn ".to_owned(),
+        };
+        let params = &function.params;
+        let param = |i: usize| {
+            let param = &params[i];
+            let mut docs = self.leading_comments(param.name.span.start, false);
+            docs.push(Doc::text(format!("{}: {}", param.name.name, param.ttype)));
+            Doc::Concat(docs)
+        };
+        Doc::Concat(vec![
+            Doc::text(name),
+            self.list(
+                self.find_token(offset, TokenType::OpenParen),
+                params.iter().map(|param| param.name.span.start),
+                param,
+            ),
+            Doc::text(format!(" -> {} ", function.ret)),
+            Doc::group(self.body(&function.body)),
+        ])
+    }
+
+    /// Comma-separated items in the parentheses opening at open, one per
+    /// line if they do not fit.  Starts are the offsets of the items, each
+    /// laid out by item.  Comments on the line of the `,` or `)` after an
+    /// item follow it, on the same side of the `,` unless they end the line.
+    fn list(
+        &self,
+        open: usize,
+        starts: impl Iterator<Item = usize>,
+        item: impl Fn(usize) -> Doc,
+    ) -> Doc {
+        let starts: Vec<usize> = starts.collect();
+        let mut items = vec![Doc::SoftLine];
+        let mut close = None;
+        for (i, &start) in starts.iter().enumerate() {
+            items.push(item(i));
+            let (last, end) = self.item_bounds(start);
+            let mut comments = Vec::new();
+            let mut ends_line = self.trailing_comments(&mut comments, last, end);
+            close = Some(end);
+            let Some(&next) = starts.get(i + 1) else {
+                items.extend(comments);
+                if ends_line {
+                    items.push(Doc::BreakParent);
+                }
+                break;
+            };
+            if ends_line {
+                items.push(Doc::text(","));
+                items.extend(comments);
+            } else {
+                items.extend(comments);
+                items.push(Doc::text(","));
+                ends_line = self.trailing_comments(&mut items, end, next);
+            }
+            items.push(if ends_line { Doc::HardLine } else { Doc::Line });
+        }
+        match close {
+            Some(close) => {
+                for comment in self.take_comments(close) {
+                    items.push(Doc::HardLine);
+                    items.push(Doc::text(&comment.text));
+                }
+            }
+            // An empty list keeps the comments inside it.
+            None => {
+                let comments = self.take_comments(self.item_bounds(open + 1).1);
+                if comments.is_empty() {
+                    return Doc::text("()");
+                }
+                for (i, comment) in comments.iter().enumerate() {
+                    if i > 0 {
+                        items.push(Doc::Line);
+                    }
+                    items.push(Doc::text(&comment.text));
+                    if comment.ends_line() {
+                        items.push(Doc::BreakParent);
+                    }
+                }
+            }
+        }
+        Doc::group(Doc::Concat(vec![
+            Doc::text("("),
+            Doc::nest(self.indent, Doc::Concat(items)),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]))
+    }
+
+    // Comments
+
+    /// Start of the first token of expr other than parentheses around it.
+    /// Comments before this go before the whole of expr.
+    fn first_token_start(&self, expr: &Expr) -> usize {
+        match &expr.kind {
+            ExprKind::Binary { left, .. } => self.first_token_start(left),
+            ExprKind::Call { callee, .. } => self.first_token_start(callee),
+            // A block's span may start at its own parenthesis.
+            ExprKind::Block { .. } => expr.span.start,
+            _ => self.trivia.skip_open_parens(expr.span.start),
+        }
+    }
+
+    /// Text of the token starting at offset.
+    fn token_text(&self, offset: usize) -> &str {
+        let mut lexer = TokenType::lexer(&self.source[offset..]);
+        lexer.next();
+        lexer.slice()
+    }
+
+    /// Start of the first token of the given type at or after offset.
+    fn find_token(&self, offset: usize, token_type: TokenType) -> usize {
+        TokenType::lexer(&self.source[offset..])
+            .spanned()
+            .find(|(found, _)| *found == Ok(token_type))
+            .map_or(self.source.len(), |(_, span)| offset + span.start)
+    }
+
+    /// Offsets just past the last token of the list item starting at
+    /// offset, and of the `,` or `)` ending it.
+    fn item_bounds(&self, offset: usize) -> (usize, usize) {
+        let mut depth = 0;
+        let mut last = offset;
+        for (token_type, span) in TokenType::lexer(&self.source[offset..]).spanned() {
+            match token_type {
+                Ok(TokenType::CloseParen | TokenType::Comma) if depth == 0 => {
+                    return (last, offset + span.start);
+                }
+                Ok(token_type) if token_type.is_trivia() => continue,
+                Ok(TokenType::OpenParen) => depth += 1,
+                Ok(TokenType::CloseParen) => depth -= 1,
+                _ => (),
+            }
+            last = offset + span.end;
+        }
+        (last, self.source.len())
+    }
+
+    /// Take the comments not yet laid out that start before offset.
+    fn take_comments(&self, offset: usize) -> &[Comment] {
+        let start = self.next_comment.get();
+        let count = self.trivia.comments[start..]
+            .iter()
+            .take_while(|comment| comment.start < offset)
+            .count();
+        self.next_comment.set(start + count);
+        &self.trivia.comments[start..start + count]
+    }
+
+    /// Start of the next comment not yet laid out, if before offset, else offset.
+    fn next_comment_start(&self, offset: usize) -> usize {
+        match self.trivia.comments.get(self.next_comment.get()) {
+            Some(comment) if comment.start < offset => comment.start,
+            _ => offset,
+        }
+    }
+
+    /// End of the last comment laid out, if after offset, else offset.
+    fn previous_comment_end(&self, offset: usize) -> usize {
+        match self.next_comment.get().checked_sub(1) {
+            Some(i) if self.trivia.comments[i].end > offset => self.trivia.comments[i].end,
+            _ => offset,
+        }
+    }
+
+    /// Comments before offset, each followed by a space or, where it must
+    /// end its line, a newline.  At the start of a line, comments that were
+    /// on their own line stay so, along with a blank line after them.
+    fn leading_comments(&self, offset: usize, line_start: bool) -> Vec<Doc> {
+        let comments = self.take_comments(offset);
+        let mut docs = Vec::new();
+        for (i, comment) in comments.iter().enumerate() {
+            docs.push(Doc::text(&comment.text));
+            if comment.ends_line() || (line_start && comment.own_line) {
+                docs.push(Doc::HardLine);
+                let next = comments.get(i + 1).map_or(offset, |next| next.start);
+                if line_start && self.trivia.blank_line_between(comment.end, next) {
+                    docs.push(Doc::HardLine);
+                }
+            } else {
+                docs.push(Doc::text(" "));
+            }
+        }
+        docs
+    }
+
+    /// Add the comments before the offset before, and on the same line as
+    /// offset, to docs.  Return whether the last must end the line.
+    fn trailing_comments(&self, docs: &mut Vec<Doc>, offset: usize, before: usize) -> bool {
+        let mut ends_line = false;
+        while let Some(comment) = self.trivia.comments.get(self.next_comment.get()) {
+            if comment.start < offset
+                || comment.start >= before
+                || !self.trivia.same_line(offset, comment.start)
+            {
+                break;
+            }
+            self.next_comment.set(self.next_comment.get() + 1);
+            docs.push(Doc::Suffix(format!(" {}", comment.text)));
+            ends_line = comment.ends_line();
+        }
+        ends_line
+    }
+}
+
+/// Whether the interpolations of a string literal, or of the strings
+/// within them, hold comments, which the syntax tree does not record.
+fn has_comments(literal: &str) -> bool {
+    let Ok(segments) = str_segments(literal) else {
+        return false;
+    };
+    segments.iter().any(|segment| match segment {
+        StrSegment::Text(_) => false,
+        StrSegment::Interpolation(range) => {
+            let source = &literal[range.clone()];
+            TokenType::lexer(source)
+                .spanned()
+                .any(|(token_type, span)| match token_type {
+                    Ok(TokenType::LineComment | TokenType::BlockComment) => true,
+                    Ok(TokenType::Str) => has_comments(&source[span]),
+                    _ => false,
+                })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests;
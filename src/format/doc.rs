@@ -0,0 +1,189 @@
+/// Layout of formatted source, where each group goes on one line if it fits
+/// and otherwise breaks all of its own lines.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Doc {
+    Text(String),
+    /// Text that does not count toward the width, for a comment ending a line.
+    Suffix(String),
+    /// A space, or a newline if the enclosing group breaks.
+    Line,
+    /// Nothing, or a newline if the enclosing group breaks.
+    SoftLine,
+    /// Always a newline.  Every group containing one breaks.
+    HardLine,
+    /// Nothing, but every group containing one breaks.
+    BreakParent,
+    /// Indent the lines of the inner doc by a number of columns.
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+    /// The first doc if the enclosing group breaks, else the second.
+    IfBreak(Box<Doc>, Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Self {
+        Doc::Text(text.into())
+    }
+
+    pub fn nest(indent: usize, doc: Doc) -> Self {
+        Doc::Nest(indent, Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Self {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn if_break(broken: Doc, flat: Doc) -> Self {
+        Doc::IfBreak(Box::new(broken), Box::new(flat))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Doc to lay out, with the indentation and mode of its enclosing group.
+type Command<'doc> = (usize, Mode, &'doc Doc);
+
+/// Lay out doc to fit within width columns where possible.
+/// Lines have no trailing whitespace.
+pub fn render(doc: &Doc, width: usize) -> String {
+    let mut output = String::new();
+    let mut col = 0;
+    // Indentation is written with the first text of a line, so blank lines stay empty.
+    let mut pending_indent = None;
+    let mut commands: Vec<Command> = vec![(0, Mode::Break, doc)];
+    while let Some((indent, mode, doc)) = commands.pop() {
+        match doc {
+            Doc::Text(text) | Doc::Suffix(text) => {
+                if let Some(indent) = pending_indent.take() {
+                    output.extend(std::iter::repeat_n(' ', indent));
+                }
+                output.push_str(text);
+                col += text.len();
+            }
+            Doc::Line if mode == Mode::Flat => {
+                output.push(' ');
+                col += 1;
+            }
+            Doc::SoftLine if mode == Mode::Flat => (),
+            Doc::BreakParent => (),
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                output.push('\n');
+                pending_indent = Some(indent);
+                col = indent;
+            }
+            Doc::Nest(extra, inner) => commands.push((indent + extra, mode, inner)),
+            Doc::Group(inner) => {
+                let flat = (indent, Mode::Flat, &**inner);
+                if mode == Mode::Flat || fits(width as isize - col as isize, flat, &commands) {
+                    commands.push(flat);
+                } else {
+                    commands.push((indent, Mode::Break, inner));
+                }
+            }
+            Doc::IfBreak(broken, flat) => match mode {
+                Mode::Break => commands.push((indent, mode, broken)),
+                Mode::Flat => commands.push((indent, mode, flat)),
+            },
+            Doc::Concat(docs) => {
+                commands.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+            }
+        }
+    }
+    output
+}
+
+/// Whether next fits in the remaining columns, along with the rest of its
+/// line from the commands after it.
+fn fits(mut remaining: isize, next: Command, rest: &[Command]) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut commands = vec![next];
+    while remaining >= 0 {
+        let Some((indent, mode, doc)) = commands.pop().or_else(|| rest.next().copied()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.len() as isize,
+            Doc::Suffix(_) => (),
+            Doc::Line | Doc::SoftLine if mode == Mode::Break => return true,
+            Doc::Line => remaining -= 1,
+            Doc::SoftLine => (),
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::BreakParent if mode == Mode::Flat => return false,
+            Doc::BreakParent => (),
+            Doc::Nest(_, inner) | Doc::Group(inner) => commands.push((indent, mode, inner)),
+            Doc::IfBreak(broken, flat) => match mode {
+                Mode::Break => commands.push((indent, mode, broken)),
+                Mode::Flat => commands.push((indent, mode, flat)),
+            },
+            Doc::Concat(docs) => {
+                commands.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> Doc {
+        let mut docs = vec![Doc::text("(")];
+        let mut inner = vec![Doc::SoftLine];
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                inner.push(Doc::text(","));
+                inner.push(Doc::Line);
+            }
+            inner.push(Doc::text(*item));
+        }
+        docs.push(Doc::nest(2, Doc::Concat(inner)));
+        docs.push(Doc::SoftLine);
+        docs.push(Doc::text(")"));
+        Doc::group(Doc::Concat(docs))
+    }
+
+    #[test]
+    fn test_groups() {
+        let doc = list(&["a", "b", "c"]);
+        assert_eq!(render(&doc, 9), "(a, b, c)");
+        assert_eq!(render(&doc, 8), "(\n  a,\n  b,\n  c\n)");
+
+        let nested = Doc::Concat(vec![Doc::text("f"), list(&["a", "b"])]);
+        let outer = Doc::group(Doc::Concat(vec![
+            Doc::text("x"),
+            Doc::nest(1, Doc::Concat(vec![Doc::Line, nested])),
+        ]));
+        assert_eq!(render(&outer, 9), "x f(a, b)");
+        assert_eq!(render(&outer, 8), "x\n f(a, b)");
+        assert_eq!(render(&outer, 7), "x\n f(\n   a,\n   b\n )");
+    }
+
+    #[test]
+    fn test_hard_lines() {
+        let doc = Doc::group(Doc::Concat(vec![
+            Doc::text("a"),
+            Doc::nest(2, Doc::Concat(vec![Doc::HardLine, Doc::HardLine])),
+            Doc::text("b"),
+            Doc::if_break(Doc::text(";"), Doc::text(",")),
+        ]));
+        assert_eq!(render(&doc, 80), "a\n\n  b;");
+    }
+
+    #[test]
+    fn test_suffixes() {
+        let doc = Doc::group(Doc::Concat(vec![
+            Doc::text("a"),
+            Doc::Line,
+            Doc::text("b"),
+            Doc::Suffix(" // c".to_owned()),
+        ]));
+        assert_eq!(render(&doc, 3), "a b // c");
+        assert_eq!(render(&doc, 2), "a\nb // c");
+    }
+}
@@ -0,0 +1,284 @@
+use logos::Logos;
+
+use super::format;
+use super::FormatOptions;
+use crate::ast::expr::Expr;
+use crate::parse::parse_expr;
+use crate::parse::TokenType;
+use crate::parse::Tokenizer;
+use crate::test_util::CORPUS;
+
+fn fmt(source: &str) -> String {
+    format(source, &FormatOptions::default()).unwrap()
+}
+
+fn fmt_width(source: &str, width: usize) -> String {
+    let options = FormatOptions {
+        width,
+        ..FormatOptions::default()
+    };
+    format(source, &options).unwrap()
+}
+
+#[test]
+fn test_one_line() {
+    assert_eq!(fmt("1+2*  3"), "1 + 2 * 3\n");
+    assert_eq!(fmt("(1 + 2) * 3"), "(1 + 2) * 3\n");
+    assert_eq!(fmt("((x))"), "x\n");
+    assert_eq!(fmt("( let x = 1 ;x ; )"), "(let x = 1; x;)\n");
+    assert_eq!(
+        fmt("if a (1)else if b (2) else 3"),
+        "if a (1) else if b (2) else 3\n"
+    );
+    assert_eq!(
+        fmt("fn f ( x:int )->int(x)( 1 )"),
+        "fn f(x: int) -> int (x)(1)\n"
+    );
+    assert_eq!(fmt("- -x"), "- -x\n");
+    assert_eq!(fmt("0xff*1_000+ 1.50e3"), "0xff * 1_000 + 1.50e3\n");
+}
+
+#[test]
+fn test_blocks() {
+    let source = "(let x = 1; let y = 2; x + y)";
+    assert_eq!(fmt_width(source, 30), "(let x = 1; let y = 2; x + y)\n");
+    assert_eq!(
+        fmt_width(source, 20),
+        "(\n    let x = 1;\n    let y = 2;\n    x + y\n)\n"
+    );
+    // Blank lines between items are kept, but not blank lines elsewhere.
+    assert_eq!(
+        fmt("(\n\n  let x = 1;\n\n\n  x;\n\n)"),
+        "(\n    let x = 1;\n\n    x;\n)\n"
+    );
+}
+
+#[test]
+fn test_if_chains() {
+    let source = "if x < 10 (small) else if x < 100 (medium) else large";
+    assert_eq!(fmt_width(source, 60), format!("{source}\n"));
+    assert_eq!(
+        fmt_width(source, 40),
+        "\
+if x < 10 (
+    small
+) else if x < 100 (
+    medium
+) else (
+    large
+)
+"
+    );
+}
+
+#[test]
+fn test_binary_chains() {
+    let source = "first_value + second_value * factor - third_value";
+    assert_eq!(
+        fmt_width(source, 30),
+        "\
+first_value
+    + second_value * factor
+    - third_value
+"
+    );
+    assert_eq!(
+        fmt_width("alpha and beta or gamma and delta", 22),
+        "alpha and beta\n    or gamma and delta\n"
+    );
+}
+
+#[test]
+fn test_calls_and_functions() {
+    let options = FormatOptions {
+        width: 30,
+        indent: 2,
+    };
+    let source = "(let add = fn (first: int, second: int) -> int (first + second); add(1, 2))";
+    assert_eq!(
+        format(source, &options).unwrap(),
+        "\
+(
+  let add = fn (
+    first: int,
+    second: int
+  ) -> int (first + second);
+  add(1, 2)
+)
+"
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(format("1 +", &FormatOptions::default()).is_err());
+}
+
+#[test]
+fn test_corpus() {
+    let mut count = 0;
+    for source in CORPUS {
+        let Ok(expr) = parse_expr(source) else {
+            continue;
+        };
+        for width in [10, 40, 80] {
+            let options = FormatOptions { width, indent: 4 };
+            let formatted = format(source, &options).unwrap();
+            let reparsed = parse_expr(&formatted).unwrap();
+            assert_eq!(
+                reparsed.without_spans(),
+                expr.clone().without_spans(),
+                "{source:?} formatted as {formatted:?}"
+            );
+            let again = format(&formatted, &options).unwrap();
+            assert_eq!(again, formatted, "formatting {source:?} is not idempotent");
+        }
+        count += 1;
+    }
+    assert!(count > 100);
+}
+
+#[test]
+fn test_comments() {
+    assert_eq!(fmt("1 + /* two */ 2 // end"), "1 + /* two */ 2 // end\n");
+    assert_eq!(
+        fmt("// header\n\n(let x = 1; // one\n  /* own line */\n x // last\n)"),
+        "\
+// header
+
+(
+    let x = 1; // one
+    /* own line */
+    x // last
+)
+"
+    );
+    assert_eq!(
+        fmt("(a;\n\n// about b\n\nb)"),
+        "(\n    a;\n\n    // about b\n\n    b\n)\n"
+    );
+    // A comment that would run into the following code ends its line.
+    assert_eq!(fmt("f(// x\nx)"), "f(\n    // x\n    x\n)\n");
+    assert_eq!(fmt("1 + 2 /* a\n b */"), "1 + 2 /* a\n b */\n");
+    // Comments in lists stay with the item before them.
+    assert_eq!(
+        fmt("fn f(a: int /* p */) -> int (a)"),
+        "fn f(a: int /* p */) -> int (a)\n"
+    );
+    assert_eq!(fmt("f(a /* p */ , b)"), "f(a /* p */, b)\n");
+    assert_eq!(fmt("f(a // p\n, b)"), "f(\n    a, // p\n    b\n)\n");
+    assert_eq!(fmt("f(a, b /* q */\n)"), "f(a, b /* q */)\n");
+    // An empty list keeps the comments inside it.
+    assert_eq!(fmt("f(/* a */)"), "f(/* a */)\n");
+    assert_eq!(
+        fmt("fn f(/* a */) -> int (1)"),
+        "fn f(/* a */) -> int (1)\n"
+    );
+    assert_eq!(fmt("f(// a\n)"), "f(\n    // a\n)\n");
+    // Comments stay on their side of a then branch's `(` and of an `else`.
+    assert_eq!(
+        fmt("if a // c1\n(1) /* c2 */ else // c3\n2"),
+        "if a // c1\n(\n    1\n) /* c2 */ else // c3\n(\n    2\n)\n"
+    );
+    assert_eq!(
+        fmt("if a /* c */ (1) else /* d */ 2"),
+        "if a /* c */ (1) else /* d */ 2\n"
+    );
+    // A comment after a unary operator keeps them apart.
+    assert_eq!(fmt("- /* c */ x"), "- /* c */ x\n");
+    assert_eq!(fmt("-x"), "-x\n");
+    // Comments in interpolations keep the string as written.
+    assert_eq!(fmt(r#""a{x /* keep */}b""#), "\"a{x /* keep */}b\"\n");
+    assert_eq!(
+        fmt(r#"f( "a{ "{y /* c */}" }",1)"#),
+        "f(\"a{ \"{y /* c */}\" }\", 1)\n"
+    );
+    assert_eq!(fmt(r#""a{ x }b""#), "\"a{x}b\"\n");
+}
+
+/// Parseable sources from the test corpus.
+fn corpus() -> Vec<(String, Expr)> {
+    CORPUS
+        .iter()
+        .filter_map(|&source| {
+            let expr = parse_expr(source).ok()?.without_spans();
+            Some((source.to_owned(), expr))
+        })
+        .collect()
+}
+
+/// Offsets after each token of source, except where a `(` follows
+/// immediately, as a comment there would turn a call into something else.
+fn token_ends(source: &str) -> Vec<usize> {
+    let mut tokenizer = Tokenizer::new(TokenType::lexer(source));
+    let mut ends = Vec::new();
+    while let Some(token) = tokenizer.advance() {
+        if !source[token.data.span.end..].starts_with('(') {
+            ends.push(token.data.span.end);
+        }
+    }
+    ends
+}
+
+/// Assert that formatting source keeps expr and the comments, in order,
+/// and that formatting again changes nothing.
+fn assert_keeps_comments(source: &str, expr: &Expr, comments: &[String]) {
+    for width in [10, 80] {
+        let options = FormatOptions { width, indent: 4 };
+        let formatted = format(source, &options).unwrap();
+        let reparsed = parse_expr(&formatted)
+            .unwrap_or_else(|e| panic!("{source:?} formatted as unparseable {formatted:?}: {e}"));
+        assert_eq!(
+            &reparsed.without_spans(),
+            expr,
+            "{source:?} formatted as {formatted:?}"
+        );
+        let mut rest = formatted.as_str();
+        for comment in comments {
+            let found = rest.find(comment.as_str()).unwrap_or_else(|| {
+                panic!("{source:?} formatted as {formatted:?} lost {comment:?}")
+            });
+            rest = &rest[found + comment.len()..];
+        }
+        let again = format(&formatted, &options).unwrap();
+        assert_eq!(again, formatted, "formatting {source:?} is not idempotent");
+    }
+}
+
+#[test]
+fn test_corpus_comments() {
+    for (source, expr) in corpus() {
+        let ends = token_ends(&source);
+        // One comment of each kind at each position.
+        for &end in &ends {
+            for comment in ["/* c */", "// c\n"] {
+                let commented = format!("{} {comment}{}", &source[..end], &source[end..]);
+                let comments = [comment.trim_end().to_owned()];
+                assert_keeps_comments(&commented, &expr, &comments);
+            }
+        }
+        // Comments at every position at once.
+        for line in [false, true] {
+            let mut commented = String::new();
+            let mut comments = Vec::new();
+            let mut start = 0;
+            for (i, &end) in ends.iter().enumerate() {
+                let comment = if line {
+                    format!("// {i}")
+                } else {
+                    format!("/* {i} */")
+                };
+                commented.push_str(&source[start..end]);
+                commented.push(' ');
+                commented.push_str(&comment);
+                if line {
+                    commented.push('\n');
+                }
+                comments.push(comment);
+                start = end;
+            }
+            commented.push_str(&source[start..]);
+            assert_keeps_comments(&commented, &expr, &comments);
+        }
+    }
+}
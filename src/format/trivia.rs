@@ -0,0 +1,147 @@
+use logos::Logos;
+
+use crate::parse::TokenType;
+use crate::parse::Tokenizer;
+
+/// Comment from the source, with where it sat relative to the code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    /// Byte offset of the start of the comment.
+    pub start: usize,
+    /// Byte offset one past the end of the comment.
+    pub end: usize,
+    /// Whether it is alone on its line, with only whitespace around it.
+    pub own_line: bool,
+    /// Whether it is a line comment, which ends its line.
+    pub line_comment: bool,
+}
+
+impl Comment {
+    /// Whether what follows must go on a later line.
+    pub fn ends_line(&self) -> bool {
+        self.line_comment || self.text.contains('\n')
+    }
+}
+
+/// Layout from the source that the syntax tree does not record,
+/// gathered from the full token stream.
+pub struct Trivia {
+    pub comments: Vec<Comment>,
+    /// Byte offsets of every newline, in order.
+    newlines: Vec<usize>,
+    /// Byte offsets of the newlines ending blank lines, in order.
+    blank_lines: Vec<usize>,
+    /// Byte offsets of the tokens other than trivia, in order, and whether
+    /// each is a `(`.
+    code: Vec<(usize, bool)>,
+}
+
+impl Trivia {
+    pub fn new(source: &str) -> Self {
+        let mut comments: Vec<Comment> = Vec::new();
+        let mut newlines = Vec::new();
+        let mut blank_lines = Vec::new();
+        let mut code = Vec::new();
+        // Whether the current line follows another, and has only whitespace so far.
+        let mut after_newline = false;
+        let mut line_empty = true;
+        // Index of a comment alone on the current line so far.
+        let mut alone: Option<usize> = None;
+        // Tokenizer::next yields every token, including whitespace and comments.
+        for token in Tokenizer::new(TokenType::lexer(source)) {
+            match token.token_type {
+                TokenType::Newline => {
+                    if after_newline && line_empty {
+                        blank_lines.push(token.data.span.start);
+                    }
+                    newlines.push(token.data.span.start);
+                    after_newline = true;
+                    line_empty = true;
+                    alone = None;
+                }
+                TokenType::Whitespace => (),
+                TokenType::LineComment | TokenType::BlockComment => {
+                    if let Some(i) = alone.take() {
+                        comments[i].own_line = false;
+                    }
+                    if line_empty {
+                        alone = Some(comments.len());
+                    }
+                    comments.push(Comment {
+                        text: token.data.lexeme.to_owned(),
+                        start: token.data.span.start,
+                        end: token.data.span.end,
+                        own_line: line_empty,
+                        line_comment: token.token_type == TokenType::LineComment,
+                    });
+                    line_empty = false;
+                }
+                token_type => {
+                    if let Some(i) = alone.take() {
+                        comments[i].own_line = false;
+                    }
+                    line_empty = false;
+                    code.push((token.data.span.start, token_type == TokenType::OpenParen));
+                }
+            }
+        }
+        Self {
+            comments,
+            newlines,
+            blank_lines,
+            code,
+        }
+    }
+
+    /// Whether there is a blank line between the byte offsets start and end.
+    pub fn blank_line_between(&self, start: usize, end: usize) -> bool {
+        let i = self.blank_lines.partition_point(|&offset| offset < start);
+        self.blank_lines.get(i).is_some_and(|&offset| offset < end)
+    }
+
+    /// Start of the first token at or after offset that is not a `(`.
+    pub fn skip_open_parens(&self, offset: usize) -> usize {
+        let i = self.code.partition_point(|&(start, _)| start < offset);
+        self.code[i..]
+            .iter()
+            .find(|(_, open_paren)| !open_paren)
+            .map_or(offset, |&(start, _)| start)
+    }
+
+    /// Whether the byte offsets start and end are on the same line.
+    pub fn same_line(&self, start: usize, end: usize) -> bool {
+        let i = self.newlines.partition_point(|&offset| offset < start);
+        self.newlines.get(i).is_none_or(|&offset| offset >= end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trivia() {
+        let source = "a // one\n\n  /* two\n */ b\n\n/* three */\nc /* four */";
+        let trivia = Trivia::new(source);
+        let texts: Vec<_> = trivia.comments.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["// one", "/* two\n */", "/* three */", "/* four */"]
+        );
+        let own_line: Vec<_> = trivia.comments.iter().map(|c| c.own_line).collect();
+        assert_eq!(own_line, vec![false, false, true, false]);
+
+        assert!(trivia.blank_line_between(0, 12));
+        assert!(!trivia.blank_line_between(10, 24));
+        assert!(trivia.blank_line_between(10, source.len()));
+        assert!(trivia.same_line(0, 8));
+        assert!(!trivia.same_line(0, 9));
+        assert!(trivia.same_line(38, source.len()));
+
+        let trivia = Trivia::new("((x) /* y */ + (( z)))");
+        assert_eq!(trivia.skip_open_parens(0), 2);
+        assert_eq!(trivia.skip_open_parens(3), 3);
+        assert_eq!(trivia.skip_open_parens(15), 18);
+    }
+}
@@ -1,3 +1,18 @@
+pub mod analysis;
 pub mod ast;
+pub mod binary;
+pub mod compile;
+pub mod cst;
+pub mod diagnostic;
+pub mod eval;
+pub mod format;
+pub mod optimize;
 pub mod parse;
+pub mod resolve;
+pub mod span;
 pub mod ttype;
+pub mod typecheck;
+pub mod vm;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,418 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::io::IsTerminal;
+use std::io::Read;
+use std::process::ExitCode;
+
+mod json;
+mod repl;
+
+use carbide::diagnostic;
+use carbide::diagnostic::Diagnostic;
+use carbide::diagnostic::Style;
+use carbide::diagnostic::ToDiagnostic;
+use carbide::eval::eval;
+use carbide::eval::with_eval_stack;
+use carbide::format::format;
+use carbide::format::FormatOptions;
+use carbide::parse::parse_expr;
+use carbide::parse::parse_expr_recovering;
+use carbide::parse::Token;
+use carbide::parse::TokenType;
+use carbide::parse::Tokenizer;
+use logos::Logos;
+
+use json::Json;
+
+const USAGE: &str = "\
+usage: carbide <command> [options]
+
+commands:
+  fmt [--width N] [--indent N] [--check] [FILE...]
+      Format each FILE in place, or standard input to standard output.
+      With --check, list the inputs that are not formatted and change nothing.
+  check [--format F] [FILE...]
+      Report the parse, variable and type errors in each FILE, or standard input.
+  run [--format F] [FILE]
+      Check FILE, or standard input, and print its value.
+  tokens [--format F] [FILE]
+      List the tokens of FILE, or standard input.
+  ast [--format F] [FILE]
+      Print the syntax tree of FILE, or standard input.
+  repl
+      Evaluate expressions interactively.  Type :help for its commands.
+
+Commands taking --format F print text, or with `--format json`, a JSON object.
+";
+
+/// Error in the command line arguments.
+type UsageError = String;
+
+/// How commands print their results.
+#[derive(Copy, Clone, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, rest)) if command == "fmt" => fmt(rest),
+        Some((command, rest)) if command == "check" => check(rest),
+        Some((command, rest)) if command == "run" => run(rest),
+        Some((command, rest)) if command == "tokens" => tokens(rest),
+        Some((command, rest)) if command == "ast" => ast(rest),
+        Some((command, rest)) if command == "repl" => repl(rest),
+        Some((flag, _)) if flag == "-h" || flag == "--help" => {
+            print!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        }
+        Some((command, _)) => Err(format!("unknown command '{command}'")),
+        None => Err("missing command".to_owned()),
+    };
+    result.unwrap_or_else(|message| {
+        eprint!("carbide: {message}\n\n{USAGE}");
+        ExitCode::from(2)
+    })
+}
+
+fn fmt(args: &[String]) -> Result<ExitCode, UsageError> {
+    let mut options = FormatOptions::default();
+    let mut check = false;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => options.width = number(arg, args.next())?,
+            "--indent" => options.indent = number(arg, args.next())?,
+            "--check" => check = true,
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{flag}'")),
+            path => paths.push(path),
+        }
+    }
+
+    if paths.is_empty() {
+        let Some(source) = read_stdin() else {
+            return Ok(ExitCode::FAILURE);
+        };
+        let Some(formatted) = format_source("<stdin>", &source, &options) else {
+            return Ok(ExitCode::FAILURE);
+        };
+        if !check {
+            print!("{formatted}");
+        } else if formatted != source {
+            println!("<stdin>");
+            return Ok(ExitCode::FAILURE);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut success = true;
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("carbide: cannot read {path}: {error}");
+                success = false;
+                continue;
+            }
+        };
+        let Some(formatted) = format_source(path, &source, &options) else {
+            success = false;
+            continue;
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{path}");
+            success = false;
+        } else if let Err(error) = fs::write(path, formatted) {
+            eprintln!("carbide: cannot write {path}: {error}");
+            success = false;
+        }
+    }
+    Ok(exit_code(success))
+}
+
+fn check(args: &[String]) -> Result<ExitCode, UsageError> {
+    let (output, paths) = script_args(args)?;
+    let inputs: Vec<Option<&str>> = if paths.is_empty() {
+        vec![None]
+    } else {
+        paths.into_iter().map(Some).collect()
+    };
+    let mut success = true;
+    let mut reported = Vec::new();
+    for path in inputs {
+        let Some(source) = read_source(path) else {
+            success = false;
+            continue;
+        };
+        let name = path.unwrap_or("<stdin>");
+        let diagnostics = diagnostic::check(&source);
+        success &= !diagnostics.iter().any(Diagnostic::is_error);
+        match output {
+            OutputFormat::Text => diagnostics
+                .iter()
+                .for_each(|diagnostic| report_diagnostic(diagnostic, name, &source)),
+            OutputFormat::Json => reported.extend(
+                diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic_json(diagnostic, name)),
+            ),
+        }
+    }
+    if output == OutputFormat::Json {
+        println!("{}", Json::object([("diagnostics", Json::Array(reported))]));
+    }
+    Ok(exit_code(success))
+}
+
+fn run(args: &[String]) -> Result<ExitCode, UsageError> {
+    let (output, path) = script_arg(args)?;
+    let Some(source) = read_source(path) else {
+        return Ok(ExitCode::FAILURE);
+    };
+    let name = path.unwrap_or("<stdin>");
+    let mut diagnostics = diagnostic::check(&source);
+    let mut value = None;
+    if !diagnostics.iter().any(Diagnostic::is_error) {
+        // Checking found no parse errors.
+        let expr = parse_expr(&source).expect("checked source parses");
+        match with_eval_stack(|| eval(&expr).map(|result| result.to_string())) {
+            Ok(result) => value = Some(result),
+            Err(error) => diagnostics.push(error.to_diagnostic(&source)),
+        }
+    }
+    match output {
+        OutputFormat::Text => {
+            for diagnostic in &diagnostics {
+                report_diagnostic(diagnostic, name, &source);
+            }
+            if let Some(value) = &value {
+                println!("{value}");
+            }
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            Json::object([
+                ("value", Json::from(value.clone())),
+                ("diagnostics", diagnostics_json(&diagnostics, name)),
+            ])
+        ),
+    }
+    Ok(exit_code(value.is_some()))
+}
+
+fn tokens(args: &[String]) -> Result<ExitCode, UsageError> {
+    let (output, path) = script_arg(args)?;
+    let Some(source) = read_source(path) else {
+        return Ok(ExitCode::FAILURE);
+    };
+    let tokens =
+        Tokenizer::new(TokenType::lexer(&source)).filter(|token| !token.token_type.is_trivia());
+    match output {
+        OutputFormat::Text => tokens.for_each(|token| println!("{}", token_line(&token))),
+        OutputFormat::Json => println!(
+            "{}",
+            Json::object([(
+                "tokens",
+                Json::array(tokens.map(|token| token_json(&token)))
+            )])
+        ),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn ast(args: &[String]) -> Result<ExitCode, UsageError> {
+    let (output, path) = script_arg(args)?;
+    let Some(source) = read_source(path) else {
+        return Ok(ExitCode::FAILURE);
+    };
+    let name = path.unwrap_or("<stdin>");
+    let (expr, errors) = parse_expr_recovering(&source);
+    let tree = format!("{expr:#?}");
+    let diagnostics: Vec<Diagnostic> = errors
+        .iter()
+        .map(|error| error.to_diagnostic(&source))
+        .collect();
+    match output {
+        OutputFormat::Text => {
+            for diagnostic in &diagnostics {
+                report_diagnostic(diagnostic, name, &source);
+            }
+            println!("{tree}");
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            Json::object([
+                ("ast", Json::from(tree)),
+                ("diagnostics", diagnostics_json(&diagnostics, name)),
+            ])
+        ),
+    }
+    Ok(exit_code(diagnostics.is_empty()))
+}
+
+fn repl(args: &[String]) -> Result<ExitCode, UsageError> {
+    if let Some(arg) = args.first() {
+        return Err(format!("unexpected argument '{arg}'"));
+    }
+    let style = if io::stdout().is_terminal() {
+        Style::Ansi
+    } else {
+        Style::Plain
+    };
+    match with_eval_stack(|| repl::run(style)) {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(error) => {
+            eprintln!("carbide: {error}");
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Format source read from path, reporting any error.
+fn format_source(path: &str, source: &str, options: &FormatOptions) -> Option<String> {
+    format(source, options)
+        .map_err(|error| report(&error, path, source))
+        .ok()
+}
+
+fn report(error: &impl ToDiagnostic, path: &str, source: &str) {
+    report_diagnostic(&error.to_diagnostic(source), path, source);
+}
+
+fn report_diagnostic(diagnostic: &Diagnostic, path: &str, source: &str) {
+    let style = if io::stderr().is_terminal() {
+        Style::Ansi
+    } else {
+        Style::Plain
+    };
+    eprint!("{}", diagnostic.render_file(path, source, style));
+}
+
+/// Describe a token as `line:col Type "lexeme"`, with 1-indexed positions.
+fn token_line(token: &Token) -> String {
+    format!(
+        "{}:{} {:?} {:?}",
+        token.data.line + 1,
+        token.data.col + 1,
+        token.token_type,
+        token.data.lexeme
+    )
+}
+
+fn token_json(token: &Token) -> Json {
+    Json::object([
+        ("type", Json::from(format!("{:?}", token.token_type))),
+        ("lexeme", Json::from(token.data.lexeme)),
+        ("line", Json::from(token.data.line + 1)),
+        ("column", Json::from(token.data.col + 1)),
+        ("start", Json::from(token.data.span.start)),
+        ("end", Json::from(token.data.span.end)),
+    ])
+}
+
+/// Describe a diagnostic in source read from path.  Lines and columns are
+/// 1-indexed, as in rendered diagnostics; start and end are byte offsets.
+fn diagnostic_json(diagnostic: &Diagnostic, path: &str) -> Json {
+    let span = diagnostic.span;
+    Json::object([
+        ("file", Json::from(path)),
+        ("severity", Json::from(diagnostic.severity.name())),
+        ("message", Json::from(diagnostic.message.as_str())),
+        ("line", Json::from(span.line + 1)),
+        ("column", Json::from(span.col + 1)),
+        ("start", Json::from(span.start)),
+        ("end", Json::from(span.end)),
+        ("label", Json::from(diagnostic.label.clone())),
+        (
+            "notes",
+            Json::array(diagnostic.notes.iter().map(String::as_str)),
+        ),
+        ("help", Json::from(diagnostic.help.clone())),
+    ])
+}
+
+fn diagnostics_json(diagnostics: &[Diagnostic], path: &str) -> Json {
+    Json::array(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic_json(diagnostic, path)),
+    )
+}
+
+fn exit_code(success: bool) -> ExitCode {
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Arguments to commands reading scripts: an output format, and the paths
+/// of the scripts.
+fn script_args(args: &[String]) -> Result<(OutputFormat, Vec<&str>), UsageError> {
+    let mut output = OutputFormat::Text;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                output = match args.next().map(String::as_str) {
+                    Some("text") => OutputFormat::Text,
+                    Some("json") => OutputFormat::Json,
+                    Some(value) => return Err(format!("invalid value '{value}' for {arg}")),
+                    None => return Err(format!("missing value for {arg}")),
+                }
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{flag}'")),
+            path => paths.push(path),
+        }
+    }
+    Ok((output, paths))
+}
+
+/// Arguments to commands reading at most one script.
+fn script_arg(args: &[String]) -> Result<(OutputFormat, Option<&str>), UsageError> {
+    let (output, paths) = script_args(args)?;
+    match paths[..] {
+        [] => Ok((output, None)),
+        [path] => Ok((output, Some(path))),
+        [_, extra, ..] => Err(format!("unexpected argument '{extra}'")),
+    }
+}
+
+/// Read the script at path, or standard input if there is no path,
+/// reporting any error.
+fn read_source(path: Option<&str>) -> Option<String> {
+    let Some(path) = path else {
+        return read_stdin();
+    };
+    fs::read_to_string(path)
+        .map_err(|error| eprintln!("carbide: cannot read {path}: {error}"))
+        .ok()
+}
+
+fn read_stdin() -> Option<String> {
+    let mut source = String::new();
+    match io::stdin().read_to_string(&mut source) {
+        Ok(_) => Some(source),
+        Err(error) => {
+            eprintln!("carbide: cannot read standard input: {error}");
+            None
+        }
+    }
+}
+
+/// The value of a numeric option.
+fn number(option: &str, value: Option<&String>) -> Result<usize, UsageError> {
+    let value = value.ok_or_else(|| format!("missing value for {option}"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{value}' for {option}"))
+}
@@ -0,0 +1,165 @@
+use crate::ast::expr::BinaryOp;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Literal;
+use crate::ast::expr::StrPart;
+use crate::ast::expr::UnaryOp;
+use crate::ast::fold::walk_interpolate;
+use crate::ast::fold::Folder;
+use crate::eval::eval;
+use crate::eval::Value;
+use crate::span::Span;
+
+/// Simplify expr, folding constant operators and interpolations, collapsing single-expression
+/// blocks, pruning ifs with literal conditions and applying identities
+/// such as `x + 0` -> `x`.
+///
+/// expr should type check: identities that drop an operator assume its
+/// other operand has the type the operator requires.  Otherwise the result
+/// evaluates to the same value, or fails with the same RuntimeError.
+pub fn optimize(expr: Expr) -> Expr {
+    Optimizer.fold_expr(expr)
+}
+
+/// Folder simplifying each expression after its subexpressions.
+struct Optimizer;
+
+impl Folder for Optimizer {
+    fn fold_unary(&mut self, span: Span, op: UnaryOp, right: Expr) -> Expr {
+        let right = self.fold_expr(right);
+        unary(span, op, right)
+    }
+
+    fn fold_binary(&mut self, span: Span, left: Expr, op: BinaryOp, right: Expr) -> Expr {
+        let left = self.fold_expr(left);
+        let right = self.fold_expr(right);
+        binary(span, left, op, right)
+    }
+
+    fn fold_block(&mut self, span: Span, contents: Vec<Expr>, trailing_semicolon: bool) -> Expr {
+        let contents = contents
+            .into_iter()
+            .map(|expr| self.fold_expr(expr))
+            .collect();
+        block(span, contents, trailing_semicolon)
+    }
+
+    fn fold_if(
+        &mut self,
+        span: Span,
+        condition: Expr,
+        then_expr: Expr,
+        else_expr: Option<Expr>,
+    ) -> Expr {
+        let condition = self.fold_expr(condition);
+        let then_expr = self.fold_expr(then_expr);
+        let else_expr = else_expr.map(|else_expr| self.fold_expr(else_expr));
+        if_(span, condition, then_expr, else_expr)
+    }
+
+    // A string interpolating only literals is a literal.
+    fn fold_interpolate(&mut self, span: Span, parts: Vec<StrPart>) -> Expr {
+        let expr = walk_interpolate(self, span, parts);
+        let ExprKind::Interpolate(parts) = &expr.kind else {
+            unreachable!("walk_interpolate returns an interpolation");
+        };
+        let literal = |part: &StrPart| match part {
+            StrPart::Text(_) => true,
+            StrPart::Expr(expr) => matches!(expr.kind, ExprKind::Literal(_)),
+        };
+        if parts.iter().all(literal) {
+            fold(expr)
+        } else {
+            expr
+        }
+    }
+}
+
+fn unary(span: Span, op: UnaryOp, right: Expr) -> Expr {
+    if let ExprKind::Literal(_) = right.kind {
+        return fold(Expr::unary(op, right).with_span(span));
+    }
+    match (op, right.kind) {
+        (
+            UnaryOp::Not,
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                right: operand,
+            },
+        ) => *operand,
+        (UnaryOp::Pos, kind) => Expr::new(kind, right.span),
+        (op, kind) => Expr::unary(op, Expr::new(kind, right.span)).with_span(span),
+    }
+}
+
+// The right side is dropped only if it would not be evaluated, or if it
+// cannot fail.
+fn binary(span: Span, left: Expr, op: BinaryOp, right: Expr) -> Expr {
+    use ExprKind::Literal as L;
+    use Literal::Bool;
+    use Literal::Int;
+    match (&left.kind, op, &right.kind) {
+        (L(_), _, L(_)) => fold(Expr::binary(left, op, right).with_span(span)),
+        (L(Bool(false)), BinaryOp::And, _) | (L(Bool(true)), BinaryOp::Or, _) => {
+            left.with_span(span)
+        }
+        (L(Bool(true)), BinaryOp::And, _)
+        | (L(Bool(false)), BinaryOp::Or, _)
+        | (L(Int(0)), BinaryOp::Add, _)
+        | (L(Int(1)), BinaryOp::Mult, _) => right,
+        (_, BinaryOp::And, L(Bool(true)))
+        | (_, BinaryOp::Or, L(Bool(false)))
+        | (_, BinaryOp::Add | BinaryOp::Sub, L(Int(0)))
+        | (_, BinaryOp::Mult | BinaryOp::Div, L(Int(1))) => left,
+        _ => Expr::binary(left, op, right).with_span(span),
+    }
+}
+
+/// Replace expr, whose operands are literals, with the literal it evaluates to.
+/// If it fails, keep it so that it fails when evaluated.
+fn fold(expr: Expr) -> Expr {
+    let span = expr.span;
+    match eval(&expr) {
+        Ok(Value::Bool(b)) => Expr::bool(b).with_span(span),
+        Ok(Value::Int(i)) => Expr::int(i).with_span(span),
+        // Infinities and NaN have no literal.
+        Ok(Value::Float(x)) if x.is_finite() => Expr::float(x).with_span(span),
+        Ok(Value::Str(s)) => Expr::str(&*s).with_span(span),
+        _ => expr,
+    }
+}
+
+// A block of one expression is just that expression, unless it is a let,
+// which would then bind its name in the enclosing block.
+fn block(span: Span, mut contents: Vec<Expr>, trailing_semicolon: bool) -> Expr {
+    let single = contents.len() == 1 && !trailing_semicolon;
+    if single && !matches!(contents[0].kind, ExprKind::Let { .. }) {
+        return contents.pop().unwrap();
+    }
+    Expr::new(
+        ExprKind::Block {
+            contents,
+            trailing_semicolon,
+        },
+        span,
+    )
+}
+
+// An if without an else is unit, so a true condition leaves the then
+// branch in a unit block, and a false one leaves an empty unit block.
+fn if_(span: Span, condition: Expr, then_expr: Expr, else_expr: Option<Expr>) -> Expr {
+    match (&condition.kind, else_expr) {
+        (ExprKind::Literal(Literal::Bool(true)), Some(_)) => then_expr,
+        (ExprKind::Literal(Literal::Bool(false)), Some(else_expr)) => else_expr,
+        (ExprKind::Literal(Literal::Bool(true)), None) => {
+            Expr::unit_block(vec![then_expr]).with_span(span)
+        }
+        (ExprKind::Literal(Literal::Bool(false)), None) => {
+            Expr::unit_block(Vec::new()).with_span(span)
+        }
+        (_, else_expr) => Expr::ifthen(condition, then_expr, else_expr).with_span(span),
+    }
+}
+
+#[cfg(test)]
+mod tests;
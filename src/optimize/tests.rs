@@ -0,0 +1,151 @@
+use super::optimize;
+use crate::ast::expr::BinaryOp;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Param;
+use crate::ast::expr::UnaryOp;
+use crate::eval::eval;
+use crate::parse::parse_expr;
+use crate::span::Span;
+use crate::ttype::Type;
+use crate::typecheck::typecheck;
+
+/// Optimize source, ignoring spans so the result compares equal to hand-built Exprs.
+fn opt(source: &str) -> Expr {
+    optimize(parse_expr(source).unwrap()).without_spans()
+}
+
+fn parse(source: &str) -> Expr {
+    parse_expr(source).unwrap().without_spans()
+}
+
+#[test]
+fn test_fold_constants() {
+    assert_eq!(opt("not not true"), Expr::bool(true));
+    assert_eq!(opt("1 * 2 + 3"), Expr::int(5));
+    assert_eq!(opt("-(2 - 5) * 4 / 2"), Expr::int(6));
+    assert_eq!(opt("1 + 2 < 4 == true"), Expr::bool(true));
+    assert_eq!(opt("true and not false or false"), Expr::bool(true));
+    assert_eq!(opt("\"a\" + \"b\" < \"b\""), Expr::bool(true));
+    assert_eq!(opt("\"{len \"abc\"} {1 < 2}\""), Expr::str("3 true"));
+    assert_eq!(opt("-1.5 * 2.0 + 0.5"), Expr::float(-2.5));
+    assert_eq!(opt("\"{1.0 / 4.0}\""), Expr::str("0.25"));
+    assert_eq!(
+        opt("x + 2 * 3"),
+        Expr::binary(Expr::var("x"), BinaryOp::Add, Expr::int(6))
+    );
+
+    let expr = optimize(parse_expr("x + (2 * 3)").unwrap());
+    let ExprKind::Binary { right, .. } = expr.kind else {
+        panic!("expected a binary expression, found {:?}", expr.kind);
+    };
+    assert_eq!(right.span, Span::new(4, 11, 0, 4));
+}
+
+#[test]
+fn test_fold_keeps_errors() {
+    // Folding would fail, so the expression is kept to fail when evaluated.
+    assert_eq!(
+        opt("9223372036854775807 + 1"),
+        parse("9223372036854775807 + 1")
+    );
+    assert_eq!(opt("1 / (2 - 2)"), parse("1 / 0"));
+    // Infinities and NaN have no literal to fold to.
+    assert_eq!(opt("1.0 / (2.0 - 2.0)"), parse("1.0 / 0.0"));
+    assert_eq!(opt("not 1"), parse("not 1"));
+    assert_eq!(opt("1 == true"), parse("1 == true"));
+    // The operands of these might fail, so are not dropped.
+    assert_eq!(opt("x * 0"), parse("x * 0"));
+    assert_eq!(opt("x and false"), parse("x and false"));
+    assert_eq!(opt("- -x"), parse("- -x"));
+}
+
+#[test]
+fn test_identities() {
+    let x = Expr::var("x");
+    assert_eq!(opt("x and true"), x);
+    assert_eq!(opt("true and x"), x);
+    assert_eq!(opt("x or false"), x);
+    assert_eq!(opt("false or x"), x);
+    assert_eq!(opt("x + 0"), x);
+    assert_eq!(opt("0 + x"), x);
+    assert_eq!(opt("x - 0"), x);
+    assert_eq!(opt("x * 1"), x);
+    assert_eq!(opt("1 * x"), x);
+    assert_eq!(opt("x / 1"), x);
+    assert_eq!(opt("not not x"), x);
+    assert_eq!(opt("+x"), x);
+    assert_eq!(opt("x * (3 - 2) + (0 * 5)"), x);
+    assert_eq!(
+        opt("0 - x"),
+        Expr::binary(Expr::int(0), BinaryOp::Sub, Expr::var("x"))
+    );
+    assert_eq!(
+        opt("not not not x"),
+        Expr::unary(UnaryOp::Not, Expr::var("x"))
+    );
+    // The right side is never evaluated.
+    assert_eq!(opt("false and f(1 / 0)"), Expr::bool(false));
+    assert_eq!(opt("true or f(1 / 0)"), Expr::bool(true));
+}
+
+#[test]
+fn test_blocks_and_ifs() {
+    assert_eq!(opt("(((x)))"), Expr::var("x"));
+    assert_eq!(opt("if true (x) else y"), Expr::var("x"));
+    assert_eq!(opt("if false (x) else (y)"), Expr::var("y"));
+    assert_eq!(opt("if 1 < 2 (x) else (y)"), Expr::var("x"));
+    assert_eq!(opt("if true (x)"), Expr::unit_block(vec![Expr::var("x")]));
+    assert_eq!(opt("if false (x)"), Expr::unit_block(Vec::new()));
+    assert_eq!(opt("if 2 < 1 (x)"), Expr::unit_block(Vec::new()));
+    assert_eq!(
+        opt("if x (1 + 1) else (2)"),
+        Expr::ifthen(Expr::var("x"), Expr::int(2), Some(Expr::int(2)))
+    );
+    // A let keeps its block, so its name stays in scope only there.
+    assert_eq!(opt("(let x = 1 + 1)"), parse("(let x = 2)"));
+    assert_eq!(opt("(let x = 1; (x + 0))"), parse("(let x = 1; x)"));
+    assert_eq!(opt("(x; (y);)"), parse("(x; y;)"));
+}
+
+#[test]
+fn test_functions() {
+    assert_eq!(
+        opt("fn f(x: int) -> int (x * 1)"),
+        Expr::named_fn(
+            "f",
+            vec![Param::new("x", Type::Int)],
+            Type::Int,
+            Expr::var("x")
+        )
+    );
+    assert_eq!(
+        opt("f(1 + 1, (g))"),
+        Expr::call(Expr::var("f"), vec![Expr::int(2), Expr::var("g")])
+    );
+}
+
+#[test]
+fn test_same_results() {
+    let sources = [
+        "(let x = 3; x * 1 + 0 - (2 * 2))",
+        "(let b = false; not not b or (true and b))",
+        "if 1 < 2 (let y = 4; y / 1) else (0)",
+        "(let x = 9223372036854775807; x + 0 + 1)",
+        "(let x = 0; 10 / x * 1)",
+        "fn fact(n: int) -> int (if n <= 1 + 0 (1) else (n * fact(n - 1) * 1))(10)",
+        "(let f = fn (x: int) -> bool (x > 0 and true); f(1) or false)",
+        "if true (1 + 1;)",
+        "if false (1 / 0)",
+    ];
+    for source in sources {
+        let expr = parse_expr(source).unwrap();
+        assert!(typecheck(&expr).is_ok(), "{source} does not type check");
+        let optimized = optimize(expr.clone());
+        assert_eq!(
+            eval(&optimized).map(|value| value.to_string()),
+            eval(&expr).map(|value| value.to_string()),
+            "for source {source:?}"
+        );
+    }
+}
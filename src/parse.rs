@@ -1,24 +1,70 @@
 mod error;
+pub mod incremental;
+pub mod number;
+pub mod operator;
 pub mod parser;
+pub mod string;
 mod token_type;
 mod tokenizer;
 
+pub use error::ParseError;
+pub use token_type::LexError;
 pub use token_type::TokenType;
+pub use tokenizer::LexedToken;
 pub use tokenizer::Token;
 pub use tokenizer::TokenData;
 pub use tokenizer::Tokenizer;
 
 use crate::ast::expr::Expr;
-use error::ParseError;
+use crate::cst::SyntaxNode;
 use logos::Logos;
 
 type ParseResult<T> = Result<T, ParseError>;
 
+/// Parse source as one expression.  Parentheses around a single expression
+/// only group it, widening its span to include them; around a `let`, or
+/// items separated or ended by `;`, they make a Block.
 pub fn parse_expr(source: &str) -> ParseResult<Expr> {
     let lex = TokenType::lexer(source);
     let tokenizer = Tokenizer::new(lex);
     let mut parser = parser::Parser::new(tokenizer);
-    parser.parse_expression()
+    parser.parse_complete()
+}
+
+/// Parse source as a block item: an expression, or a `let` whose binding
+/// stays in scope for items evaluated after it.
+pub fn parse_item(source: &str) -> ParseResult<Expr> {
+    let lex = TokenType::lexer(source);
+    let tokenizer = Tokenizer::new(lex);
+    let mut parser = parser::Parser::new(tokenizer);
+    parser.parse_complete_item()
+}
+
+/// Parse source, recovering from errors to report as many as possible.
+/// Unparseable parts of the source become ExprKind::Error nodes in the
+/// returned partial Expr.  If the error list is empty, the Expr is the
+/// same as that from parse_expr.
+pub fn parse_expr_recovering(source: &str) -> (Expr, Vec<ParseError>) {
+    let lex = TokenType::lexer(source);
+    let tokenizer = Tokenizer::new(lex);
+    let mut parser = parser::Parser::recovering(tokenizer);
+    let expr = parser
+        .parse_complete()
+        .expect("recovering parser reports errors rather than returning them");
+    (expr, parser.into_errors())
+}
+
+/// Parse source into a lossless syntax tree, recovering from errors like
+/// parse_expr_recovering.  The tree's text is source, and it lowers to the
+/// Expr parse_expr_recovering returns.
+pub fn parse_cst(source: &str) -> (SyntaxNode, Vec<ParseError>) {
+    let lex = TokenType::lexer(source);
+    let tokenizer = Tokenizer::new(lex);
+    let mut parser = parser::Parser::lossless(tokenizer);
+    parser
+        .parse_complete()
+        .expect("recovering parser reports errors rather than returning them");
+    parser.into_cst()
 }
 
 #[cfg(test)]
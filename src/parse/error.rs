@@ -1,8 +1,17 @@
+use std::ops::Range;
+
 use super::Token;
 use super::TokenType;
 use thiserror::Error;
 
+/// Error from parsing.  With the `serde` feature, errors serialize as
+/// Expr kinds do, as in `{"eof": {"line": 0, "col": 3}}`.
 #[derive(Debug, Error, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum ParseError {
     #[error("unknown token '{lexeme}' at {line}:{col}")]
     UnknownToken {
@@ -20,6 +29,38 @@ pub enum ParseError {
         line: usize,
         col: usize,
     },
+    #[error("comparison operators cannot be chained, but found '{lexeme}' at {line}:{col}")]
+    ChainedComparison {
+        lexeme: String,
+        line: usize,
+        col: usize,
+    },
+    #[error("unterminated block comment starting at {line}:{col}")]
+    UnterminatedComment { line: usize, col: usize },
+    #[error("unterminated string starting at {line}:{col}")]
+    UnterminatedString { line: usize, col: usize },
+    #[error("invalid escape '{escape}' in string at {line}:{col}")]
+    BadEscape {
+        escape: String,
+        line: usize,
+        col: usize,
+    },
+    #[error("unexpected end of interpolated expression at {line}:{col}")]
+    UnfinishedInterpolation { line: usize, col: usize },
+    #[error("integer literal '{lexeme}' out of range at {line}:{col}")]
+    IntegerOverflow {
+        lexeme: String,
+        line: usize,
+        col: usize,
+    },
+    #[error("float literal '{lexeme}' out of range at {line}:{col}")]
+    FloatOverflow {
+        lexeme: String,
+        line: usize,
+        col: usize,
+    },
+    #[error("unexpected ';' at {line}:{col}")]
+    StraySemicolon { line: usize, col: usize },
     #[error("Unclassified error: {0}")]
     Unclassified(String),
 }
@@ -41,6 +82,22 @@ impl ParseError {
         }
     }
 
+    pub fn chained_comparison(token: Token) -> Self {
+        ParseError::ChainedComparison {
+            lexeme: token.data.lexeme.to_owned(),
+            line: token.data.line,
+            col: token.data.col,
+        }
+    }
+
+    pub fn stray_semicolon(token: Token) -> Self {
+        assert_eq!(token.token_type, TokenType::Semicolon);
+        ParseError::StraySemicolon {
+            line: token.data.line,
+            col: token.data.col,
+        }
+    }
+
     pub fn unknown_token(token: Token) -> Self {
         assert_eq!(token.token_type, TokenType::UnknownToken);
         ParseError::UnknownToken {
@@ -49,4 +106,49 @@ impl ParseError {
             col: token.data.col,
         }
     }
+
+    pub fn unterminated_comment(token: Token) -> Self {
+        assert_eq!(token.token_type, TokenType::UnterminatedComment);
+        ParseError::UnterminatedComment {
+            line: token.data.line,
+            col: token.data.col,
+        }
+    }
+
+    pub fn unterminated_string(token: Token) -> Self {
+        assert_eq!(token.token_type, TokenType::UnterminatedString);
+        ParseError::UnterminatedString {
+            line: token.data.line,
+            col: token.data.col,
+        }
+    }
+
+    /// Error for the escape at range within a string token, which lies on
+    /// one line.
+    pub fn bad_escape(token: Token, range: Range<usize>) -> Self {
+        assert_eq!(token.token_type, TokenType::Str);
+        ParseError::BadEscape {
+            escape: token.data.lexeme[range.clone()].to_owned(),
+            line: token.data.line,
+            col: token.data.col + range.start,
+        }
+    }
+
+    pub fn integer_overflow(token: Token) -> Self {
+        assert_eq!(token.token_type, TokenType::Int);
+        ParseError::IntegerOverflow {
+            lexeme: token.data.lexeme.to_owned(),
+            line: token.data.line,
+            col: token.data.col,
+        }
+    }
+
+    pub fn float_overflow(token: Token) -> Self {
+        assert_eq!(token.token_type, TokenType::Float);
+        ParseError::FloatOverflow {
+            lexeme: token.data.lexeme.to_owned(),
+            line: token.data.line,
+            col: token.data.col,
+        }
+    }
 }
@@ -0,0 +1,392 @@
+use std::ops::Range;
+
+use logos::Logos;
+
+use super::parser::Parser;
+use super::tokenizer::LexedToken;
+use super::tokenizer::Tokenizer;
+use super::ParseError;
+use super::TokenType;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::fold;
+use crate::ast::fold::Folder;
+use crate::ast::visit;
+use crate::ast::visit::Visitor;
+use crate::span::Span;
+
+/// Most bytes the lexer may read past the end of a token to find where it
+/// ends, as it reads the `e-x` after `1` to tell it from the float `1e-5`.
+const MAX_LOOKAHEAD: usize = 3;
+
+/// Bytes the lexer may read past the end of a token of the type, as it
+/// reads the `>` after `-` to tell it from `->`.
+fn lookahead(token_type: TokenType) -> usize {
+    match token_type {
+        TokenType::Int | TokenType::Float => MAX_LOOKAHEAD,
+        _ => 1,
+    }
+}
+
+/// Replacement of the source in byte range with text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+}
+
+/// Source with its tokens and recovering parse, kept up to date as the
+/// source is edited.
+///
+/// An edit re-lexes from the first token it could affect until the new tokens line up
+/// with the old ones again, and re-parses only the innermost parenthesized
+/// group around the re-lexed tokens, reusing the rest of the Expr.  The
+/// tokens, Expr and errors are always those of lexing and parsing the
+/// whole source from scratch with parse_expr_recovering.
+pub struct IncrementalParse {
+    source: String,
+    tokens: Vec<LexedToken>,
+    expr: Expr,
+    errors: Vec<ParseError>,
+}
+
+impl IncrementalParse {
+    pub fn new(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let tokens = Tokenizer::new(TokenType::lexer(&source))
+            .map(|token| LexedToken::from(&token))
+            .collect();
+        let mut parse = Self {
+            source,
+            tokens,
+            expr: Expr::new(ExprKind::Error, Span::default()),
+            errors: Vec::new(),
+        };
+        parse.reparse_all();
+        parse
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// All tokens of the source, including trivia.
+    pub fn tokens(&self) -> &[LexedToken] {
+        &self.tokens
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Apply edit to the source, and bring the tokens and parse up to date.
+    /// Return the span of the new source that was re-parsed, which is all
+    /// of it if no group could be re-parsed on its own.
+    ///
+    /// Panics if the edit's range is out of bounds or does not lie on char
+    /// boundaries, as String::replace_range does.
+    pub fn edit(&mut self, edit: &TextEdit) -> Span {
+        let (relexed, shift) = self.relex(edit);
+        match self.reparse_group(relexed, shift) {
+            Some(span) => span,
+            None => self.reparse_all(),
+        }
+    }
+
+    /// Replace the tokens affected by edit, and apply it to the source.
+    /// Return the range of the new tokens, and the Shift from old positions
+    /// after the edit to new ones.
+    fn relex(&mut self, edit: &TextEdit) -> (Range<usize>, Shift) {
+        let (start_line, start_col) = self.position(edit.range.start);
+        let (end_line, end_col) = self.position(edit.range.end);
+        let (new_line, new_col) = advance((start_line, start_col), &edit.text);
+        let shift = Shift {
+            delta: edit.text.len() as isize - edit.range.len() as isize,
+            old_line: end_line,
+            old_col: end_col,
+            new_line,
+            new_col,
+        };
+        self.source.replace_range(edit.range.clone(), &edit.text);
+
+        // Tokens ending within their lookahead of the edit may change.
+        let unchanged =
+            |token: &LexedToken, lookahead: usize| token.span.end + lookahead <= edit.range.start;
+        let mut first = self
+            .tokens
+            .partition_point(|token| unchanged(token, MAX_LOOKAHEAD));
+        while self
+            .tokens
+            .get(first)
+            .is_some_and(|token| unchanged(token, lookahead(token.token_type)))
+        {
+            first += 1;
+        }
+        let from = self.tokens.get(first).map(|token| token.span);
+        let (offset, line, col) = from.map_or((0, 0, 0), |span| (span.start, span.line, span.col));
+
+        // Old tokens starting after the edit are the same as the new ones
+        // starting at the same text, so lexing stops at the first of those.
+        let mut old = self
+            .tokens
+            .partition_point(|token| token.span.start < edit.range.end);
+        let mut rest = self.tokens.len();
+        let mut relexed = Vec::new();
+        let tokenizer = Tokenizer::starting_at(TokenType::lexer(&self.source), offset, line, col);
+        for token in tokenizer {
+            let token = LexedToken::from(&token);
+            if token.span.start >= edit.range.start + edit.text.len() {
+                let old_start = shift.unshift_offset(token.span.start);
+                while self
+                    .tokens
+                    .get(old)
+                    .is_some_and(|token| token.span.start < old_start)
+                {
+                    old += 1;
+                }
+                if self
+                    .tokens
+                    .get(old)
+                    .is_some_and(|token| token.span.start == old_start)
+                {
+                    rest = old;
+                    break;
+                }
+            }
+            relexed.push(token);
+        }
+        let range = first..first + relexed.len();
+        let tail: Vec<_> = self.tokens[rest..]
+            .iter()
+            .map(|token| LexedToken {
+                span: shift.span(token.span),
+                ..*token
+            })
+            .collect();
+        self.tokens.truncate(first);
+        self.tokens.extend(relexed);
+        self.tokens.extend(tail);
+        (range, shift)
+    }
+
+    /// Line and column of byte offset in the source, found from the token
+    /// containing it.
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let i = self
+            .tokens
+            .partition_point(|token| token.span.end <= offset);
+        let Some(token) = self.tokens.get(i).or(self.tokens.last()) else {
+            return (0, 0);
+        };
+        let end = offset.clamp(token.span.start, token.span.end);
+        advance(
+            (token.span.line, token.span.col),
+            &self.source[token.span.start..end],
+        )
+    }
+
+    /// Re-parse the innermost group, in parens that were not re-lexed, that
+    /// encloses the re-lexed tokens.  Return its span, or None if no group
+    /// can be re-parsed on its own.
+    ///
+    /// A parse without errors is a tree of the parser's productions, in
+    /// which an Expr spanning a matched pair of parens is always that
+    /// parsed by Parser::block, which only depends on the tokens within.
+    fn reparse_group(&mut self, relexed: Range<usize>, shift: Shift) -> Option<Span> {
+        if !self.errors.is_empty() {
+            return None;
+        }
+        let mut before = relexed.start;
+        loop {
+            let open = unmatched_open(&self.tokens[..before])?;
+            let close = matching_close(&self.tokens, open)?;
+            before = open;
+            if close < relexed.end {
+                continue;
+            }
+            let open_span = self.tokens[open].span;
+            let close_span = self.tokens[close].span;
+            let old_end = shift.unshift_offset(close_span.end);
+            let old = Span::new(open_span.start, old_end, open_span.line, open_span.col);
+            if !contains_span(&self.expr, old) {
+                continue;
+            }
+            let tokenizer = Tokenizer::from_tokens(&self.source, &self.tokens[open..=close]);
+            let mut parser = Parser::recovering(tokenizer);
+            let group = parser
+                .parse_complete()
+                .expect("recovering parser reports errors rather than returning them");
+            let span = open_span.to(close_span);
+            if !parser.errors().is_empty() || group.span != span {
+                return None;
+            }
+            let expr = std::mem::replace(&mut self.expr, Expr::new(ExprKind::Error, span));
+            let mut splicer = Splicer {
+                old,
+                group: Some(group),
+                shift,
+            };
+            self.expr = splicer.fold_expr(expr);
+            return Some(span);
+        }
+    }
+
+    /// Parse all the tokens from scratch, returning the span of the source.
+    fn reparse_all(&mut self) -> Span {
+        let tokenizer = Tokenizer::from_tokens(&self.source, &self.tokens);
+        let mut parser = Parser::recovering(tokenizer);
+        self.expr = parser
+            .parse_complete()
+            .expect("recovering parser reports errors rather than returning them");
+        self.errors = parser.into_errors();
+        Span::new(0, self.source.len(), 0, 0)
+    }
+}
+
+/// Line and column after text, starting at line:col.
+fn advance((line, col): (usize, usize), text: &str) -> (usize, usize) {
+    match text.rfind('\n') {
+        Some(last) => (line + text.matches('\n').count(), text.len() - last - 1),
+        None => (line, col + text.len()),
+    }
+}
+
+/// Index of the last OpenParen in tokens without a CloseParen after it.
+fn unmatched_open(tokens: &[LexedToken]) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().rev() {
+        match token.token_type {
+            TokenType::CloseParen => depth += 1,
+            TokenType::OpenParen if depth == 0 => return Some(i),
+            TokenType::OpenParen => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Index of the CloseParen matching the OpenParen at tokens[open].
+fn matching_close(tokens: &[LexedToken], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open + 1) {
+        match token.token_type {
+            TokenType::OpenParen => depth += 1,
+            TokenType::CloseParen if depth == 0 => return Some(i),
+            TokenType::CloseParen => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Whether expr has a subexpression spanning exactly the same bytes as span.
+fn contains_span(expr: &Expr, span: Span) -> bool {
+    let mut finder = SpanFinder { span, found: false };
+    finder.visit_expr(expr);
+    finder.found
+}
+
+/// Visitor looking for an Expr with a given span, visiting only the
+/// expressions that contain it.
+struct SpanFinder {
+    span: Span,
+    found: bool,
+}
+
+impl Visitor for SpanFinder {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if expr.span.range() == self.span.range() {
+            self.found = true;
+        } else if !self.found
+            && expr.span.start <= self.span.start
+            && self.span.end <= expr.span.end
+        {
+            visit::walk_expr(self, expr);
+        }
+    }
+}
+
+/// Mapping of positions at or after the end of an edit, from the old
+/// source to the new one.
+#[derive(Debug, Copy, Clone)]
+struct Shift {
+    /// change in length of the source
+    delta: isize,
+    /// line and column of the end of the edit in the old source
+    old_line: usize,
+    old_col: usize,
+    /// line and column of the end of the edit in the new source
+    new_line: usize,
+    new_col: usize,
+}
+
+impl Shift {
+    fn offset(&self, offset: usize) -> usize {
+        offset.wrapping_add_signed(self.delta)
+    }
+
+    fn unshift_offset(&self, offset: usize) -> usize {
+        offset.wrapping_add_signed(-self.delta)
+    }
+
+    /// Move span, which starts at or after the edit.
+    fn span(&self, span: Span) -> Span {
+        let (line, col) = if span.line == self.old_line {
+            (self.new_line, span.col - self.old_col + self.new_col)
+        } else {
+            (span.line - self.old_line + self.new_line, span.col)
+        };
+        Span::new(self.offset(span.start), self.offset(span.end), line, col)
+    }
+}
+
+/// Folder replacing the Expr spanning old with group, moving the spans
+/// that end after it and reusing the subtrees before it as they are.
+struct Splicer {
+    old: Span,
+    group: Option<Expr>,
+    shift: Shift,
+}
+
+impl Folder for Splicer {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        if expr.span.end <= self.old.start {
+            return expr;
+        }
+        if expr.span.range() == self.old.range() {
+            if let Some(group) = self.group.take() {
+                return group;
+            }
+        }
+        fold::walk_expr(self, expr)
+    }
+
+    fn fold_span(&mut self, span: Span) -> Span {
+        if span.start >= self.old.end {
+            self.shift.span(span)
+        } else if span.end >= self.old.end {
+            Span {
+                end: self.shift.offset(span.end),
+                ..span
+            }
+        } else {
+            span
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,183 @@
+use logos::Logos;
+
+use super::IncrementalParse;
+use super::TextEdit;
+use crate::parse::parse_expr_recovering;
+use crate::parse::LexedToken;
+use crate::parse::TokenType;
+use crate::parse::Tokenizer;
+use crate::span::Span;
+
+/// Assert that parse has the tokens, Expr and errors of parsing its
+/// source from scratch.
+fn assert_fresh(parse: &IncrementalParse) {
+    let source = parse.source();
+    let tokens: Vec<_> = Tokenizer::new(TokenType::lexer(source))
+        .map(|token| LexedToken::from(&token))
+        .collect();
+    assert_eq!(parse.tokens(), tokens, "tokens of {source:?}");
+    let (expr, errors) = parse_expr_recovering(source);
+    assert_eq!(parse.expr(), &expr, "expr of {source:?}");
+    assert_eq!(parse.errors(), errors, "errors of {source:?}");
+}
+
+/// Replace the first occurrence of old in parse's source with new,
+/// returning the span reparsed.
+fn replace(parse: &mut IncrementalParse, old: &str, new: &str) -> Span {
+    let start = parse.source().find(old).unwrap();
+    let span = parse.edit(&TextEdit::new(start..start + old.len(), new));
+    assert_fresh(parse);
+    span
+}
+
+#[test]
+fn test_relex() {
+    let mut parse = IncrementalParse::new("ab + c - 1");
+    assert_fresh(&parse);
+    replace(&mut parse, "b", "bx");
+    assert_eq!(parse.source(), "abx + c - 1");
+    replace(&mut parse, " ", "");
+    replace(&mut parse, "- 1", "->1");
+    replace(&mut parse, ">", "");
+    replace(&mut parse, "abx", "");
+    replace(&mut parse, "1", "12 + 3");
+    assert_eq!(parse.source(), "+ c -12 + 3");
+}
+
+#[test]
+fn test_relex_numbers() {
+    let mut parse = IncrementalParse::new("1e-x + 2");
+    replace(&mut parse, "x", "5");
+    assert_eq!(parse.tokens()[0].token_type, TokenType::Float);
+    replace(&mut parse, "5", "y");
+    replace(&mut parse, "2", "2.");
+    replace(&mut parse, " + 2.", " + 2.5");
+    assert_eq!(parse.tokens().last().unwrap().token_type, TokenType::Float);
+    replace(&mut parse, "1", "0x");
+    assert_eq!(parse.source(), "0xe-y + 2.5");
+}
+
+#[test]
+fn test_comments() {
+    let mut parse = IncrementalParse::new("(1 + 2;\n 3 * 4)");
+    replace(&mut parse, "+", "/* +");
+    assert_eq!(parse.errors().len(), 1);
+    replace(&mut parse, "3", "*/ + 3");
+    assert!(parse.errors().is_empty());
+    replace(&mut parse, "/*", "//");
+    replace(&mut parse, "// +", "+");
+    assert_eq!(parse.source(), "(1 + 2;\n */ + 3 * 4)");
+}
+
+#[test]
+fn test_reparses_group() {
+    let source = "(let f = fn (x: int) -> int (x + 1);\n  f(2) + (3 *\n 4))";
+    let mut parse = IncrementalParse::new(source);
+
+    let span = replace(&mut parse, "1", "10");
+    assert_eq!(&parse.source()[span.range()], "(x + 10)");
+    let span = replace(&mut parse, " + 10", "\n + 1");
+    assert_eq!(&parse.source()[span.range()], "(x\n + 1)");
+    let span = replace(&mut parse, " *", "\n *");
+    assert_eq!(&parse.source()[span.range()], "(3\n *\n 4)");
+    assert_eq!((span.line, span.col), (2, 9));
+
+    // Only the whole source contains the edit to the argument list.
+    let span = replace(&mut parse, "2", "2, 5");
+    assert_eq!(span.range(), 0..parse.source().len());
+}
+
+#[test]
+fn test_reparses_after_errors() {
+    let mut parse = IncrementalParse::new("(1 + (2 * 3))");
+    let span = replace(&mut parse, "*", "*)");
+    assert_eq!(span.range(), 0..parse.source().len());
+    assert!(!parse.errors().is_empty());
+    let span = replace(&mut parse, "*)", "*");
+    assert_eq!(span.range(), 0..parse.source().len());
+    assert!(parse.errors().is_empty());
+    let span = replace(&mut parse, "3", "4");
+    assert_eq!(&parse.source()[span.range()], "(2 * 4)");
+
+    // Parens that change which tokens they group are reparsed from scratch.
+    let mut parse = IncrementalParse::new("((1 ) + (2);)");
+    let span = replace(&mut parse, ") + (", " + ");
+    assert_eq!(&parse.source()[span.range()], "(1  + 2)");
+    let span = replace(&mut parse, " + ", ") + (");
+    assert_eq!(span.range(), 0..parse.source().len());
+}
+
+/// Random edits, each followed by its undo, interleaved with edits that
+/// keep the source valid so that groups are reparsed on their own.
+#[test]
+fn test_random_edits() {
+    const SNIPPETS: [&str; 21] = [
+        "(",
+        ")",
+        "x",
+        " ",
+        "\n",
+        "1",
+        " + ",
+        " < ",
+        ";",
+        "let y = 2;",
+        "/*",
+        "*/",
+        "f(",
+        ", ",
+        "fn (a: int) -> int (a)",
+        "\u{e9}",
+        "\"",
+        "\"a{x}\"",
+        "e-",
+        ".5",
+        "0x",
+    ];
+    const TRIVIA: [&str; 4] = [" ", "\n", "/* \u{e9} */", "// c\n"];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut random = |n: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n as u64) as usize
+    };
+    let source = "(let x = 1;\n let f = fn (a: int) -> int (a * (2 + x));\n (f(3) + 4) * (5 - x))";
+    let mut parse = IncrementalParse::new(source);
+    let mut groups = 0;
+    for _ in 0..500 {
+        let source = parse.source();
+        let mut start = random(source.len() + 1);
+        while !source.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = (start + random(4)).min(source.len());
+        while !source.is_char_boundary(end) {
+            end += 1;
+        }
+        let removed = source[start..end].to_owned();
+        let text = SNIPPETS[random(SNIPPETS.len())];
+        parse.edit(&TextEdit::new(start..end, text));
+        assert_fresh(&parse);
+        parse.edit(&TextEdit::new(start..start + text.len(), removed));
+        assert_fresh(&parse);
+        assert!(parse.errors().is_empty());
+
+        // Replace an int, or insert trivia before a token other than the
+        // paren of an argument list.
+        let token = parse.tokens()[random(parse.tokens().len())];
+        let at = token.span.start..token.span.start;
+        let edit = match token.token_type {
+            TokenType::Int if random(2) == 0 => TextEdit::new(token.span.range(), "(1 + 2)"),
+            TokenType::Int => TextEdit::new(token.span.range(), random(100).to_string()),
+            TokenType::OpenParen => TextEdit::new(at, ""),
+            _ => TextEdit::new(at, TRIVIA[random(TRIVIA.len())]),
+        };
+        let span = parse.edit(&edit);
+        assert_fresh(&parse);
+        if span.len() < parse.source().len() {
+            groups += 1;
+        }
+    }
+    assert!(groups > 100, "only {groups} groups reparsed");
+}
@@ -0,0 +1,48 @@
+//! Values of numeric literals, as lexed by TokenType::Int and Float.
+
+/// Value of an integer literal, or None if it is beyond i64::MAX.
+pub fn int_value(lexeme: &str) -> Option<i64> {
+    let digits = lexeme.replace('_', "");
+    let (digits, radix) = match digits.get(..2) {
+        Some("0x") => (&digits[2..], 16),
+        Some("0o") => (&digits[2..], 8),
+        Some("0b") => (&digits[2..], 2),
+        _ => (&digits[..], 10),
+    };
+    i64::from_str_radix(digits, radix).ok()
+}
+
+/// Value of a float literal, or None if it is too large to be finite.
+/// Literals too small to represent are zero.
+pub fn float_value(lexeme: &str) -> Option<f64> {
+    let value: f64 = lexeme.replace('_', "").parse().ok()?;
+    value.is_finite().then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_int_value() {
+        assert_eq!(int_value("0"), Some(0));
+        assert_eq!(int_value("1_000"), Some(1000));
+        assert_eq!(int_value("0xff"), Some(255));
+        assert_eq!(int_value("0xDEAD_beef"), Some(0xdead_beef));
+        assert_eq!(int_value("0o17"), Some(15));
+        assert_eq!(int_value("0b1010"), Some(10));
+        assert_eq!(int_value("9223372036854775807"), Some(i64::MAX));
+        assert_eq!(int_value("9223372036854775808"), None);
+        assert_eq!(int_value("0x8000_0000_0000_0000"), None);
+    }
+
+    #[test]
+    fn test_float_value() {
+        assert_eq!(float_value("1.5"), Some(1.5));
+        assert_eq!(float_value("1_000.000_1"), Some(1000.0001));
+        assert_eq!(float_value("1e-3"), Some(0.001));
+        assert_eq!(float_value("2.5E2"), Some(250.0));
+        assert_eq!(float_value("1e-400"), Some(0.0));
+        assert_eq!(float_value("1e400"), None);
+    }
+}
@@ -0,0 +1,177 @@
+use super::TokenType as ToT;
+use crate::ast::expr::BinaryOp;
+use crate::ast::expr::UnaryOp;
+
+/// Binding power of an operator; higher binds tighter.
+pub type Precedence = u8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a ** b ** c` is `a ** (b ** c)`
+    Right,
+    /// `a < b < c` is an error
+    None,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BinaryOperator {
+    pub token_type: ToT,
+    pub op: BinaryOp,
+    /// Source text of the operator's token.
+    pub symbol: &'static str,
+    pub precedence: Precedence,
+    pub assoc: Assoc,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct UnaryOperator {
+    pub token_type: ToT,
+    pub op: UnaryOp,
+    /// Source text of the operator's token.
+    pub symbol: &'static str,
+    /// Binding power of the operand: the operand extends over binary
+    /// operators with at least this precedence.
+    pub precedence: Precedence,
+}
+
+const fn binary(
+    token_type: ToT,
+    op: BinaryOp,
+    symbol: &'static str,
+    precedence: Precedence,
+    assoc: Assoc,
+) -> BinaryOperator {
+    BinaryOperator {
+        token_type,
+        op,
+        symbol,
+        precedence,
+        assoc,
+    }
+}
+
+const fn unary(
+    token_type: ToT,
+    op: UnaryOp,
+    symbol: &'static str,
+    precedence: Precedence,
+) -> UnaryOperator {
+    UnaryOperator {
+        token_type,
+        op,
+        symbol,
+        precedence,
+    }
+}
+
+/// Binary operators, loosest binding first.
+/// Adding an operator needs only a TokenType, a BinaryOp, and an entry here.
+/// The parser and the Display of Expr both follow this table.
+pub const BINARY_OPERATORS: &[BinaryOperator] = &[
+    binary(ToT::Or, BinaryOp::Or, "or", 1, Assoc::Left),
+    binary(ToT::And, BinaryOp::And, "and", 2, Assoc::Left),
+    binary(ToT::EqualEqual, BinaryOp::Equal, "==", 3, Assoc::None),
+    binary(ToT::BangEqual, BinaryOp::NotEqual, "!=", 3, Assoc::None),
+    binary(ToT::LAngle, BinaryOp::Less, "<", 4, Assoc::None),
+    binary(ToT::LAngleEq, BinaryOp::LessEq, "<=", 4, Assoc::None),
+    binary(ToT::RAngle, BinaryOp::Great, ">", 4, Assoc::None),
+    binary(ToT::RAngleEq, BinaryOp::GreatEq, ">=", 4, Assoc::None),
+    binary(ToT::Plus, BinaryOp::Add, "+", 5, Assoc::Left),
+    binary(ToT::Minus, BinaryOp::Sub, "-", 5, Assoc::Left),
+    binary(ToT::Star, BinaryOp::Mult, "*", 6, Assoc::Left),
+    binary(ToT::Slash, BinaryOp::Div, "/", 6, Assoc::Left),
+];
+
+/// Prefix operators.  These bind tighter than every binary operator.
+pub const UNARY_OPERATORS: &[UnaryOperator] = &[
+    unary(ToT::Not, UnaryOp::Not, "not", 7),
+    unary(ToT::Minus, UnaryOp::Neg, "-", 7),
+    unary(ToT::Plus, UnaryOp::Pos, "+", 7),
+    unary(ToT::Len, UnaryOp::Len, "len", 7),
+];
+
+impl BinaryOperator {
+    /// The binary operator for a token, if any.
+    pub fn from_token(token_type: ToT) -> Option<Self> {
+        BINARY_OPERATORS
+            .iter()
+            .find(|o| o.token_type == token_type)
+            .copied()
+    }
+
+    pub fn from_op(op: BinaryOp) -> Self {
+        *BINARY_OPERATORS
+            .iter()
+            .find(|o| o.op == op)
+            .expect("every BinaryOp is in BINARY_OPERATORS")
+    }
+}
+
+impl UnaryOperator {
+    /// The prefix operator for a token, if any.
+    pub fn from_token(token_type: ToT) -> Option<Self> {
+        UNARY_OPERATORS
+            .iter()
+            .find(|o| o.token_type == token_type)
+            .copied()
+    }
+
+    pub fn from_op(op: UnaryOp) -> Self {
+        *UNARY_OPERATORS
+            .iter()
+            .find(|o| o.op == op)
+            .expect("every UnaryOp is in UNARY_OPERATORS")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_are_unambiguous() {
+        for (i, a) in BINARY_OPERATORS.iter().enumerate() {
+            for b in &BINARY_OPERATORS[i + 1..] {
+                assert_ne!(a.token_type, b.token_type);
+                assert_ne!(a.op, b.op);
+            }
+        }
+        for (i, a) in UNARY_OPERATORS.iter().enumerate() {
+            for b in &UNARY_OPERATORS[i + 1..] {
+                assert_ne!(a.token_type, b.token_type);
+                assert_ne!(a.op, b.op);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let operator = BinaryOperator::from_token(ToT::Star).unwrap();
+        assert_eq!(operator.op, BinaryOp::Mult);
+        assert_eq!(
+            BinaryOperator::from_op(BinaryOp::Mult).token_type,
+            ToT::Star
+        );
+        assert!(BinaryOperator::from_token(ToT::Not).is_none());
+        assert_eq!(
+            UnaryOperator::from_token(ToT::Minus).unwrap().op,
+            UnaryOp::Neg
+        );
+    }
+
+    #[test]
+    fn test_symbols_lex_to_tokens() {
+        use logos::Logos;
+
+        for operator in BINARY_OPERATORS {
+            let tokens: Vec<_> = ToT::lexer(operator.symbol).collect();
+            assert_eq!(tokens, vec![Ok(operator.token_type)]);
+        }
+        for operator in UNARY_OPERATORS {
+            let tokens: Vec<_> = ToT::lexer(operator.symbol).collect();
+            assert_eq!(tokens, vec![Ok(operator.token_type)]);
+        }
+    }
+}
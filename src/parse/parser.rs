@@ -1,169 +1,812 @@
+use std::ops::Range;
+use std::sync::Arc;
+
 use super::error::ParseError;
+use super::number::float_value;
+use super::number::int_value;
+use super::operator::Assoc;
+use super::operator::BinaryOperator;
+use super::operator::Precedence;
+use super::operator::UnaryOperator;
+use super::string::str_segments;
+use super::string::StrSegment;
+use super::tokenizer::Token;
 use super::tokenizer::Tokenizer;
 use super::ParseResult;
 use super::TokenType as ToT;
 use crate::ast::expr::BinaryOp;
 use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Function;
+use crate::ast::expr::Ident;
+use crate::ast::expr::Param;
+use crate::ast::expr::StrPart;
 use crate::ast::expr::UnaryOp;
+use crate::cst::GreenElement;
+use crate::cst::GreenNode;
+use crate::cst::GreenToken;
+use crate::cst::NodeKind;
+use crate::cst::SyntaxNode;
+use crate::span::Span;
+use crate::ttype::Type;
+use logos::Logos;
+
+/// Position among the children of the syntax tree being built, where a
+/// node may later start.
+type Checkpoint = usize;
 
 pub struct Parser<'source> {
     tokenizer: Tokenizer<'source>,
+    /// Errors reported so far, if recovering from errors.
+    /// None -> Return the first error encountered.
+    /// Some(errors) -> Record errors here, and replace unparseable
+    ///   expressions with ExprKind::Error.
+    errors: Option<Vec<ParseError>>,
+    /// Children of the unfinished nodes of the syntax tree, if building one.
+    /// Consumed tokens are added as nodes are started and finished, and a
+    /// finished node replaces the children since its checkpoint.
+    cst: Option<Vec<GreenElement>>,
 }
 
 impl<'source> Parser<'source> {
     pub fn new(tokenizer: Tokenizer<'source>) -> Self {
-        Self { tokenizer }
+        Self {
+            tokenizer,
+            errors: None,
+            cst: None,
+        }
+    }
+
+    /// Create a Parser that recovers from errors rather than returning them.
+    /// Recovering parses always succeed; the errors are available from errors().
+    pub fn recovering(tokenizer: Tokenizer<'source>) -> Self {
+        Self {
+            tokenizer,
+            errors: Some(Vec::new()),
+            cst: None,
+        }
+    }
+
+    /// Create a recovering Parser that also builds a lossless syntax tree
+    /// of the input it consumes, available from into_cst().
+    pub fn lossless(mut tokenizer: Tokenizer<'source>) -> Self {
+        tokenizer.record_consumed();
+        Self {
+            cst: Some(Vec::new()),
+            ..Self::recovering(tokenizer)
+        }
+    }
+
+    /// Errors recorded by a recovering Parser, in the order encountered.
+    pub fn errors(&self) -> &[ParseError] {
+        self.errors.as_deref().unwrap_or_default()
+    }
+
+    pub fn into_errors(self) -> Vec<ParseError> {
+        self.errors.unwrap_or_default()
+    }
+
+    /// The syntax tree built by a lossless Parser, with the errors recorded.
+    /// Any trivia left after the last token consumed end the tree.
+    pub fn into_cst(mut self) -> (SyntaxNode, Vec<ParseError>) {
+        let children = self.take_cst();
+        let root = GreenNode::new(NodeKind::Root, children);
+        (SyntaxNode::new_root(Arc::new(root)), self.into_errors())
+    }
+
+    /// Parse an expression, which must make up the rest of the input.
+    pub fn parse_complete(&mut self) -> ParseResult<Expr> {
+        let expr = self.parse_expression()?;
+        self.finish(expr)
+    }
+
+    /// Parse a block item, an expression or a `let`, which must make up the
+    /// rest of the input.
+    pub fn parse_complete_item(&mut self) -> ParseResult<Expr> {
+        let expr = self.block_item()?;
+        self.finish(expr)
+    }
+
+    /// Report any input left after expr.
+    fn finish(&mut self, expr: Expr) -> ParseResult<Expr> {
+        let checkpoint = self.checkpoint();
+        if let Some(token) = self.tokenizer.advance() {
+            let error = match token.token_type {
+                ToT::UnknownToken => ParseError::unknown_token(token),
+                ToT::UnterminatedComment => ParseError::unterminated_comment(token),
+                ToT::UnterminatedString => ParseError::unterminated_string(token),
+                ToT::Semicolon => ParseError::stray_semicolon(token),
+                _ => ParseError::unexpected_token(token, "end of input"),
+            };
+            self.report(error)?;
+            while self.tokenizer.advance().is_some() {}
+            self.node(checkpoint, NodeKind::Skipped);
+        }
+        Ok(expr)
     }
 
     // Specific parsing for AST
     pub fn parse_expression(&mut self) -> ParseResult<Expr> {
-        if self.match_next(ToT::If) {
-            self.parse_if()
+        let checkpoint = self.checkpoint();
+        if let Some(span) = self.match_span(ToT::If) {
+            let expr = self.parse_if(span)?;
+            self.node(checkpoint, NodeKind::If);
+            Ok(expr)
         } else {
-            self.log_or()
+            self.binary_expr(0)
         }
     }
 
-    // If Token must already be consumed
-    fn parse_if(&mut self) -> ParseResult<Expr> {
+    // If Token must already be consumed; if_span is its span.
+    fn parse_if(&mut self, if_span: Span) -> ParseResult<Expr> {
         let condition = self.parse_expression()?;
-        self.tokenizer.expect(ToT::OpenParen)?;
-        let then_expr = self.block()?;
+        let at = self.next_span();
+        let then_expr = match self.expect(ToT::OpenParen)? {
+            Some(open_span) => self.block(open_span)?,
+            None => {
+                let checkpoint = self.checkpoint();
+                self.node(checkpoint, NodeKind::Error);
+                Expr::new(ExprKind::Error, at.start_span())
+            }
+        };
         let else_expr = if self.match_next(ToT::Else) {
             Some(self.parse_expression()?)
         } else {
             None
         };
-        Ok(Expr::If {
-            condition: Box::new(condition),
-            then_expr: Box::new(then_expr),
-            else_expr: else_expr.map(Box::new),
-        })
+        let span = if_span.to(else_expr.as_ref().unwrap_or(&then_expr).span);
+        Ok(Expr::ifthen(condition, then_expr, else_expr).with_span(span))
     }
 
-    fn log_or(&mut self) -> ParseResult<Expr> {
-        let left = self.log_and()?;
-
-        if self.match_next(ToT::Or) {
-            return Ok(Expr::binary(left, BinaryOp::Or, self.log_or()?));
+    /// Parse a chain of binary operators with at least min_precedence,
+    /// by precedence climbing over the operator table.
+    fn binary_expr(&mut self, min_precedence: Precedence) -> ParseResult<Expr> {
+        let checkpoint = self.checkpoint();
+        let mut left = self.unary()?;
+
+        while let Some(operator) = self.peek_binary(min_precedence) {
+            self.tokenizer.advance();
+            let right_precedence = match operator.assoc {
+                Assoc::Left | Assoc::None => operator.precedence + 1,
+                Assoc::Right => operator.precedence,
+            };
+            left = binary(left, operator.op, self.binary_expr(right_precedence)?);
+            self.node(checkpoint, NodeKind::Binary);
+
+            if operator.assoc == Assoc::None {
+                self.reject_chain(operator.precedence)?;
+            }
         }
 
         Ok(left)
     }
 
-    fn log_and(&mut self) -> ParseResult<Expr> {
-        let left = self.equality()?;
-
-        if self.match_next(ToT::And) {
-            return Ok(Expr::binary(left, BinaryOp::And, self.log_and()?));
+    fn unary(&mut self) -> ParseResult<Expr> {
+        let checkpoint = self.checkpoint();
+        let operator = self
+            .tokenizer
+            .peek()
+            .and_then(|token| UnaryOperator::from_token(token.token_type));
+        if let Some(operator) = operator {
+            let token = self.tokenizer.force_advance()?;
+            let right = self.binary_expr(operator.precedence)?;
+            self.node(checkpoint, NodeKind::Unary);
+            return Ok(unary(Span::from(&token.data), operator.op, right));
         }
 
-        Ok(left)
+        self.call()
     }
 
-    fn equality(&mut self) -> ParseResult<Expr> {
-        let left = self.comparison()?;
-
-        if self.match_next(ToT::BangEqual) {
-            return Ok(Expr::binary(left, BinaryOp::NotEqual, self.comparison()?));
+    /// Parse a primary expression followed by any number of argument lists.
+    /// An argument list must start immediately after the callee, so that
+    /// `if f (x)` is a condition `f` followed by a block.
+    fn call(&mut self) -> ParseResult<Expr> {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.primary()?;
+        loop {
+            match self.tokenizer.peek() {
+                Some(token)
+                    if token.token_type == ToT::OpenParen
+                        && Span::from(&token.data).start == expr.span.end => {}
+                _ => return Ok(expr),
+            }
+            let args_checkpoint = self.checkpoint();
+            self.tokenizer.advance();
+            let args = self.arguments()?;
+            let close_span = self.expect(ToT::CloseParen)?;
+            self.node(args_checkpoint, NodeKind::ArgList);
+            self.node(checkpoint, NodeKind::Call);
+            let last_span = close_span.or(args.last().map(|arg| arg.span));
+            let span = expr.span.to(last_span.unwrap_or(expr.span));
+            expr = Expr::call(expr, args).with_span(span);
         }
+    }
 
-        if self.match_next(ToT::EqualEqual) {
-            return Ok(Expr::binary(left, BinaryOp::Equal, self.comparison()?));
+    // This expects the open paren to already be consumed, and does not consume the close paren.
+    fn arguments(&mut self) -> ParseResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        if self.tokenizer.peek().map(|token| token.token_type) == Some(ToT::CloseParen) {
+            return Ok(args);
         }
-
-        Ok(left)
+        args.push(self.parse_expression()?);
+        while self.match_next(ToT::Comma) {
+            args.push(self.parse_expression()?);
+        }
+        Ok(args)
     }
 
-    fn comparison(&mut self) -> ParseResult<Expr> {
-        let left = self.term()?;
+    fn primary(&mut self) -> ParseResult<Expr> {
+        let checkpoint = self.checkpoint();
+        let Some(token) = self.tokenizer.peek().cloned() else {
+            let span = self.tokenizer.end_span();
+            let error = self.tokenizer.force_advance().unwrap_err();
+            let expr = self.recover(error, span)?;
+            self.node(checkpoint, NodeKind::Error);
+            return Ok(expr);
+        };
+        let span = Span::from(&token.data);
+        match token.token_type {
+            // Leave synchronization tokens for the enclosing block, call or if.
+            ToT::CloseParen | ToT::Else | ToT::Semicolon | ToT::Comma => {
+                let error = ParseError::unexpected_token(token, "primary expression");
+                let expr = self.recover(error, span.start_span())?;
+                self.node(checkpoint, NodeKind::Error);
+                return Ok(expr);
+            }
+            _ => self.tokenizer.advance(),
+        };
+        let (expr, kind) = match token.token_type {
+            ToT::False => (Expr::bool(false).with_span(span), NodeKind::Literal),
+            ToT::True => (Expr::bool(true).with_span(span), NodeKind::Literal),
+            ToT::Int => match int_value(token.data.lexeme) {
+                Some(i) => (Expr::int(i).with_span(span), NodeKind::Literal),
+                None => {
+                    self.report(ParseError::integer_overflow(token))?;
+                    (Expr::new(ExprKind::Error, span), NodeKind::Error)
+                }
+            },
+            ToT::Float => match float_value(token.data.lexeme) {
+                Some(x) => (Expr::float(x).with_span(span), NodeKind::Literal),
+                None => {
+                    self.report(ParseError::float_overflow(token))?;
+                    (Expr::new(ExprKind::Error, span), NodeKind::Error)
+                }
+            },
+            ToT::Str => return self.string(checkpoint, token),
+            ToT::Identifier => (Expr::var(token.data.lexeme).with_span(span), NodeKind::Var),
+            ToT::OpenParen => return self.block(span),
+            ToT::Fn => (self.parse_fn(span)?, NodeKind::Fn),
+            ToT::UnknownToken => {
+                let error = ParseError::unknown_token(token);
+                (self.recover(error, span)?, NodeKind::Error)
+            }
+            ToT::UnterminatedComment => {
+                let error = ParseError::unterminated_comment(token);
+                (self.recover(error, span)?, NodeKind::Error)
+            }
+            ToT::UnterminatedString => {
+                let error = ParseError::unterminated_string(token);
+                (self.recover(error, span)?, NodeKind::Error)
+            }
+            _ => {
+                let error = ParseError::unexpected_token(token, "primary expression");
+                (self.recover(error, span)?, NodeKind::Error)
+            }
+        };
+        self.node(checkpoint, kind);
+        Ok(expr)
+    }
 
-        if self.match_next(ToT::RAngle) {
-            return Ok(Expr::binary(left, BinaryOp::Great, self.term()?));
+    /// Parse a string literal, already consumed.  Each interpolated
+    /// expression is parsed from the token's source by a parser of its own.
+    fn string(&mut self, checkpoint: Checkpoint, token: Token<'source>) -> ParseResult<Expr> {
+        let span = Span::from(&token.data);
+        let segments = match str_segments(token.data.lexeme) {
+            Ok(segments) => segments,
+            Err(range) => {
+                self.report(ParseError::bad_escape(token, range))?;
+                self.node(checkpoint, NodeKind::Error);
+                return Ok(Expr::new(ExprKind::Error, span));
+            }
+        };
+        if let [StrSegment::Text(text)] = &segments[..] {
+            self.node(checkpoint, NodeKind::Literal);
+            return Ok(Expr::str(text.clone()).with_span(span));
+        }
+        // The token is split into pieces around the interpolations' trees.
+        self.drop_consumed_last();
+        let lexeme = token.data.lexeme;
+        let mut piece_start = 0;
+        let mut parts = Vec::new();
+        for segment in segments {
+            match segment {
+                StrSegment::Text(text) => parts.push(StrPart::Text(text)),
+                StrSegment::Interpolation(range) => {
+                    self.add_token(ToT::Str, &lexeme[piece_start..range.start]);
+                    piece_start = range.end;
+                    parts.push(StrPart::Expr(self.interpolation(&token, range)?));
+                }
+            }
         }
+        self.add_token(ToT::Str, &lexeme[piece_start..]);
+        self.node(checkpoint, NodeKind::Interpolate);
+        Ok(Expr::interpolate(parts).with_span(span))
+    }
 
-        if self.match_next(ToT::RAngleEq) {
-            return Ok(Expr::binary(left, BinaryOp::GreatEq, self.term()?));
+    /// Parse the expression interpolated at range within a string token.
+    /// Running out of input within it is UnfinishedInterpolation.
+    fn interpolation(&mut self, token: &Token<'source>, range: Range<usize>) -> ParseResult<Expr> {
+        let start = token.data.span.start;
+        let lexer = ToT::lexer(&self.tokenizer.source()[..start + range.end]);
+        // A string token lies on one line.
+        let col = token.data.col + range.start;
+        let tokenizer = Tokenizer::starting_at(lexer, start + range.start, token.data.line, col);
+        let mut parser = match self.cst {
+            Some(_) => Parser::lossless(tokenizer),
+            None => Parser::new(tokenizer),
+        };
+        parser.errors = self.errors.take();
+        let reported = parser.errors().len();
+        let expr = parser.parse_complete().map_err(unfinished_interpolation);
+        if let Some(errors) = &mut parser.errors {
+            let unfinished: Vec<_> = errors
+                .drain(reported..)
+                .map(unfinished_interpolation)
+                .collect();
+            errors.extend(unfinished);
         }
+        self.errors = parser.errors.take();
+        let children = parser.take_cst();
+        if let Some(cst) = &mut self.cst {
+            cst.extend(children);
+        }
+        expr
+    }
 
-        if self.match_next(ToT::LAngle) {
-            return Ok(Expr::binary(left, BinaryOp::Less, self.term()?));
+    // This expects the open delimiter to already be consumed; open_span is its span.
+    // A block is one or more let bindings or expressions, separated by `;`,
+    // with an optional trailing `;`.  A single expression in parentheses
+    // is just grouped, and not a Block, though its span includes the parens.
+    fn block(&mut self, open_span: Span) -> ParseResult<Expr> {
+        let checkpoint = self.checkpoint_last();
+        self.skip_stray_semicolons()?;
+        let mut contents = vec![self.block_item()?];
+        let mut trailing_semicolon = false;
+        while self.match_next(ToT::Semicolon) {
+            self.skip_stray_semicolons()?;
+            if self.tokenizer.peek().map(|token| token.token_type) == Some(ToT::CloseParen) {
+                trailing_semicolon = true;
+                break;
+            }
+            contents.push(self.block_item()?);
+        }
+        let close_span = self.expect(ToT::CloseParen)?;
+        let last_span = close_span.unwrap_or(contents[contents.len() - 1].span);
+        let span = open_span.to(last_span);
+        // A single expression in parentheses is just grouping, but a let
+        // needs its own block to scope its binding.
+        let single = contents.len() == 1 && !trailing_semicolon;
+        if single && !matches!(contents[0].kind, ExprKind::Let { .. }) {
+            self.node(checkpoint, NodeKind::Paren);
+            return Ok(contents.pop().unwrap().with_span(span));
         }
+        self.node(checkpoint, NodeKind::Block);
+        let block = ExprKind::Block {
+            contents,
+            trailing_semicolon,
+        };
+        Ok(Expr::new(block, span))
+    }
 
-        if self.match_next(ToT::LAngleEq) {
-            return Ok(Expr::binary(left, BinaryOp::LessEq, self.term()?));
+    fn block_item(&mut self) -> ParseResult<Expr> {
+        let checkpoint = self.checkpoint();
+        match self.match_span(ToT::Let) {
+            Some(let_span) => {
+                let expr = self.parse_let(let_span)?;
+                self.node(checkpoint, NodeKind::Let);
+                Ok(expr)
+            }
+            None => self.parse_expression(),
         }
+    }
 
-        Ok(left)
+    /// Report ParseError::StraySemicolon for each `;` that does not follow a block item.
+    fn skip_stray_semicolons(&mut self) -> ParseResult<()> {
+        while let Some(token) = self.tokenizer.opt(ToT::Semicolon) {
+            self.report(ParseError::stray_semicolon(token))?;
+        }
+        Ok(())
     }
 
-    fn term(&mut self) -> ParseResult<Expr> {
-        let left = self.factor()?;
+    // Let Token must already be consumed; let_span is its span.
+    fn parse_let(&mut self, let_span: Span) -> ParseResult<Expr> {
+        let token = match self.tokenizer.peek().cloned() {
+            Some(token) if token.token_type == ToT::Identifier => token,
+            Some(token) => {
+                let error = ParseError::unexpected_token(token, "identifier");
+                return self.recover(error, let_span);
+            }
+            None => {
+                let error = self.tokenizer.force_advance().unwrap_err();
+                return self.recover(error, let_span);
+            }
+        };
+        self.tokenizer.advance();
+        let name = Ident::new(token.data.lexeme, Span::from(&token.data));
+        if self.expect(ToT::Equal)?.is_none() {
+            return Ok(Expr::new(ExprKind::Error, let_span.to(name.span)));
+        }
+        let value = self.parse_expression()?;
+        let span = let_span.to(value.span);
+        Ok(Expr::new(
+            ExprKind::Let {
+                name,
+                value: Box::new(value),
+            },
+            span,
+        ))
+    }
 
-        if self.match_next(ToT::Minus) {
-            return Ok(Expr::binary(left, BinaryOp::Sub, self.term()?));
+    // Fn Token must already be consumed; fn_span is its span.
+    // A function is `fn [name] (param: type, ...) -> type (body)`.
+    fn parse_fn(&mut self, fn_span: Span) -> ParseResult<Expr> {
+        let name = self
+            .tokenizer
+            .opt(ToT::Identifier)
+            .map(|token| Ident::new(token.data.lexeme, Span::from(&token.data)));
+        let error = Expr::new(ExprKind::Error, fn_span);
+        let checkpoint = self.checkpoint();
+        let params = self.params()?;
+        self.node(checkpoint, NodeKind::ParamList);
+        let Some(params) = params else {
+            return Ok(error);
+        };
+        if self.expect(ToT::Arrow)?.is_none() {
+            return Ok(error);
         }
+        let Some(ret) = self.parse_type()? else {
+            return Ok(error);
+        };
+        let Some(open_span) = self.expect(ToT::OpenParen)? else {
+            return Ok(error);
+        };
+        let body = self.block(open_span)?;
+        let span = fn_span.to(body.span);
+        let function = Function {
+            name,
+            params,
+            ret,
+            body: Box::new(body),
+        };
+        Ok(Expr::new(ExprKind::Fn(function), span))
+    }
 
-        if self.match_next(ToT::Plus) {
-            return Ok(Expr::binary(left, BinaryOp::Add, self.term()?));
+    /// Parse a parenthesized, comma-separated list of `name: type` parameters.
+    /// When recovering, return None if the list could not be parsed.
+    fn params(&mut self) -> ParseResult<Option<Vec<Param>>> {
+        let mut params = Vec::new();
+        if self.expect(ToT::OpenParen)?.is_none() {
+            return Ok(None);
         }
+        if self.match_next(ToT::CloseParen) {
+            return Ok(Some(params));
+        }
+        loop {
+            let checkpoint = self.checkpoint();
+            let param = self.param()?;
+            self.node(checkpoint, NodeKind::Param);
+            let Some(param) = param else {
+                return Ok(None);
+            };
+            params.push(param);
+            if !self.match_next(ToT::Comma) {
+                break;
+            }
+        }
+        Ok(self.expect(ToT::CloseParen)?.map(|_| params))
+    }
 
-        Ok(left)
+    /// Parse a `name: type` parameter.
+    /// When recovering, return None if it could not be parsed.
+    fn param(&mut self) -> ParseResult<Option<Param>> {
+        let Some(name) = self.expect_ident("parameter name")? else {
+            return Ok(None);
+        };
+        if self.expect(ToT::Colon)?.is_none() {
+            return Ok(None);
+        }
+        let ttype = self.parse_type()?;
+        Ok(ttype.map(|ttype| Param { name, ttype }))
     }
 
-    fn factor(&mut self) -> ParseResult<Expr> {
-        let left = self.unary()?;
+    /// Parse a type: `bool`, `int`, `float`, `unit`, `str`, or `fn(type, ...) -> type`.
+    /// When recovering, return None if the type could not be parsed.
+    fn parse_type(&mut self) -> ParseResult<Option<Type>> {
+        let checkpoint = self.checkpoint();
+        let token_type = self.tokenizer.peek().map(|token| token.token_type);
+        let ttype = match token_type {
+            Some(ToT::Bool) => Some(Type::Bool),
+            Some(ToT::IntType) => Some(Type::Int),
+            Some(ToT::FloatType) => Some(Type::Float),
+            Some(ToT::UnitType) => Some(Type::Unit),
+            Some(ToT::StrType) => Some(Type::Str),
+            Some(ToT::Fn) => None,
+            _ => {
+                self.reject("type")?;
+                self.node(checkpoint, NodeKind::Type);
+                return Ok(None);
+            }
+        };
+        self.tokenizer.advance();
+        let ttype = match ttype {
+            Some(ttype) => Some(ttype),
+            None => self.parse_fn_type()?,
+        };
+        self.node(checkpoint, NodeKind::Type);
+        Ok(ttype)
+    }
 
-        if self.match_next(ToT::Slash) {
-            return Ok(Expr::binary(left, BinaryOp::Div, self.factor()?));
+    // Fn Token must already be consumed.
+    fn parse_fn_type(&mut self) -> ParseResult<Option<Type>> {
+        let mut params = Vec::new();
+        if self.expect(ToT::OpenParen)?.is_none() {
+            return Ok(None);
         }
+        if !self.match_next(ToT::CloseParen) {
+            loop {
+                let Some(param) = self.parse_type()? else {
+                    return Ok(None);
+                };
+                params.push(param);
+                if !self.match_next(ToT::Comma) {
+                    break;
+                }
+            }
+            if self.expect(ToT::CloseParen)?.is_none() {
+                return Ok(None);
+            }
+        }
+        if self.expect(ToT::Arrow)?.is_none() {
+            return Ok(None);
+        }
+        let ret = self.parse_type()?;
+        Ok(ret.map(|ret| Type::function(params, ret)))
+    }
 
-        if self.match_next(ToT::Star) {
-            return Ok(Expr::binary(left, BinaryOp::Mult, self.factor()?));
+    /// Consume the next token, which must be an identifier.
+    /// When recovering, report the error, synchronize, and return None.
+    fn expect_ident(&mut self, expected: &str) -> ParseResult<Option<Ident>> {
+        match self.tokenizer.peek().cloned() {
+            Some(token) if token.token_type == ToT::Identifier => {
+                self.tokenizer.advance();
+                Ok(Some(Ident::new(token.data.lexeme, Span::from(&token.data))))
+            }
+            _ => {
+                self.reject(expected)?;
+                Ok(None)
+            }
         }
+    }
 
-        Ok(left)
+    // Error handling
+
+    /// Report that the next token is not the expected one, then skip to the
+    /// next synchronization point when recovering.
+    fn reject(&mut self, expected: &str) -> ParseResult<()> {
+        let at = self.next_span();
+        let error = match self.tokenizer.peek().cloned() {
+            Some(token) if token.token_type == ToT::UnknownToken => {
+                ParseError::unknown_token(token)
+            }
+            Some(token) if token.token_type == ToT::UnterminatedComment => {
+                ParseError::unterminated_comment(token)
+            }
+            Some(token) if token.token_type == ToT::UnterminatedString => {
+                ParseError::unterminated_string(token)
+            }
+            Some(token) => ParseError::unexpected_token(token, expected),
+            None => self.tokenizer.force_advance().unwrap_err(),
+        };
+        self.report(error)?;
+        self.synchronize(at.line);
+        Ok(())
     }
 
-    fn unary(&mut self) -> ParseResult<Expr> {
-        if self.match_next(ToT::Not) {
-            return Ok(Expr::unary(UnaryOp::Not, self.unary()?));
+    /// Return error if not recovering.  Otherwise record it, skip to the next
+    /// synchronization point, and return an ExprKind::Error covering span and
+    /// the skipped tokens.
+    fn recover(&mut self, error: ParseError, span: Span) -> ParseResult<Expr> {
+        self.report(error)?;
+        let span = match self.synchronize(span.line) {
+            Some(skipped) => span.to(skipped),
+            None => span,
+        };
+        Ok(Expr::new(ExprKind::Error, span))
+    }
+
+    /// Return error if not recovering; otherwise record it.
+    /// Repeats of the last error (eg Eof in nested blocks) are recorded once.
+    fn report(&mut self, error: ParseError) -> ParseResult<()> {
+        match &mut self.errors {
+            None => Err(error),
+            Some(errors) => {
+                if errors.last() != Some(&error) {
+                    errors.push(error);
+                }
+                Ok(())
+            }
         }
-        if self.match_next(ToT::Minus) {
-            return Ok(Expr::unary(UnaryOp::Neg, self.unary()?));
+    }
+
+    /// Skip tokens up to the next synchronization point: a `)`, `;`, `,` or `else`
+    /// outside of any skipped parens, a token on a later line than the
+    /// previous one, or the end of input.  Return the span of skipped tokens.
+    fn synchronize(&mut self, mut line: usize) -> Option<Span> {
+        let checkpoint = self.checkpoint();
+        let mut skipped: Option<Span> = None;
+        let mut depth = 0;
+        while let Some(token) = self.tokenizer.peek() {
+            let span = Span::from(&token.data);
+            match token.token_type {
+                ToT::CloseParen | ToT::Else | ToT::Semicolon | ToT::Comma if depth == 0 => break,
+                _ if depth == 0 && span.line > line => break,
+                ToT::OpenParen => depth += 1,
+                ToT::CloseParen => depth -= 1,
+                _ => (),
+            }
+            line = span.line;
+            skipped = Some(skipped.map_or(span, |skipped| skipped.to(span)));
+            self.tokenizer.advance();
         }
-        if self.match_next(ToT::Plus) {
-            return Ok(Expr::unary(UnaryOp::Pos, self.unary()?));
+        if skipped.is_some() {
+            self.node(checkpoint, NodeKind::Skipped);
         }
-
-        self.primary()
+        skipped
     }
 
-    fn primary(&mut self) -> ParseResult<Expr> {
-        let token = self.tokenizer.force_advance()?;
-        match token.token_type {
-            ToT::False => Ok(Expr::bool(false)),
-            ToT::True => Ok(Expr::bool(true)),
-            ToT::Int => {
-                let i: i64 = token.data.lexeme.parse().unwrap();
-                Ok(Expr::int(i))
+    /// Consume the next token, which must be of the given type, and return its span.
+    /// When recovering from an error, return the span of the token if it is
+    /// found at the next synchronization point, or None if not.
+    fn expect(&mut self, token_type: ToT) -> ParseResult<Option<Span>> {
+        let at = self.next_span();
+        match self.tokenizer.expect(token_type) {
+            Ok(token) => Ok(Some(Span::from(&token.data))),
+            Err(error) => {
+                self.report(error)?;
+                self.synchronize(at.line);
+                Ok(self.match_span(token_type))
             }
-            ToT::OpenParen => self.block(),
-            _ => Err(ParseError::unexpected_token(token, "primary expression")),
         }
     }
 
-    // This expects the open delimiter to already be consumed
-    // TODO: handle semicolons
-    fn block(&mut self) -> ParseResult<Expr> {
-        let mut contents = Vec::new();
-        contents.push(self.parse_expression()?);
-        self.tokenizer.expect(ToT::CloseParen)?;
-        Ok(Expr::Block(contents))
+    /// Span of the next token, or an empty span at the end of input.
+    fn next_span(&mut self) -> Span {
+        match self.tokenizer.peek() {
+            Some(token) => Span::from(&token.data),
+            None => self.tokenizer.end_span(),
+        }
     }
 
     // Generic Parsing functions
     fn match_next(&mut self, token_type: ToT) -> bool {
         self.tokenizer.opt(token_type).is_some()
     }
+
+    /// The next token's binary operator, if it binds at least as tight as min_precedence.
+    /// This does not consume the token.
+    fn peek_binary(&mut self, min_precedence: Precedence) -> Option<BinaryOperator> {
+        let token = self.tokenizer.peek()?;
+        BinaryOperator::from_token(token.token_type)
+            .filter(|operator| operator.precedence >= min_precedence)
+    }
+
+    /// Report ParseError::ChainedComparison if the next token is a
+    /// non-associative operator of the given precedence.
+    /// When recovering, the chain is parsed left-associatively.
+    fn reject_chain(&mut self, precedence: Precedence) -> ParseResult<()> {
+        match self.peek_binary(precedence) {
+            Some(operator) if operator.precedence == precedence => {
+                let token = self.tokenizer.peek().cloned().unwrap();
+                self.report(ParseError::chained_comparison(token))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Like match_next, but return the span of the matched token.
+    fn match_span(&mut self, token_type: ToT) -> Option<Span> {
+        self.tokenizer
+            .opt(token_type)
+            .map(|token| Span::from(&token.data))
+    }
+
+    // Syntax tree building, which does nothing unless building one
+
+    /// Checkpoint for a node starting at the next token, after the trivia
+    /// before it.
+    fn checkpoint(&mut self) -> Checkpoint {
+        if self.cst.is_none() {
+            return 0;
+        }
+        self.tokenizer.peek();
+        self.add_consumed(|consumed| consumed.len());
+        self.cst.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Checkpoint for a node starting at the last token consumed.
+    fn checkpoint_last(&mut self) -> Checkpoint {
+        self.add_consumed(|consumed| last_significant(consumed).unwrap_or(0));
+        self.cst.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Finish a node of the given kind, with the children since checkpoint
+    /// and the tokens consumed since, up to the last one that is not trivia.
+    fn node(&mut self, checkpoint: Checkpoint, kind: NodeKind) {
+        self.add_consumed(|consumed| last_significant(consumed).map_or(0, |i| i + 1));
+        if let Some(children) = &mut self.cst {
+            let node = GreenNode::new(kind, children.drain(checkpoint..).collect());
+            children.push(GreenElement::Node(Arc::new(node)));
+        }
+    }
+
+    /// The children of the syntax tree, ending with any trivia left after
+    /// the last token consumed.
+    fn take_cst(&mut self) -> Vec<GreenElement> {
+        self.checkpoint();
+        self.cst.take().unwrap_or_default()
+    }
+
+    /// Leave the last token consumed out of the syntax tree, adding the
+    /// tokens before it.
+    fn drop_consumed_last(&mut self) {
+        self.add_consumed(|consumed| consumed.len() - 1);
+        if let Some(consumed) = self.tokenizer.consumed() {
+            consumed.pop();
+        }
+    }
+
+    /// Add a token to the syntax tree other than one consumed.
+    fn add_token(&mut self, kind: ToT, text: &str) {
+        if let Some(children) = &mut self.cst {
+            let token = GreenToken::new(kind, text);
+            children.push(GreenElement::Token(Arc::new(token)));
+        }
+    }
+
+    /// Move the first of the tokens consumed to the syntax tree, as many as
+    /// count returns.
+    fn add_consumed(&mut self, count: impl FnOnce(&[Token<'source>]) -> usize) {
+        let (Some(children), Some(consumed)) = (&mut self.cst, self.tokenizer.consumed()) else {
+            return;
+        };
+        let count = count(consumed);
+        children.extend(consumed.drain(..count).map(|token| {
+            let token = GreenToken::new(token.token_type, token.data.lexeme);
+            GreenElement::Token(Arc::new(token))
+        }));
+    }
+}
+
+/// Index of the last token that is not trivia.
+fn last_significant(tokens: &[Token]) -> Option<usize> {
+    tokens
+        .iter()
+        .rposition(|token| !token.token_type.is_trivia())
+}
+
+fn unfinished_interpolation(error: ParseError) -> ParseError {
+    match error {
+        ParseError::Eof { line, col } => ParseError::UnfinishedInterpolation { line, col },
+        error => error,
+    }
+}
+
+fn unary(op_span: Span, op: UnaryOp, right: Expr) -> Expr {
+    let span = op_span.to(right.span);
+    Expr::unary(op, right).with_span(span)
+}
+
+fn binary(left: Expr, op: BinaryOp, right: Expr) -> Expr {
+    let span = left.span.to(right.span);
+    Expr::binary(left, op, right).with_span(span)
 }
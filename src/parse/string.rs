@@ -0,0 +1,227 @@
+//! Lexing of string literals, which may hold expressions to interpolate
+//! in braces, as in `"x = {x}"`.
+//!
+//! A string literal, with its interpolations, is a single token, ending at
+//! its closing `"` or at the end of its line.  The expressions interpolated
+//! are parsed from the source of the token.
+
+use std::ops::Range;
+
+/// Piece of a string literal.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StrSegment {
+    /// Text between interpolations, with escapes replaced.
+    Text(String),
+    /// Byte range within the literal of an interpolated expression,
+    /// between its braces.
+    Interpolation(Range<usize>),
+}
+
+/// Length in bytes of the rest of a string literal after its opening `"`,
+/// through its closing `"`.  Err with the length up to the end of the line
+/// or input if there is no closing `"` before it.
+pub(super) fn string_len(rest: &[u8]) -> Result<usize, usize> {
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            b'"' => return Ok(i + 1),
+            b'\n' => return Err(i),
+            b'\\' if rest.get(i + 1).is_some_and(|&b| b != b'\n') => i += 2,
+            b'{' => {
+                i += 1;
+                i += interpolation_len(&rest[i..]).map_err(|len| i + len)?;
+            }
+            _ => i += 1,
+        }
+    }
+    Err(rest.len())
+}
+
+/// Length in bytes of the rest of an interpolation after its `{`, through
+/// its closing `}`.  Strings within it are skipped whole.
+fn interpolation_len(rest: &[u8]) -> Result<usize, usize> {
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            b'}' => return Ok(i + 1),
+            b'\n' => return Err(i),
+            b'"' => {
+                i += 1;
+                i += string_len(&rest[i..]).map_err(|len| i + len)?;
+            }
+            _ => i += 1,
+        }
+    }
+    Err(rest.len())
+}
+
+/// Split a string literal, quotes included, into its text and
+/// interpolations, merging adjacent text.  Err with the byte range within
+/// the literal of the first invalid escape.
+///
+/// The escapes are `\n`, `\t`, `\r`, `\0`, `\\`, `\"`, `\{`, `\}`, and
+/// `\u{...}` with the hex code of a Unicode scalar value.
+pub fn str_segments(literal: &str) -> Result<Vec<StrSegment>, Range<usize>> {
+    let end = literal.len() - 1;
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut i = 1;
+    while i < end {
+        let c = literal[i..].chars().next().unwrap();
+        match c {
+            '\\' => {
+                let rest = &literal[i..end];
+                let (c, len) = escape(rest).ok_or_else(|| i..i + bad_escape_len(rest))?;
+                text.push(c);
+                i += len;
+            }
+            '{' => {
+                let len = interpolation_len(&literal.as_bytes()[i + 1..]).unwrap();
+                if !text.is_empty() {
+                    segments.push(StrSegment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(StrSegment::Interpolation(i + 1..i + len));
+                i += 1 + len;
+            }
+            c => {
+                text.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    if !text.is_empty() || segments.is_empty() {
+        segments.push(StrSegment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// Text escaped for a string literal, which str_segments reads back as
+/// the text.
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            '\\' | '"' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The character an escape at the start of rest stands for, and the
+/// length of the escape.
+fn escape(rest: &str) -> Option<(char, usize)> {
+    let c = match rest.as_bytes().get(1)? {
+        b'n' => '\n',
+        b't' => '\t',
+        b'r' => '\r',
+        b'0' => '\0',
+        b'\\' => '\\',
+        b'"' => '"',
+        b'{' => '{',
+        b'}' => '}',
+        b'u' => {
+            let digits = rest.strip_prefix("\\u{")?;
+            let len = digits.find('}')?;
+            if !(1..=6).contains(&len) {
+                return None;
+            }
+            let code = u32::from_str_radix(&digits[..len], 16).ok()?;
+            return Some((char::from_u32(code)?, len + 4));
+        }
+        _ => return None,
+    };
+    Some((c, 2))
+}
+
+/// Length of the invalid escape at the start of rest: through the `}` of
+/// a `\u{...}` escape, or else the `\` and the character after it.
+fn bad_escape_len(rest: &str) -> usize {
+    let braced = rest.strip_prefix("\\u{").and_then(|digits| {
+        let len = digits.find(|c: char| !c.is_ascii_alphanumeric())?;
+        digits[len..].starts_with('}').then_some(len + 4)
+    });
+    braced.unwrap_or_else(|| 1 + rest[1..].chars().next().map_or(0, char::len_utf8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use StrSegment::*;
+
+    fn text(s: &str) -> StrSegment {
+        Text(s.to_owned())
+    }
+
+    #[test]
+    fn test_string_len() {
+        assert_eq!(string_len(br#"abc" + 1"#), Ok(4));
+        assert_eq!(string_len(br#"a\"b" c"#), Ok(5));
+        assert_eq!(string_len(br#"a{f("}")}" c"#), Ok(10));
+        assert_eq!(string_len(br#"a{"b"#), Err(4));
+        assert_eq!(string_len(b"ab\ncd\""), Err(2));
+        assert_eq!(string_len(b"ab\\\ncd\""), Err(3));
+        assert_eq!(string_len(b"ab\\"), Err(3));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(
+            str_segments(r#""a\n\t\"\\\{\}\0""#),
+            Ok(vec![text("a\n\t\"\\{}\0")])
+        );
+        assert_eq!(
+            str_segments(r#""\u{e9}\u{1F600}!""#),
+            Ok(vec![text("\u{e9}\u{1f600}!")])
+        );
+        assert_eq!(str_segments(r#""""#), Ok(vec![text("")]));
+        assert_eq!(str_segments(r#""a}""#), Ok(vec![text("a}")]));
+        assert_eq!(str_segments(r#""ab\qc""#), Err(3..5));
+        assert_eq!(str_segments(r#""\é""#), Err(1..4));
+        assert_eq!(str_segments(r#""\u{d800}x""#), Err(1..9));
+        assert_eq!(str_segments(r#""\u{1234567}""#), Err(1..12));
+        assert_eq!(str_segments(r#""\u{12""#), Err(1..3));
+        assert_eq!(str_segments(r#""\u12""#), Err(1..3));
+    }
+
+    #[test]
+    fn test_interpolations() {
+        assert_eq!(
+            str_segments(r#""x = {x}, y = {f("{y}")}""#),
+            Ok(vec![
+                text("x = "),
+                Interpolation(6..7),
+                text(", y = "),
+                Interpolation(15..23),
+            ])
+        );
+        assert_eq!(
+            str_segments(r#""{}\{{1}}""#),
+            Ok(vec![
+                Interpolation(2..2),
+                text("{"),
+                Interpolation(6..7),
+                text("}")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_escape_text() {
+        let original = "a\n\"{b}\\\u{7f}\u{e9}";
+        let escaped = escape_text(original);
+        assert_eq!(escaped, r#"a\n\"\{b\}\\\u{7f}é"#);
+        assert_eq!(
+            str_segments(&format!("\"{escaped}\"")),
+            Ok(vec![text(original)])
+        );
+    }
+}
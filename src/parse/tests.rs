@@ -1,18 +1,31 @@
 use super::parse_expr;
+use super::parse_expr_recovering;
+use super::parse_item;
 use super::Expr;
 use super::ParseError;
+use super::ParseResult;
 use super::TokenType;
 use crate::ast::expr::BinaryOp;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Param;
+use crate::ast::expr::StrPart;
 use crate::ast::expr::UnaryOp;
+use crate::span::Span;
+use crate::ttype::Type;
+
+/// Parse source, ignoring spans so the result compares equal to hand-built Exprs.
+fn parse(source: &str) -> ParseResult<Expr> {
+    parse_expr(source).map(Expr::without_spans)
+}
 
 #[test]
 fn test_parse_errors() {
     assert_eq!(
-        parse_expr("  foo"),
+        parse_expr("  then"),
         Err(ParseError::UnexpectedToken {
             expected: "primary expression".to_owned(),
-            actual: TokenType::Identifier,
-            lexeme: "foo".to_owned(),
+            actual: TokenType::Then,
+            lexeme: "then".to_owned(),
             line: 0,
             col: 2
         })
@@ -27,20 +40,44 @@ fn test_parse_errors() {
     );
 }
 
+#[test]
+fn test_trailing_input() {
+    assert_eq!(
+        parse_expr("true false"),
+        Err(ParseError::UnexpectedToken {
+            expected: "end of input".to_owned(),
+            actual: TokenType::False,
+            lexeme: "false".to_owned(),
+            line: 0,
+            col: 5
+        })
+    );
+    assert_eq!(
+        parse_expr("(true))"),
+        Err(ParseError::UnexpectedToken {
+            expected: "end of input".to_owned(),
+            actual: TokenType::CloseParen,
+            lexeme: ")".to_owned(),
+            line: 0,
+            col: 6
+        })
+    );
+}
+
 #[test]
 fn test_parse_primary() {
-    assert_eq!(parse_expr("true"), Ok(Expr::bool(true)));
-    assert_eq!(parse_expr("false"), Ok(Expr::bool(false)));
+    assert_eq!(parse("true"), Ok(Expr::bool(true)));
+    assert_eq!(parse("false"), Ok(Expr::bool(false)));
 }
 
 #[test]
 fn test_parse_unary() {
     assert_eq!(
-        parse_expr("not true"),
+        parse("not true"),
         Ok(Expr::unary(UnaryOp::Not, Expr::bool(true)))
     );
     assert_eq!(
-        parse_expr("not not false"),
+        parse("not not false"),
         Ok(Expr::unary(
             UnaryOp::Not,
             Expr::unary(UnaryOp::Not, Expr::bool(false))
@@ -51,7 +88,7 @@ fn test_parse_unary() {
 #[test]
 fn test_parse_and_or() {
     assert_eq!(
-        parse_expr(" true and true"),
+        parse(" true and true"),
         Ok(Expr::binary(
             Expr::bool(true),
             BinaryOp::And,
@@ -59,7 +96,7 @@ fn test_parse_and_or() {
         ))
     );
     assert_eq!(
-        parse_expr("not true and false"),
+        parse("not true and false"),
         Ok(Expr::binary(
             Expr::unary(UnaryOp::Not, Expr::bool(true)),
             BinaryOp::And,
@@ -67,7 +104,7 @@ fn test_parse_and_or() {
         ))
     );
     assert_eq!(
-        parse_expr("not not true and not not false"),
+        parse("not not true and not not false"),
         Ok(Expr::binary(
             Expr::unary(UnaryOp::Not, Expr::unary(UnaryOp::Not, Expr::bool(true))),
             BinaryOp::And,
@@ -76,7 +113,7 @@ fn test_parse_and_or() {
     );
 
     assert_eq!(
-        parse_expr("true or false"),
+        parse("true or false"),
         Ok(Expr::binary(
             Expr::bool(true),
             BinaryOp::Or,
@@ -84,19 +121,19 @@ fn test_parse_and_or() {
         ))
     );
     assert_eq!(
-        parse_expr("true or false and not true or false"),
+        parse("true or false and not true or false"),
         Ok(Expr::binary(
-            Expr::bool(true),
-            BinaryOp::Or,
             Expr::binary(
+                Expr::bool(true),
+                BinaryOp::Or,
                 Expr::binary(
                     Expr::bool(false),
                     BinaryOp::And,
                     Expr::unary(UnaryOp::Not, Expr::bool(true))
                 ),
-                BinaryOp::Or,
-                Expr::bool(false),
-            )
+            ),
+            BinaryOp::Or,
+            Expr::bool(false),
         ))
     );
 }
@@ -104,7 +141,7 @@ fn test_parse_and_or() {
 #[test]
 fn test_equals() {
     assert_eq!(
-        parse_expr("true == false"),
+        parse("true == false"),
         Ok(Expr::binary(
             Expr::bool(true),
             BinaryOp::Equal,
@@ -112,7 +149,7 @@ fn test_equals() {
         ))
     );
     assert_eq!(
-        parse_expr("true != false"),
+        parse("true != false"),
         Ok(Expr::binary(
             Expr::bool(true),
             BinaryOp::NotEqual,
@@ -121,7 +158,7 @@ fn test_equals() {
     );
 
     assert_eq!(
-        parse_expr("true != false or true == true"),
+        parse("true != false or true == true"),
         Ok(Expr::binary(
             Expr::binary(Expr::bool(true), BinaryOp::NotEqual, Expr::bool(false),),
             BinaryOp::Or,
@@ -143,50 +180,63 @@ fn test_parse_block() {
         })
     );
 
-    assert_eq!(
-        parse_expr("(true)"),
-        Ok(Expr::Block(vec![Expr::bool(true)]))
-    );
+    assert_eq!(parse("(true)"), Ok(Expr::bool(true)));
 
     assert_eq!(
-        parse_expr("(true or false)"),
-        Ok(Expr::Block(vec![Expr::binary(
+        parse("(true or false)"),
+        Ok(Expr::binary(
             Expr::bool(true),
             BinaryOp::Or,
             Expr::bool(false),
-        ),]))
+        ))
     );
 
     assert_eq!(
-        parse_expr("false and (true or false)"),
+        parse("false and (true or false)"),
         Ok(Expr::binary(
             Expr::bool(false),
             BinaryOp::And,
-            Expr::Block(vec![Expr::binary(
-                Expr::bool(true),
-                BinaryOp::Or,
-                Expr::bool(false)
-            ),])
+            Expr::binary(Expr::bool(true), BinaryOp::Or, Expr::bool(false))
         ))
     );
 }
 
+/// Parentheses group a single expression rather than making a Block of it.
+#[test]
+fn test_grouping() {
+    let expr = parse_expr("((x))").unwrap();
+    assert_eq!(expr.kind, ExprKind::Var("x".to_owned()));
+    assert_eq!(expr.span, Span::new(0, 5, 0, 0));
+
+    let expr = parse_expr("if c (x)").unwrap();
+    let ExprKind::If { then_expr, .. } = expr.kind else {
+        panic!("expected an if, found {expr:?}");
+    };
+    assert_eq!(then_expr.kind, ExprKind::Var("x".to_owned()));
+    assert_eq!(then_expr.span, Span::new(5, 8, 0, 5));
+
+    assert_eq!(
+        parse("(let x = 1)"),
+        Ok(Expr::from(ExprKind::Block {
+            contents: vec![Expr::let_("x", Expr::int(1))],
+            trailing_semicolon: false,
+        }))
+    );
+    assert_eq!(parse("(x;)"), Ok(Expr::unit_block(vec![Expr::var("x")])));
+}
+
 #[test]
 fn test_if_exprs() {
     assert_eq!(
-        parse_expr("if true (false)"),
-        Ok(Expr::ifthen(
-            Expr::bool(true),
-            Expr::Block(vec![Expr::bool(false)]),
-            None,
-        ))
+        parse("if true (false)"),
+        Ok(Expr::ifthen(Expr::bool(true), Expr::bool(false), None,))
     );
 
     assert_eq!(
-        parse_expr("if true (false) else true or false"),
+        parse("if true (false) else true or false"),
         Ok(Expr::ifthen(
             Expr::bool(true),
-            Expr::Block(vec![Expr::bool(false)]),
+            Expr::bool(false),
             Some(Expr::binary(
                 Expr::bool(true),
                 BinaryOp::Or,
@@ -196,13 +246,13 @@ fn test_if_exprs() {
     );
 
     assert_eq!(
-        parse_expr("if true (false) else if true (true) else false"),
+        parse("if true (false) else if true (true) else false"),
         Ok(Expr::ifthen(
             Expr::bool(true),
-            Expr::Block(vec![Expr::bool(false)]),
+            Expr::bool(false),
             Some(Expr::ifthen(
                 Expr::bool(true),
-                Expr::Block(vec![Expr::bool(true)]),
+                Expr::bool(true),
                 Some(Expr::bool(false)),
             ))
         ))
@@ -211,15 +261,12 @@ fn test_if_exprs() {
 
 #[test]
 fn test_int_exprs() {
-    assert_eq!(parse_expr("12"), Ok(Expr::int(12)));
+    assert_eq!(parse("12"), Ok(Expr::int(12)));
 
-    assert_eq!(
-        parse_expr("-12"),
-        Ok(Expr::unary(UnaryOp::Neg, Expr::int(12)))
-    );
+    assert_eq!(parse("-12"), Ok(Expr::unary(UnaryOp::Neg, Expr::int(12))));
 
     assert_eq!(
-        parse_expr("1 + -2"),
+        parse("1 + -2"),
         Ok(Expr::binary(
             Expr::int(1),
             BinaryOp::Add,
@@ -228,7 +275,7 @@ fn test_int_exprs() {
     );
 
     assert_eq!(
-        parse_expr("1 * 2 + 3"),
+        parse("1 * 2 + 3"),
         Ok(Expr::binary(
             Expr::binary(Expr::int(1), BinaryOp::Mult, Expr::int(2)),
             BinaryOp::Add,
@@ -237,7 +284,7 @@ fn test_int_exprs() {
     );
 
     assert_eq!(
-        parse_expr("1 * -2"),
+        parse("1 * -2"),
         Ok(Expr::binary(
             Expr::int(1),
             BinaryOp::Mult,
@@ -246,7 +293,7 @@ fn test_int_exprs() {
     );
 
     assert_eq!(
-        parse_expr("-1 * 2"),
+        parse("-1 * 2"),
         Ok(Expr::binary(
             Expr::unary(UnaryOp::Neg, Expr::int(1)),
             BinaryOp::Mult,
@@ -258,12 +305,12 @@ fn test_int_exprs() {
 #[test]
 fn test_comparisons() {
     assert_eq!(
-        parse_expr("1 <= 2"),
+        parse("1 <= 2"),
         Ok(Expr::binary(Expr::int(1), BinaryOp::LessEq, Expr::int(2)))
     );
 
     assert_eq!(
-        parse_expr("1 <= 2 != 3 > 4"),
+        parse("1 <= 2 != 3 > 4"),
         Ok(Expr::binary(
             Expr::binary(Expr::int(1), BinaryOp::LessEq, Expr::int(2)),
             BinaryOp::NotEqual,
@@ -272,7 +319,7 @@ fn test_comparisons() {
     );
 
     assert_eq!(
-        parse_expr("1 + 2 >= 3 * 4"),
+        parse("1 + 2 >= 3 * 4"),
         Ok(Expr::binary(
             Expr::binary(Expr::int(1), BinaryOp::Add, Expr::int(2)),
             BinaryOp::GreatEq,
@@ -280,3 +327,873 @@ fn test_comparisons() {
         ))
     );
 }
+
+#[test]
+fn test_spans() {
+    let expr = parse_expr("1 + (2 *\n  3)").unwrap();
+    assert_eq!(expr.span, Span::new(0, 13, 0, 0));
+    let ExprKind::Binary { left, right, .. } = expr.kind else {
+        panic!("Expected binary expression");
+    };
+    assert_eq!(left.span, Span::new(0, 1, 0, 0));
+    // A parenthesized expression's span includes the parens.
+    assert_eq!(right.span, Span::new(4, 13, 0, 4));
+    let ExprKind::Binary { left, .. } = right.kind else {
+        panic!("Expected binary expression");
+    };
+    assert_eq!(left.span, Span::new(5, 6, 0, 5));
+
+    let expr = parse_expr("if true (1) else -2").unwrap();
+    assert_eq!(expr.span, Span::new(0, 19, 0, 0));
+    let expr = parse_expr("if true (1)").unwrap();
+    assert_eq!(expr.span, Span::new(0, 11, 0, 0));
+    let expr = parse_expr("not  false").unwrap();
+    assert_eq!(expr.span, Span::new(0, 10, 0, 0));
+}
+
+#[test]
+fn test_left_associativity() {
+    assert_eq!(
+        parse("10 - 3 - 2"),
+        Ok(Expr::binary(
+            Expr::binary(Expr::int(10), BinaryOp::Sub, Expr::int(3)),
+            BinaryOp::Sub,
+            Expr::int(2)
+        ))
+    );
+
+    assert_eq!(
+        parse("8 / 4 / 2"),
+        Ok(Expr::binary(
+            Expr::binary(Expr::int(8), BinaryOp::Div, Expr::int(4)),
+            BinaryOp::Div,
+            Expr::int(2)
+        ))
+    );
+
+    assert_eq!(
+        parse("1 - 2 + 3 * 4 / 5"),
+        Ok(Expr::binary(
+            Expr::binary(Expr::int(1), BinaryOp::Sub, Expr::int(2)),
+            BinaryOp::Add,
+            Expr::binary(
+                Expr::binary(Expr::int(3), BinaryOp::Mult, Expr::int(4)),
+                BinaryOp::Div,
+                Expr::int(5)
+            )
+        ))
+    );
+
+    assert_eq!(
+        parse("true and false and true"),
+        Ok(Expr::binary(
+            Expr::binary(Expr::bool(true), BinaryOp::And, Expr::bool(false)),
+            BinaryOp::And,
+            Expr::bool(true)
+        ))
+    );
+
+    assert_eq!(
+        parse("true or false or true"),
+        Ok(Expr::binary(
+            Expr::binary(Expr::bool(true), BinaryOp::Or, Expr::bool(false)),
+            BinaryOp::Or,
+            Expr::bool(true)
+        ))
+    );
+}
+
+#[test]
+fn test_chained_comparisons() {
+    assert_eq!(
+        parse_expr("1 < 2 < 3"),
+        Err(ParseError::ChainedComparison {
+            lexeme: "<".to_owned(),
+            line: 0,
+            col: 6,
+        })
+    );
+    assert_eq!(
+        parse_expr("1 <= 2 > 3"),
+        Err(ParseError::ChainedComparison {
+            lexeme: ">".to_owned(),
+            line: 0,
+            col: 7,
+        })
+    );
+    assert_eq!(
+        parse_expr("true == false != true"),
+        Err(ParseError::ChainedComparison {
+            lexeme: "!=".to_owned(),
+            line: 0,
+            col: 14,
+        })
+    );
+
+    // Comparisons of different precedence, or grouped in a block, are fine.
+    assert_eq!(
+        parse("1 < 2 == true"),
+        Ok(Expr::binary(
+            Expr::binary(Expr::int(1), BinaryOp::Less, Expr::int(2)),
+            BinaryOp::Equal,
+            Expr::bool(true)
+        ))
+    );
+    assert_eq!(
+        parse("(1 < 2) == (3 < 4)"),
+        Ok(Expr::binary(
+            Expr::binary(Expr::int(1), BinaryOp::Less, Expr::int(2)),
+            BinaryOp::Equal,
+            Expr::binary(Expr::int(3), BinaryOp::Less, Expr::int(4))
+        ))
+    );
+}
+
+/// Parse source with error recovery, ignoring spans.
+fn parse_recovering(source: &str) -> (Expr, Vec<ParseError>) {
+    let (expr, errors) = parse_expr_recovering(source);
+    (expr.without_spans(), errors)
+}
+
+fn error_expr() -> Expr {
+    ExprKind::Error.into()
+}
+
+#[test]
+fn test_recovery_without_errors() {
+    for source in ["1 + 2 * 3", "if true (false) else true or false", "(1 < 2)"] {
+        assert_eq!(
+            parse_expr_recovering(source),
+            (parse_expr(source).unwrap(), vec![])
+        );
+    }
+}
+
+#[test]
+fn test_recover_multiple_errors() {
+    assert_eq!(
+        parse_recovering("(1 + ) and (then bar) or true"),
+        (
+            Expr::binary(
+                Expr::binary(
+                    Expr::binary(Expr::int(1), BinaryOp::Add, error_expr()),
+                    BinaryOp::And,
+                    error_expr(),
+                ),
+                BinaryOp::Or,
+                Expr::bool(true),
+            ),
+            vec![
+                ParseError::UnexpectedToken {
+                    expected: "primary expression".to_owned(),
+                    actual: TokenType::CloseParen,
+                    lexeme: ")".to_owned(),
+                    line: 0,
+                    col: 5,
+                },
+                ParseError::UnexpectedToken {
+                    expected: "primary expression".to_owned(),
+                    actual: TokenType::Then,
+                    lexeme: "then".to_owned(),
+                    line: 0,
+                    col: 12,
+                },
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_recover_error_spans() {
+    // The Error node covers the bad token and those skipped after it.
+    let (expr, _) = parse_expr_recovering("1 + then bar");
+    let ExprKind::Binary { right, .. } = expr.kind else {
+        panic!("Expected binary expression");
+    };
+    assert_eq!(right.span, Span::new(4, 12, 0, 4));
+
+    // A missing operand is an empty Error node where it should be.
+    let (expr, _) = parse_expr_recovering("(1 + )");
+    let ExprKind::Binary { right, .. } = &expr.kind else {
+        panic!("Expected binary expression");
+    };
+    assert_eq!(right.span, Span::new(5, 5, 0, 5));
+}
+
+#[test]
+fn test_recover_synchronization() {
+    // Synchronize at else.
+    assert_eq!(
+        parse_recovering("if true 1 else 2"),
+        (
+            Expr::ifthen(Expr::bool(true), error_expr(), Some(Expr::int(2))),
+            vec![ParseError::UnexpectedToken {
+                expected: "OpenParen".to_owned(),
+                actual: TokenType::Int,
+                lexeme: "1".to_owned(),
+                line: 0,
+                col: 8,
+            }]
+        )
+    );
+
+    // Synchronize at a newline.
+    assert_eq!(
+        parse_recovering("(1 + then bar\n 2)"),
+        (
+            Expr::binary(Expr::int(1), BinaryOp::Add, error_expr()),
+            vec![
+                ParseError::UnexpectedToken {
+                    expected: "primary expression".to_owned(),
+                    actual: TokenType::Then,
+                    lexeme: "then".to_owned(),
+                    line: 0,
+                    col: 5,
+                },
+                ParseError::UnexpectedToken {
+                    expected: "CloseParen".to_owned(),
+                    actual: TokenType::Int,
+                    lexeme: "2".to_owned(),
+                    line: 1,
+                    col: 1,
+                },
+            ]
+        )
+    );
+
+    // Unterminated input.
+    assert_eq!(
+        parse_recovering("(1 +"),
+        (
+            Expr::binary(Expr::int(1), BinaryOp::Add, error_expr()),
+            vec![ParseError::Eof { line: 0, col: 4 }]
+        )
+    );
+}
+
+#[test]
+fn test_recover_chained_comparison() {
+    assert_eq!(
+        parse_recovering("1 < 2 < 3"),
+        (
+            Expr::binary(
+                Expr::binary(Expr::int(1), BinaryOp::Less, Expr::int(2)),
+                BinaryOp::Less,
+                Expr::int(3)
+            ),
+            vec![ParseError::ChainedComparison {
+                lexeme: "<".to_owned(),
+                line: 0,
+                col: 6,
+            }]
+        )
+    );
+}
+
+#[test]
+fn test_let_bindings() {
+    assert_eq!(parse("x"), Ok(Expr::var("x")));
+    assert_eq!(
+        parse("(let x = 1; let y2 = x + 1; x * y2)"),
+        Ok(Expr::block(vec![
+            Expr::let_("x", Expr::int(1)),
+            Expr::let_(
+                "y2",
+                Expr::binary(Expr::var("x"), BinaryOp::Add, Expr::int(1))
+            ),
+            Expr::binary(Expr::var("x"), BinaryOp::Mult, Expr::var("y2")),
+        ]))
+    );
+
+    let expr = parse_expr("(let x = 1; x)").unwrap();
+    let ExprKind::Block { contents, .. } = expr.kind else {
+        panic!("Expected block");
+    };
+    assert_eq!(contents[0].span, Span::new(1, 10, 0, 1));
+    let ExprKind::Let { name, .. } = &contents[0].kind else {
+        panic!("Expected let");
+    };
+    assert_eq!(name.span, Span::new(5, 6, 0, 5));
+
+    assert_eq!(
+        parse_expr("(let 1 = 2; 3)"),
+        Err(ParseError::UnexpectedToken {
+            expected: "identifier".to_owned(),
+            actual: TokenType::Int,
+            lexeme: "1".to_owned(),
+            line: 0,
+            col: 5,
+        })
+    );
+    assert_eq!(
+        parse_expr("(let x == 2; 3)"),
+        Err(ParseError::UnexpectedToken {
+            expected: "Equal".to_owned(),
+            actual: TokenType::EqualEqual,
+            lexeme: "==".to_owned(),
+            line: 0,
+            col: 7,
+        })
+    );
+    // A let is not an expression on its own.
+    assert_eq!(
+        parse_expr("let x = 1"),
+        Err(ParseError::UnexpectedToken {
+            expected: "primary expression".to_owned(),
+            actual: TokenType::Let,
+            lexeme: "let".to_owned(),
+            line: 0,
+            col: 0,
+        })
+    );
+}
+
+#[test]
+fn test_parse_item() {
+    assert_eq!(
+        parse_item("let x = 1 + 2").map(|e| e.without_spans()),
+        Ok(Expr::let_(
+            "x",
+            Expr::binary(Expr::int(1), BinaryOp::Add, Expr::int(2))
+        ))
+    );
+    assert_eq!(
+        parse_item("x * 2").map(|e| e.without_spans()),
+        Ok(Expr::binary(Expr::var("x"), BinaryOp::Mult, Expr::int(2)))
+    );
+    assert_eq!(
+        parse_item("let x = 1; x"),
+        Err(ParseError::StraySemicolon { line: 0, col: 9 })
+    );
+    assert_eq!(
+        parse_item("let x ="),
+        Err(ParseError::Eof { line: 0, col: 7 })
+    );
+}
+
+#[test]
+fn test_semicolons() {
+    assert_eq!(
+        parse("(1; true)"),
+        Ok(Expr::block(vec![Expr::int(1), Expr::bool(true)]))
+    );
+    assert_eq!(
+        parse("(1; true;)"),
+        Ok(Expr::unit_block(vec![Expr::int(1), Expr::bool(true)]))
+    );
+    assert_eq!(
+        parse("(let x = 1)"),
+        Ok(Expr::block(vec![Expr::let_("x", Expr::int(1))]))
+    );
+    assert_eq!(
+        parse("(let x = 1;)"),
+        Ok(Expr::unit_block(vec![Expr::let_("x", Expr::int(1))]))
+    );
+    assert_eq!(
+        parse("if true (1;) else (2)"),
+        Ok(Expr::ifthen(
+            Expr::bool(true),
+            Expr::unit_block(vec![Expr::int(1)]),
+            Some(Expr::int(2))
+        ))
+    );
+
+    let expr = parse_expr("(1;\n 2;)").unwrap();
+    assert_eq!(expr.span, Span::new(0, 8, 0, 0));
+}
+
+#[test]
+fn test_stray_semicolons() {
+    assert_eq!(
+        parse_expr("(; 1)"),
+        Err(ParseError::StraySemicolon { line: 0, col: 1 })
+    );
+    assert_eq!(
+        parse_expr("(1;; 2)"),
+        Err(ParseError::StraySemicolon { line: 0, col: 3 })
+    );
+    assert_eq!(
+        parse_expr("(1; 2;;)"),
+        Err(ParseError::StraySemicolon { line: 0, col: 6 })
+    );
+    assert_eq!(
+        parse_expr("1; 2"),
+        Err(ParseError::StraySemicolon { line: 0, col: 1 })
+    );
+    assert_eq!(
+        parse_expr("(1 2)"),
+        Err(ParseError::UnexpectedToken {
+            expected: "CloseParen".to_owned(),
+            actual: TokenType::Int,
+            lexeme: "2".to_owned(),
+            line: 0,
+            col: 3,
+        })
+    );
+
+    // Recovery skips stray semicolons, and synchronizes at them.
+    assert_eq!(
+        parse_recovering("(;1;; 2 + ; 3)"),
+        (
+            Expr::block(vec![
+                Expr::int(1),
+                Expr::binary(Expr::int(2), BinaryOp::Add, error_expr()),
+                Expr::int(3),
+            ]),
+            vec![
+                ParseError::StraySemicolon { line: 0, col: 1 },
+                ParseError::StraySemicolon { line: 0, col: 4 },
+                ParseError::UnexpectedToken {
+                    expected: "primary expression".to_owned(),
+                    actual: TokenType::Semicolon,
+                    lexeme: ";".to_owned(),
+                    line: 0,
+                    col: 10,
+                },
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_functions() {
+    let add = Expr::binary(Expr::var("x"), BinaryOp::Add, Expr::var("y"));
+    assert_eq!(
+        parse("fn (x: int, y: int) -> int (x + y)"),
+        Ok(Expr::lambda(
+            vec![Param::new("x", Type::Int), Param::new("y", Type::Int)],
+            Type::Int,
+            add
+        ))
+    );
+    assert_eq!(
+        parse("fn() -> unit (1;)"),
+        Ok(Expr::lambda(
+            vec![],
+            Type::Unit,
+            Expr::unit_block(vec![Expr::int(1)])
+        ))
+    );
+    assert_eq!(
+        parse("fn twice(f: fn(int) -> int, b: bool) -> fn() -> bool (f)"),
+        Ok(Expr::named_fn(
+            "twice",
+            vec![
+                Param::new("f", Type::function(vec![Type::Int], Type::Int)),
+                Param::new("b", Type::Bool),
+            ],
+            Type::function(vec![], Type::Bool),
+            Expr::var("f")
+        ))
+    );
+
+    let expr = parse_expr("fn f(x: int) -> int (x)").unwrap();
+    assert_eq!(expr.span, Span::new(0, 23, 0, 0));
+    let ExprKind::Fn(function) = expr.kind else {
+        panic!("expected a function, found {:?}", expr.kind);
+    };
+    assert_eq!(function.name.unwrap().span, Span::new(3, 4, 0, 3));
+    assert_eq!(function.params[0].name.span, Span::new(5, 6, 0, 5));
+}
+
+#[test]
+fn test_calls() {
+    assert_eq!(parse("f()"), Ok(Expr::call(Expr::var("f"), vec![])));
+    assert_eq!(
+        parse("f(1, x + 2)"),
+        Ok(Expr::call(
+            Expr::var("f"),
+            vec![
+                Expr::int(1),
+                Expr::binary(Expr::var("x"), BinaryOp::Add, Expr::int(2))
+            ]
+        ))
+    );
+    assert_eq!(
+        parse("f(1)(2)"),
+        Ok(Expr::call(
+            Expr::call(Expr::var("f"), vec![Expr::int(1)]),
+            vec![Expr::int(2)]
+        ))
+    );
+    assert_eq!(
+        parse("-f(1) * 2"),
+        Ok(Expr::binary(
+            Expr::unary(UnaryOp::Neg, Expr::call(Expr::var("f"), vec![Expr::int(1)])),
+            BinaryOp::Mult,
+            Expr::int(2)
+        ))
+    );
+    assert_eq!(
+        parse("(fn (x: int) -> int (x))(1)"),
+        Ok(Expr::call(
+            Expr::lambda(vec![Param::new("x", Type::Int)], Type::Int, Expr::var("x")),
+            vec![Expr::int(1)]
+        ))
+    );
+    // An argument list must immediately follow the callee.
+    assert_eq!(
+        parse("if f (1)"),
+        Ok(Expr::ifthen(Expr::var("f"), Expr::int(1), None))
+    );
+    assert_eq!(
+        parse("if f(x) (1)"),
+        Ok(Expr::ifthen(
+            Expr::call(Expr::var("f"), vec![Expr::var("x")]),
+            Expr::int(1),
+            None
+        ))
+    );
+
+    assert_eq!(parse_expr("f(1, 2)").unwrap().span, Span::new(0, 7, 0, 0));
+}
+
+#[test]
+fn test_function_errors() {
+    assert_eq!(
+        parse_expr("fn (x) -> int (x)"),
+        Err(ParseError::UnexpectedToken {
+            expected: "Colon".to_owned(),
+            actual: TokenType::CloseParen,
+            lexeme: ")".to_owned(),
+            line: 0,
+            col: 5,
+        })
+    );
+    assert_eq!(
+        parse_expr("fn (x: y) -> int (x)"),
+        Err(ParseError::UnexpectedToken {
+            expected: "type".to_owned(),
+            actual: TokenType::Identifier,
+            lexeme: "y".to_owned(),
+            line: 0,
+            col: 7,
+        })
+    );
+    assert_eq!(
+        parse_expr("fn (1: int) -> int (1)"),
+        Err(ParseError::UnexpectedToken {
+            expected: "parameter name".to_owned(),
+            actual: TokenType::Int,
+            lexeme: "1".to_owned(),
+            line: 0,
+            col: 4,
+        })
+    );
+    assert_eq!(
+        parse_expr("fn (x: int) (x)"),
+        Err(ParseError::UnexpectedToken {
+            expected: "Arrow".to_owned(),
+            actual: TokenType::OpenParen,
+            lexeme: "(".to_owned(),
+            line: 0,
+            col: 12,
+        })
+    );
+    assert_eq!(
+        parse_expr("f(1, )"),
+        Err(ParseError::UnexpectedToken {
+            expected: "primary expression".to_owned(),
+            actual: TokenType::CloseParen,
+            lexeme: ")".to_owned(),
+            line: 0,
+            col: 5,
+        })
+    );
+
+    // Recovery synchronizes at the commas between arguments.
+    let (expr, errors) = parse_recovering("f(1 +, 2)");
+    assert_eq!(
+        expr,
+        Expr::call(
+            Expr::var("f"),
+            vec![
+                Expr::binary(Expr::int(1), BinaryOp::Add, error_expr()),
+                Expr::int(2)
+            ]
+        )
+    );
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn test_comments() {
+    assert_eq!(parse("// sum\n1 + /* two */ 2 // done"), parse("1 + 2"));
+    assert_eq!(
+        parse("(let x = 1; /* /* nested */ x; */\n x)"),
+        parse("(let x = 1; x)")
+    );
+    assert_eq!(parse("1 //* not a block comment"), parse("1"));
+    assert_eq!(parse("4 /**/ / 2"), parse("4 / 2"));
+
+    // Comments spanning lines move the positions after them.
+    let expr = parse_expr("/* a\n b */ x").unwrap();
+    assert_eq!(expr.span, Span::new(11, 12, 1, 6));
+}
+
+#[test]
+fn test_unterminated_comments() {
+    assert_eq!(
+        parse_expr("1 +\n  /* two /* */ 2"),
+        Err(ParseError::UnterminatedComment { line: 1, col: 2 })
+    );
+    assert_eq!(
+        parse_expr("1 /* two"),
+        Err(ParseError::UnterminatedComment { line: 0, col: 2 })
+    );
+    assert_eq!(
+        parse_recovering("(1; 2 /* three"),
+        (
+            Expr::block(vec![Expr::int(1), Expr::int(2)]),
+            vec![ParseError::UnterminatedComment { line: 0, col: 6 }]
+        )
+    );
+}
+
+#[test]
+fn test_strings() {
+    assert_eq!(parse("\"\""), Ok(Expr::str("")));
+    assert_eq!(parse("\"a\\tb\\n\\\"c\\\"\""), Ok(Expr::str("a\tb\n\"c\"")));
+    assert_eq!(parse("\"caf\\u{e9} \\{}\""), Ok(Expr::str("caf\u{e9} {}")));
+    assert_eq!(
+        parse("\"a\" + \"b\" < \"c\""),
+        Ok(Expr::binary(
+            Expr::binary(Expr::str("a"), BinaryOp::Add, Expr::str("b")),
+            BinaryOp::Less,
+            Expr::str("c")
+        ))
+    );
+    assert_eq!(
+        parse("len \"ab\" + 1"),
+        Ok(Expr::binary(
+            Expr::unary(UnaryOp::Len, Expr::str("ab")),
+            BinaryOp::Add,
+            Expr::int(1)
+        ))
+    );
+    assert_eq!(
+        parse("fn (s: str) -> int (len s)"),
+        parse("fn (s: str) -> int (len(s))")
+    );
+}
+
+#[test]
+fn test_interpolation() {
+    assert_eq!(
+        parse("\"x = {x}, y = { f(y, \"{z}\") }!\""),
+        Ok(Expr::interpolate(vec![
+            StrPart::Text("x = ".to_owned()),
+            StrPart::Expr(Expr::var("x")),
+            StrPart::Text(", y = ".to_owned()),
+            StrPart::Expr(Expr::call(
+                Expr::var("f"),
+                vec![
+                    Expr::var("y"),
+                    Expr::interpolate(vec![StrPart::Expr(Expr::var("z"))])
+                ]
+            )),
+            StrPart::Text("!".to_owned()),
+        ]))
+    );
+
+    // Interpolated expressions have positions in the whole source.
+    let expr = parse_expr("\n  \"a{ b + 1 }\"").unwrap();
+    assert_eq!(expr.span, Span::new(3, 15, 1, 2));
+    let ExprKind::Interpolate(parts) = expr.kind else {
+        panic!("expected an interpolation, found {expr:?}");
+    };
+    let StrPart::Expr(inner) = &parts[1] else {
+        panic!("expected an expression, found {:?}", parts[1]);
+    };
+    assert_eq!(inner.span, Span::new(7, 12, 1, 6));
+}
+
+#[test]
+fn test_string_errors() {
+    assert_eq!(
+        parse_expr("1 +\n  \"two"),
+        Err(ParseError::UnterminatedString { line: 1, col: 2 })
+    );
+    assert_eq!(
+        parse_expr("\"a{\"b\"\n}\""),
+        Err(ParseError::UnterminatedString { line: 0, col: 0 })
+    );
+    assert_eq!(
+        parse_expr(" \"ab\\qc\""),
+        Err(ParseError::BadEscape {
+            escape: "\\q".to_owned(),
+            line: 0,
+            col: 4
+        })
+    );
+    assert_eq!(
+        parse_expr("\"\\u{110000}\""),
+        Err(ParseError::BadEscape {
+            escape: "\\u{110000}".to_owned(),
+            line: 0,
+            col: 1
+        })
+    );
+    assert_eq!(
+        parse_expr("\"x = {x +}\""),
+        Err(ParseError::UnfinishedInterpolation { line: 0, col: 9 })
+    );
+    assert_eq!(
+        parse_expr("\"{}\""),
+        Err(ParseError::UnfinishedInterpolation { line: 0, col: 2 })
+    );
+    assert_eq!(
+        parse_expr("\"{x y}\""),
+        Err(ParseError::UnexpectedToken {
+            expected: "end of input".to_owned(),
+            actual: TokenType::Identifier,
+            lexeme: "y".to_owned(),
+            line: 0,
+            col: 4
+        })
+    );
+    assert_eq!(
+        parse_recovering("(\"a\\z\"; \"{1 +}\"; \"b\n)"),
+        (
+            Expr::block(vec![
+                error_expr(),
+                Expr::interpolate(vec![StrPart::Expr(Expr::binary(
+                    Expr::int(1),
+                    BinaryOp::Add,
+                    error_expr()
+                ))]),
+                error_expr(),
+            ]),
+            vec![
+                ParseError::BadEscape {
+                    escape: "\\z".to_owned(),
+                    line: 0,
+                    col: 3
+                },
+                ParseError::UnfinishedInterpolation { line: 0, col: 13 },
+                ParseError::UnterminatedString { line: 0, col: 17 },
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_numbers() {
+    assert_eq!(parse("1_000_000"), Ok(Expr::int(1_000_000)));
+    assert_eq!(parse("0xff + 0o17"), parse("255 + 15"));
+    assert_eq!(parse("0b1010_1010"), Ok(Expr::int(170)));
+    assert_eq!(parse("9223372036854775807"), Ok(Expr::int(i64::MAX)));
+    assert_eq!(parse("1.5"), Ok(Expr::float(1.5)));
+    assert_eq!(parse("2.5e3 / 1e-3"), parse("2500.0 / 0.001"));
+    assert_eq!(
+        parse("-1.5 * 2"),
+        Ok(Expr::binary(
+            Expr::unary(UnaryOp::Neg, Expr::float(1.5)),
+            BinaryOp::Mult,
+            Expr::int(2)
+        ))
+    );
+    assert_eq!(
+        parse("fn (x: float) -> float (x)"),
+        Ok(Expr::lambda(
+            vec![Param::new("x", Type::Float)],
+            Type::Float,
+            Expr::var("x")
+        ))
+    );
+}
+
+#[test]
+fn test_number_errors() {
+    assert_eq!(
+        parse_expr("1 + 9223372036854775808"),
+        Err(ParseError::IntegerOverflow {
+            lexeme: "9223372036854775808".to_owned(),
+            line: 0,
+            col: 4
+        })
+    );
+    // The `-` is a negation, so the literal itself is out of range.
+    assert_eq!(
+        parse_expr("-9223372036854775808"),
+        Err(ParseError::IntegerOverflow {
+            lexeme: "9223372036854775808".to_owned(),
+            line: 0,
+            col: 1
+        })
+    );
+    assert_eq!(
+        parse_expr("0x1_0000_0000_0000_0000"),
+        Err(ParseError::IntegerOverflow {
+            lexeme: "0x1_0000_0000_0000_0000".to_owned(),
+            line: 0,
+            col: 0
+        })
+    );
+    assert_eq!(
+        parse_expr("1e309"),
+        Err(ParseError::FloatOverflow {
+            lexeme: "1e309".to_owned(),
+            line: 0,
+            col: 0
+        })
+    );
+    assert_eq!(
+        parse_expr("0x_"),
+        Err(ParseError::UnknownToken {
+            lexeme: "0x_".to_owned(),
+            line: 0,
+            col: 0
+        })
+    );
+    assert_eq!(
+        parse_recovering("(99999999999999999999 + 1; 1e999)"),
+        (
+            Expr::block(vec![
+                Expr::binary(error_expr(), BinaryOp::Add, Expr::int(1)),
+                error_expr(),
+            ]),
+            vec![
+                ParseError::IntegerOverflow {
+                    lexeme: "99999999999999999999".to_owned(),
+                    line: 0,
+                    col: 1
+                },
+                ParseError::FloatOverflow {
+                    lexeme: "1e999".to_owned(),
+                    line: 0,
+                    col: 27
+                },
+            ]
+        )
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trips() {
+    use super::Tokenizer;
+    use crate::test_util::CORPUS;
+    use logos::Logos;
+
+    assert_eq!(
+        serde_json::to_value(parse_expr("1 <").unwrap_err()).unwrap(),
+        serde_json::json!({"eof": {"line": 0, "col": 3}})
+    );
+    let mut errors = 0;
+    for source in CORPUS {
+        for token in Tokenizer::new(TokenType::lexer(source)) {
+            let json = serde_json::to_string(&token.token_type).unwrap();
+            assert_eq!(
+                serde_json::from_str::<TokenType>(&json).unwrap(),
+                token.token_type
+            );
+        }
+        if let Err(error) = parse_expr(source) {
+            let json = serde_json::to_string(&error).unwrap();
+            assert_eq!(serde_json::from_str::<ParseError>(&json).unwrap(), error);
+            errors += 1;
+        }
+    }
+    assert!(errors > 20);
+}
@@ -1,15 +1,45 @@
+use logos::Lexer;
 use logos::Logos;
 
+use super::string::string_len;
+
+/// Why the lexer could not produce a token.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum LexError {
+    #[default]
+    UnknownToken,
+    UnterminatedComment,
+    UnterminatedString,
+}
+
 #[derive(Logos, Copy, Clone, Debug, PartialEq)]
-#[logos()]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[logos(error = LexError)]
 pub enum TokenType {
     #[token("\n")]
     Newline,
     #[regex(r"[ \t\r\f]+")]
     Whitespace,
+    #[regex(r"//[^\n]*")]
+    LineComment,
+    /// `/* */` comment, which may contain nested block comments.
+    #[token("/*", block_comment)]
+    BlockComment,
 
     #[token("bool")]
     Bool,
+    #[token("int")]
+    IntType,
+    #[token("float")]
+    FloatType,
+    #[token("unit")]
+    UnitType,
+    #[token("str")]
+    StrType,
     #[token("true")]
     True,
     #[token("false")]
@@ -20,6 +50,8 @@ pub enum TokenType {
     And,
     #[token("or")]
     Or,
+    #[token("len")]
+    Len,
 
     #[token("+")]
     Plus,
@@ -29,8 +61,21 @@ pub enum TokenType {
     Star,
     #[token("/")]
     Slash,
-    #[regex(r"[0-9]+")]
+    /// Integer literal: decimal, or hex, octal or binary with a `0x`, `0o`
+    /// or `0b` prefix.  Digits may be separated by `_`.
+    #[regex(r"[0-9][0-9_]*")]
+    #[regex(r"0x[0-9a-fA-F_]+", radix_digits)]
+    #[regex(r"0o[0-7_]+", radix_digits)]
+    #[regex(r"0b[01_]+", radix_digits)]
     Int,
+    /// Float literal, with a fraction, an exponent or both, as in `1.5`,
+    /// `1e-3` or `1.5e3`.
+    #[regex(r"[0-9][0-9_]*\.[0-9][0-9_]*([eE][+-]?[0-9_]+)?", exponent_digits)]
+    #[regex(r"[0-9][0-9_]*[eE][+-]?[0-9_]+", exponent_digits)]
+    Float,
+    /// String literal, with any interpolations in it.
+    #[token("\"", string_literal)]
+    Str,
 
     #[token("<=")]
     LAngleEq,
@@ -49,18 +94,109 @@ pub enum TokenType {
     #[token(")")]
     CloseParen,
 
+    #[token("=")]
+    Equal,
+    #[token(";")]
+    Semicolon,
+    #[token(",")]
+    Comma,
+    #[token(":")]
+    Colon,
+    #[token("->")]
+    Arrow,
+
+    #[token("let")]
+    Let,
     #[token("if")]
     If,
     #[token("then")]
     Then,
     #[token("else")]
     Else,
+    #[token("fn")]
+    Fn,
 
     #[token("_")]
     Underscore,
-    #[regex(r"[a-zA-Z_]+")]
+    #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
     Identifier,
     UnknownToken,
+    /// Block comment missing its `*/`, which runs to the end of input.
+    UnterminatedComment,
+    /// String literal missing its closing `"`, which runs to the end of its line.
+    UnterminatedString,
+}
+
+impl TokenType {
+    /// Whether the token only separates or annotates other tokens, so the
+    /// parser skips it.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenType::Newline
+                | TokenType::Whitespace
+                | TokenType::LineComment
+                | TokenType::BlockComment
+        )
+    }
+}
+
+/// Consume the rest of a block comment after its opening `/*`.
+fn block_comment(lexer: &mut Lexer<TokenType>) -> Result<(), LexError> {
+    let rest = lexer.remainder().as_bytes();
+    let mut depth = 1;
+    let mut i = 0;
+    while i + 1 < rest.len() {
+        match &rest[i..i + 2] {
+            b"/*" => {
+                depth += 1;
+                i += 2;
+            }
+            b"*/" => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    lexer.bump(i);
+                    return Ok(());
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    lexer.bump(rest.len());
+    Err(LexError::UnterminatedComment)
+}
+
+/// Check an integer literal has a digit after its radix prefix, unlike `0x_`.
+fn radix_digits(lexer: &mut Lexer<TokenType>) -> Result<(), LexError> {
+    match lexer.slice()[2..].bytes().any(|b| b != b'_') {
+        true => Ok(()),
+        false => Err(LexError::UnknownToken),
+    }
+}
+
+/// Check a float literal has a digit in any exponent, unlike `1e_`.
+fn exponent_digits(lexer: &mut Lexer<TokenType>) -> Result<(), LexError> {
+    let slice = lexer.slice();
+    let exponent = slice.rfind(['e', 'E']).map_or("0", |i| &slice[i + 1..]);
+    match exponent.bytes().any(|b| b.is_ascii_digit()) {
+        true => Ok(()),
+        false => Err(LexError::UnknownToken),
+    }
+}
+
+/// Consume the rest of a string literal after its opening `"`.
+fn string_literal(lexer: &mut Lexer<TokenType>) -> Result<(), LexError> {
+    match string_len(lexer.remainder().as_bytes()) {
+        Ok(len) => {
+            lexer.bump(len);
+            Ok(())
+        }
+        Err(len) => {
+            lexer.bump(len);
+            Err(LexError::UnterminatedString)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -69,11 +205,11 @@ mod tests {
     use TokenType::*;
 
     fn assert_tokens(input: &str, tokens: Vec<TokenType>) {
-        let mut lex = TokenType::lexer(input);
+        let lex = TokenType::lexer(input);
         let mut output = Vec::new();
-        while let Some(res) = lex.next() {
+        for res in lex {
             match res.unwrap() {
-                Newline | Whitespace => continue,
+                token if token.is_trivia() => continue,
                 token => output.push(token),
             }
         }
@@ -96,6 +232,31 @@ mod tests {
     fn test_underscores() {
         assert_tokens("_", vec![Underscore]);
         assert_tokens("_a", vec![Identifier]);
+        assert_tokens("a_1", vec![Identifier]);
+    }
+
+    #[test]
+    fn test_let() {
+        assert_tokens(
+            "let x = y == z",
+            vec![Let, Identifier, Equal, Identifier, EqualEqual, Identifier],
+        );
+        assert_tokens("letter", vec![Identifier]);
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_tokens(
+            "fn (x: int, y: bool) -> unit",
+            vec![
+                Fn, OpenParen, Identifier, Colon, IntType, Comma, Identifier, Colon, Bool,
+                CloseParen, Arrow, UnitType,
+            ],
+        );
+        assert_tokens("f(1)", vec![Identifier, OpenParen, Int, CloseParen]);
+        assert_tokens("x->y", vec![Identifier, Arrow, Identifier]);
+        assert_tokens("x - >y", vec![Identifier, Minus, RAngle, Identifier]);
+        assert_tokens("fns integer", vec![Identifier, Identifier]);
     }
 
     #[test]
@@ -105,5 +266,99 @@ mod tests {
         assert_tokens("--123", vec![Minus, Minus, Int]);
         assert_tokens("- -123", vec![Minus, Minus, Int]);
         assert_tokens("-+123", vec![Minus, Plus, Int]);
+        assert_tokens("1_000 1__0_", vec![Int, Int]);
+        assert_tokens(
+            "0xff 0xDead_Beef 0o17 0b1010_0101",
+            vec![Int, Int, Int, Int],
+        );
+        assert_tokens("0b12", vec![Int, Int]);
+        assert_tokens("0xg _1", vec![Int, Identifier, Identifier]);
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_tokens("1.5 0.25 1_000.000_1", vec![Float, Float, Float]);
+        assert_tokens(
+            "1e3 1E-3 1e+3 1.5e3 1e1_0",
+            vec![Float, Float, Float, Float, Float],
+        );
+        assert_tokens("-1.5", vec![Minus, Float]);
+        assert_tokens("1e", vec![Int, Identifier]);
+        assert_tokens("1e-x", vec![Int, Identifier, Minus, Identifier]);
+
+        let tokens: Vec<_> = TokenType::lexer("1. .5").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Int),
+                Err(LexError::UnknownToken),
+                Ok(Whitespace),
+                Err(LexError::UnknownToken),
+                Ok(Int)
+            ]
+        );
+        let tokens: Vec<_> = TokenType::lexer("0x_ 1e_").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Err(LexError::UnknownToken),
+                Ok(Whitespace),
+                Err(LexError::UnknownToken)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_tokens("1 // two\n3", vec![Int, Int]);
+        assert_tokens("1 /* two */ 3", vec![Int, Int]);
+        assert_tokens("1 /* a /* b */ c */ 3", vec![Int, Int]);
+        assert_tokens("1/2 /**/", vec![Int, Slash, Int]);
+
+        let tokens: Vec<_> = TokenType::lexer("x /* a\n/* b */ */ // c").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Identifier),
+                Ok(Whitespace),
+                Ok(BlockComment),
+                Ok(Whitespace),
+                Ok(LineComment)
+            ]
+        );
+        let tokens: Vec<_> = TokenType::lexer("x /* a /* b */").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Identifier),
+                Ok(Whitespace),
+                Err(LexError::UnterminatedComment)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_tokens(r#""a" + "b c""#, vec![Str, Plus, Str]);
+        assert_tokens(r#""say \"hi\"" // "x"#, vec![Str]);
+        assert_tokens(
+            r#"len "x = {f("}")} " == s"#,
+            vec![Len, Str, EqualEqual, Identifier],
+        );
+        assert_tokens("str strs", vec![StrType, Identifier]);
+
+        let tokens: Vec<_> = TokenType::lexer("\"ab\n\"c").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Err(LexError::UnterminatedString),
+                Ok(Newline),
+                Err(LexError::UnterminatedString)
+            ]
+        );
+        let mut lexer = TokenType::lexer("(\"a {x");
+        assert_eq!(lexer.next(), Some(Ok(OpenParen)));
+        assert_eq!(lexer.next(), Some(Err(LexError::UnterminatedString)));
+        assert_eq!(lexer.slice(), "\"a {x");
     }
 }
@@ -1,9 +1,12 @@
 use logos::Lexer;
 use std::ops::Range;
+use std::slice;
 
+use super::token_type::LexError;
 use super::token_type::TokenType;
 use super::ParseResult;
 use crate::parse::error::ParseError;
+use crate::span::Span;
 
 /// Token data (lexeme etc)
 #[derive(Debug, Clone, PartialEq)]
@@ -17,73 +20,187 @@ pub struct TokenData<'source> {
     pub col: usize,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token<'source> {
     pub token_type: TokenType,
     pub data: TokenData<'source>,
 }
 
+/// Token stored apart from its source text, as kept by incremental parsing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LexedToken {
+    pub token_type: TokenType,
+    pub span: Span,
+}
+
+impl From<&Token<'_>> for LexedToken {
+    fn from(token: &Token<'_>) -> Self {
+        Self {
+            token_type: token.token_type,
+            span: Span::from(&token.data),
+        }
+    }
+}
+
 pub struct Tokenizer<'source> {
-    lexer: Lexer<'source, TokenType>,
+    input: Input<'source>,
     /// Contain next Token, if any.  To interpret this,
     /// None -> We have not pulled the next token.
     /// Some(None) -> We have pulled the next token, but there isn't any.
     /// Some(Some(t)) -> We've pulled the next token, and this is it.
     dock: Option<Option<Token<'source>>>,
+    /// byte index one past the last token pulled
+    end: usize,
     /// line (0-index) the next token is on
     line: usize,
     /// column (0-index) the next token starts
     col: usize,
+    /// Tokens consumed, trivia included, if recording them.
+    consumed: Option<Vec<Token<'source>>>,
+}
+
+/// Where a Tokenizer pulls its tokens from.
+enum Input<'source> {
+    Lexer(Lexer<'source, TokenType>),
+    /// Tokens lexed earlier from source.
+    Tokens {
+        source: &'source str,
+        tokens: slice::Iter<'source, LexedToken>,
+    },
 }
 
 // Tokenizer allows common operations for tokens
 impl<'source> Tokenizer<'source> {
     pub fn new(lexer: Lexer<'source, TokenType>) -> Self {
+        Self::starting_at(lexer, 0, 0, 0)
+    }
+
+    /// Tokenizer for the lexer's source from byte index offset, which must
+    /// be at line:col (0-index).
+    pub fn starting_at(
+        mut lexer: Lexer<'source, TokenType>,
+        offset: usize,
+        line: usize,
+        col: usize,
+    ) -> Self {
+        lexer.bump(offset);
+        Self {
+            input: Input::Lexer(lexer),
+            dock: None,
+            end: offset,
+            line,
+            col,
+            consumed: None,
+        }
+    }
+
+    /// Tokenizer replaying tokens previously lexed from source, rather
+    /// than lexing it again.  Positions start at the first token.
+    pub fn from_tokens(source: &'source str, tokens: &'source [LexedToken]) -> Self {
+        let start = tokens.first().map(|token| token.span).unwrap_or_default();
         Self {
-            lexer,
+            input: Input::Tokens {
+                source,
+                tokens: tokens.iter(),
+            },
             dock: None,
-            line: 0,
-            col: 0,
+            end: start.start,
+            line: start.line,
+            col: start.col,
+            consumed: None,
         }
     }
 
-    /// Pull the next TokenData from lexer; does not check dock
-    /// An Err from lexer is converted to an UnknownToken.
+    /// Pull the next TokenData from the input; does not check dock
+    /// An Err from lexer is converted to an UnknownToken, UnterminatedComment
+    /// or UnterminatedString.
     fn pull(&mut self) -> Option<Token<'source>> {
-        let token_type_opt = self
-            .lexer
-            .next()
-            .map(|res| res.unwrap_or(TokenType::UnknownToken));
+        let (token_type, span, lexeme) = match &mut self.input {
+            Input::Lexer(lexer) => {
+                let token_type = match lexer.next()? {
+                    Ok(token_type) => token_type,
+                    Err(LexError::UnknownToken) => TokenType::UnknownToken,
+                    Err(LexError::UnterminatedComment) => TokenType::UnterminatedComment,
+                    Err(LexError::UnterminatedString) => TokenType::UnterminatedString,
+                };
+                (token_type, lexer.span(), lexer.slice())
+            }
+            Input::Tokens { source, tokens } => {
+                let token = tokens.next()?;
+                let source: &'source str = source;
+                (
+                    token.token_type,
+                    token.span.range(),
+                    &source[token.span.range()],
+                )
+            }
+        };
         let data = TokenData {
-            span: self.lexer.span(),
-            lexeme: self.lexer.slice(),
+            span,
+            lexeme,
             line: self.line,
             col: self.col,
         };
-        match &token_type_opt {
-            None => (),
-            Some(TokenType::Newline) => {
-                self.line += 1;
-                self.col = 0;
+        self.end = data.span.end;
+        // Block comments may span lines.
+        match data.lexeme.rfind('\n') {
+            Some(last) => {
+                self.line += data.lexeme.matches('\n').count();
+                self.col = data.lexeme.len() - last - 1;
             }
-            Some(_) => self.col += self.lexer.span().end - self.lexer.span().start,
+            None => self.col += data.lexeme.len(),
         }
-        token_type_opt.map(|token_type| Token { token_type, data })
+        Some(Token { token_type, data })
+    }
+
+    /// The whole source the tokens are from.
+    pub fn source(&self) -> &'source str {
+        match &self.input {
+            Input::Lexer(lexer) => lexer.source(),
+            Input::Tokens { source, .. } => source,
+        }
+    }
+
+    /// Record the tokens consumed from now on, trivia included, in
+    /// consumed().  A token is consumed when advanced past, not when peeked,
+    /// and trivia when the token after them is peeked or consumed.
+    pub fn record_consumed(&mut self) {
+        self.consumed.get_or_insert_with(Vec::new);
+    }
+
+    /// Tokens consumed since record_consumed, less any taken from the Vec.
+    pub fn consumed(&mut self) -> Option<&mut Vec<Token<'source>>> {
+        self.consumed.as_mut()
     }
 
     /// Produce the next token if any, or None if no tokens left.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<Token<'source>> {
         self.dock.take().unwrap_or_else(|| self.pull())
     }
 
-    /// Produce the next token, skipping whitespace/newline.
+    /// Produce the next token, skipping whitespace, newlines and comments.
     /// Return None if no tokens are left.
     pub fn advance(&mut self) -> Option<Token<'source>> {
+        let token = match self.dock.take() {
+            Some(token) => token,
+            None => self.pull_significant(),
+        };
+        if let (Some(consumed), Some(token)) = (&mut self.consumed, &token) {
+            consumed.push(token.clone());
+        }
+        token
+    }
+
+    /// Pull the next non-trivia token, consuming the trivia before it.
+    fn pull_significant(&mut self) -> Option<Token<'source>> {
         loop {
-            let token = self.next()?;
-            match token.token_type {
-                TokenType::Newline | TokenType::Whitespace => continue,
-                _ => return Some(token),
+            let token = self.pull()?;
+            if !token.token_type.is_trivia() {
+                return Some(token);
+            }
+            if let Some(consumed) = &mut self.consumed {
+                consumed.push(token);
             }
         }
     }
@@ -102,6 +219,18 @@ impl<'source> Tokenizer<'source> {
                     ..
                 },
             ) => Err(ParseError::unknown_token(token)),
+            Some(
+                token @ Token {
+                    token_type: TokenType::UnterminatedComment,
+                    ..
+                },
+            ) => Err(ParseError::unterminated_comment(token)),
+            Some(
+                token @ Token {
+                    token_type: TokenType::UnterminatedString,
+                    ..
+                },
+            ) => Err(ParseError::unterminated_string(token)),
             Some(token) => Ok(token),
         }
     }
@@ -109,24 +238,36 @@ impl<'source> Tokenizer<'source> {
     /// Produce the next token if it is of the given type.
     /// Return ParseError::UnexpectedToken if the next token is not of the given type.
     /// Return ParseError::UnexpectedEnd if no tokens are left.
-    pub fn expect(&mut self, expected: TokenType) -> ParseResult<Token> {
+    pub fn expect(&mut self, expected: TokenType) -> ParseResult<Token<'source>> {
         match self.force_advance()? {
             token if token.token_type == expected => Ok(token),
             token => Err(ParseError::unexpected_token(token, format!("{expected:?}"))),
         }
     }
 
+    /// Return the next non-trivia token without consuming it.
+    /// Return None if no tokens are left.
+    pub fn peek(&mut self) -> Option<&Token<'source>> {
+        if self.dock.is_none() {
+            let token = self.pull_significant();
+            self.dock = Some(token);
+        }
+        self.dock.as_ref().and_then(Option::as_ref)
+    }
+
+    /// Empty span just past the last token pulled from the lexer.
+    /// Once all tokens are consumed, this is the end of input.
+    pub fn end_span(&self) -> Span {
+        Span::new(self.end, self.end, self.line, self.col)
+    }
+
     /// Produce the next token if it is of the given type.
     /// Return None if the next token is not of the given type; this does not consume the token.
     /// Return None if no tokens are left.
-    pub fn opt(&mut self, expected: TokenType) -> Option<Token> {
-        let token = self.advance()?;
-        assert!(self.dock.is_none(), "Expected empty dock after advance",);
-
-        if token.token_type == expected {
-            Some(token)
+    pub fn opt(&mut self, expected: TokenType) -> Option<Token<'source>> {
+        if self.peek()?.token_type == expected {
+            self.advance()
         } else {
-            self.dock = Some(Some(token));
             None
         }
     }
@@ -147,6 +288,15 @@ impl<'source> Tokenizer<'source> {
     // }
 }
 
+/// Every token, as from Tokenizer::next.
+impl<'source> Iterator for Tokenizer<'source> {
+    type Item = Token<'source>;
+
+    fn next(&mut self) -> Option<Token<'source>> {
+        Tokenizer::next(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use logos::Logos;
@@ -212,4 +362,49 @@ mod tests {
         let t = tokenizer.advance().unwrap();
         assert_eq!(t.token_type, False);
     }
+
+    #[test]
+    fn test_comment_positions() {
+        let mut tokenizer = Tokenizer::new(TokenType::lexer("/* a\n b */ x // y\nz"));
+        let t = tokenizer.advance().unwrap();
+        assert_eq!(t.token_type, Identifier);
+        assert_eq!((t.data.line, t.data.col), (1, 6));
+        let t = tokenizer.advance().unwrap();
+        assert_eq!(t.token_type, Identifier);
+        assert_eq!((t.data.line, t.data.col), (2, 0));
+        assert_eq!(tokenizer.advance(), None);
+
+        let mut tokenizer = Tokenizer::new(TokenType::lexer("1\n  /* x"));
+        tokenizer.advance().unwrap();
+        match tokenizer.force_advance() {
+            Err(e) => assert_eq!(e, ParseError::UnterminatedComment { line: 1, col: 2 }),
+            x => panic!("Unexpected result: {x:?}"),
+        }
+    }
+
+    #[test]
+    fn test_resume_and_replay() {
+        let source = "a\n  b c";
+        let tokens: Vec<_> = Tokenizer::new(TokenType::lexer(source))
+            .map(|token| LexedToken::from(&token))
+            .collect();
+        let mut tokenizer = Tokenizer::starting_at(TokenType::lexer(source), 4, 1, 2);
+        let t = tokenizer.advance().unwrap();
+        assert_eq!(
+            (t.data.lexeme, t.data.span.clone(), t.data.line, t.data.col),
+            ("b", 4..5, 1, 2)
+        );
+        assert_eq!(LexedToken::from(&t), tokens[3]);
+
+        let mut tokenizer = Tokenizer::from_tokens(source, &tokens[3..]);
+        let t = tokenizer.advance().unwrap();
+        assert_eq!((t.data.lexeme, t.data.line, t.data.col), ("b", 1, 2));
+        let t = tokenizer.advance().unwrap();
+        assert_eq!(
+            (t.data.lexeme, t.data.span.clone(), t.data.line, t.data.col),
+            ("c", 6..7, 1, 4)
+        );
+        assert_eq!(tokenizer.advance(), None);
+        assert_eq!(tokenizer.end_span(), Span::new(7, 7, 1, 5));
+    }
 }
@@ -0,0 +1,245 @@
+use std::env;
+use std::mem;
+use std::path::PathBuf;
+
+use carbide::ast::expr::ExprKind;
+use carbide::diagnostic::Style;
+use carbide::diagnostic::ToDiagnostic;
+use carbide::eval::Evaluator;
+use carbide::parse::parse_item;
+use carbide::parse::ParseError;
+use carbide::parse::TokenType;
+use carbide::parse::Tokenizer;
+use logos::Logos;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use crate::token_line;
+
+const HELP: &str = "\
+Enter an expression to evaluate it, or `let name = expr` to bind a name.
+An incomplete entry continues on the next line.
+
+  :ast EXPR     show the syntax tree of EXPR
+  :tokens TEXT  show the tokens of TEXT
+  :help         show this message
+  :quit         exit
+";
+
+const HISTORY_FILE: &str = ".carbide_history";
+
+/// State kept between entries: the bindings made so far, and the lines of
+/// an entry that is not yet complete.
+pub struct Session {
+    evaluator: Evaluator,
+    pending: String,
+    style: Style,
+}
+
+/// What to do after an entered line.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    /// The entry is incomplete; read another line.
+    More,
+    /// The entry is complete; show its output, if any.
+    Done(String),
+    Quit,
+}
+
+impl Session {
+    pub fn new(style: Style) -> Self {
+        Self {
+            evaluator: Evaluator::new(),
+            pending: String::new(),
+            style,
+        }
+    }
+
+    /// Whether lines are being gathered for an incomplete entry.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Discard the lines of an incomplete entry.
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn enter(&mut self, line: &str) -> Reply {
+        if !self.pending.is_empty() {
+            self.pending.push('\n');
+        }
+        self.pending.push_str(line);
+        let pending = mem::take(&mut self.pending);
+        let entry = pending.trim();
+        let (command, source) = match entry.strip_prefix(':') {
+            Some(rest) => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+            None => ("", entry),
+        };
+        let reply = match command {
+            "" if source.is_empty() => Reply::Done(String::new()),
+            "" => self.eval(source),
+            "ast" => self.ast(source),
+            "tokens" => Reply::Done(tokens(source)),
+            "help" => Reply::Done(HELP.to_owned()),
+            "quit" | "q" => Reply::Quit,
+            _ => Reply::Done(format!("unknown command ':{command}'; try :help\n")),
+        };
+        if reply == Reply::More {
+            self.pending = pending;
+        }
+        reply
+    }
+
+    fn eval(&mut self, source: &str) -> Reply {
+        let expr = match parse_item(source) {
+            Ok(expr) => expr,
+            Err(error) if is_incomplete(&error) => return Reply::More,
+            Err(error) => return Reply::Done(self.render(&error, source)),
+        };
+        match self.evaluator.eval(&expr) {
+            Ok(_) if matches!(expr.kind, ExprKind::Let { .. }) => Reply::Done(String::new()),
+            Ok(value) => Reply::Done(format!("{value}\n")),
+            Err(error) => Reply::Done(self.render(&error, source)),
+        }
+    }
+
+    fn ast(&self, source: &str) -> Reply {
+        match parse_item(source) {
+            Ok(expr) => Reply::Done(format!("{:#?}\n", expr.without_spans())),
+            Err(error) if is_incomplete(&error) => Reply::More,
+            Err(error) => Reply::Done(self.render(&error, source)),
+        }
+    }
+
+    fn render(&self, error: &impl ToDiagnostic, source: &str) -> String {
+        error.to_diagnostic(source).render(source, self.style)
+    }
+}
+
+/// Whether more input could complete the entry that produced error.
+fn is_incomplete(error: &ParseError) -> bool {
+    matches!(
+        error,
+        ParseError::Eof { .. } | ParseError::UnterminatedComment { .. }
+    )
+}
+
+/// List the tokens of source other than whitespace, one per line.
+fn tokens(source: &str) -> String {
+    Tokenizer::new(TokenType::lexer(source))
+        .filter(|token| !token.token_type.is_trivia())
+        .map(|token| token_line(&token) + "\n")
+        .collect()
+}
+
+/// Where to keep the history of entered lines: the home directory if
+/// known, else the current directory.
+fn history_path() -> PathBuf {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(HISTORY_FILE)
+}
+
+/// Read and evaluate lines from the terminal until end of input.
+pub fn run(style: Style) -> Result<(), ReadlineError> {
+    let mut editor = DefaultEditor::new()?;
+    let path = history_path();
+    // A missing history file is expected on the first run.
+    let _ = editor.load_history(&path);
+    let mut session = Session::new(style);
+    loop {
+        let prompt = if session.is_pending() { "... " } else { "> " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                session.cancel();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error),
+        };
+        if !line.trim().is_empty() {
+            editor.add_history_entry(line.as_str())?;
+        }
+        match session.enter(&line) {
+            Reply::More => (),
+            Reply::Done(output) => print!("{output}"),
+            Reply::Quit => break,
+        }
+    }
+    editor.save_history(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn done(output: &str) -> Reply {
+        Reply::Done(output.to_owned())
+    }
+
+    #[test]
+    fn test_bindings() {
+        let mut session = Session::new(Style::Plain);
+        assert_eq!(session.enter("let x = 2"), done(""));
+        assert_eq!(
+            session.enter("let double = fn (n: int) -> int (n * 2)"),
+            done("")
+        );
+        assert_eq!(session.enter("double(x) + 1"), done("5\n"));
+        assert_eq!(session.enter("(let x = 10; x)"), done("10\n"));
+        assert_eq!(session.enter("x"), done("2\n"));
+        assert_eq!(session.enter(""), done(""));
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let mut session = Session::new(Style::Plain);
+        assert_eq!(session.enter("if true ("), Reply::More);
+        assert!(session.is_pending());
+        assert_eq!(session.enter("  1 /* one"), Reply::More);
+        assert_eq!(session.enter("*/ ) else"), Reply::More);
+        assert_eq!(session.enter("2"), done("1\n"));
+        assert!(!session.is_pending());
+
+        assert_eq!(session.enter("let y ="), Reply::More);
+        session.cancel();
+        assert_eq!(session.enter("3"), done("3\n"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut session = Session::new(Style::Plain);
+        let Reply::Done(output) = session.enter("1 +* 2") else {
+            panic!("expected output");
+        };
+        assert!(output.starts_with("error: "), "{output}");
+        let Reply::Done(output) = session.enter("y") else {
+            panic!("expected output");
+        };
+        assert!(output.contains("y"), "{output}");
+        assert_eq!(session.enter("1"), done("1\n"));
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut session = Session::new(Style::Plain);
+        assert_eq!(
+            session.enter(":tokens let x = 1 // one"),
+            done("1:1 Let \"let\"\n1:5 Identifier \"x\"\n1:7 Equal \"=\"\n1:9 Int \"1\"\n")
+        );
+        // The tree leaves out spans.
+        let expr = parse_item("-x").unwrap().without_spans();
+        assert_eq!(session.enter(":ast -x"), done(&format!("{expr:#?}\n")));
+        assert_eq!(session.enter(":ast (1 +"), Reply::More);
+        assert!(matches!(session.enter("2)"), Reply::Done(_)));
+        assert_eq!(session.enter(":help"), done(HELP));
+        assert_eq!(session.enter(":quit"), Reply::Quit);
+        assert_eq!(
+            session.enter(":frob"),
+            done("unknown command ':frob'; try :help\n")
+        );
+    }
+}
@@ -0,0 +1,108 @@
+mod error;
+
+pub use error::ResolveError;
+
+use crate::ast::expr::Expr;
+use crate::ast::expr::Function;
+use crate::ast::expr::Ident;
+use crate::ast::visit::walk_block;
+use crate::ast::visit::walk_fn;
+use crate::ast::visit::Visitor;
+use crate::span::Span;
+
+/// Result of resolving the variables in an expression.
+#[derive(Debug, Default, PartialEq)]
+pub struct Resolution {
+    /// Span of each resolved variable reference, with the span of the name it refers to.
+    pub references: Vec<(Span, Span)>,
+    /// Undefined and shadowed variables, in source order.
+    pub errors: Vec<ResolveError>,
+}
+
+impl Resolution {
+    /// Span of the binding referred to by the variable reference at span, if any.
+    pub fn definition(&self, span: Span) -> Option<Span> {
+        self.references
+            .iter()
+            .find(|(reference, _)| *reference == span)
+            .map(|(_, definition)| *definition)
+    }
+}
+
+/// Resolve each variable reference in expr to its binding.
+pub fn resolve(expr: &Expr) -> Resolution {
+    let mut resolver = Resolver::default();
+    resolver.visit_expr(expr);
+    resolver.resolution
+}
+
+#[derive(Default)]
+struct Resolver {
+    /// Bindings in scope, innermost last.
+    scope: Vec<Ident>,
+    resolution: Resolution,
+}
+
+impl Visitor for Resolver {
+    // Bindings made in the block go out of scope at its end.
+    fn visit_block(&mut self, _span: Span, contents: &[Expr], _trailing_semicolon: bool) {
+        let depth = self.scope.len();
+        walk_block(self, contents);
+        self.scope.truncate(depth);
+    }
+
+    fn visit_var(&mut self, span: Span, name: &str) {
+        match self.lookup(name) {
+            Some(definition) => {
+                let definition = definition.span;
+                self.resolution.references.push((span, definition));
+            }
+            None => self.resolution.errors.push(ResolveError::Undefined {
+                name: name.to_owned(),
+                span,
+            }),
+        }
+    }
+
+    // The value is resolved before the name is bound, so it cannot refer to itself.
+    fn visit_let(&mut self, _span: Span, name: &Ident, value: &Expr) {
+        self.visit_expr(value);
+        self.bind(name);
+    }
+
+    // The name and parameters are only in scope in the body.
+    fn visit_fn(&mut self, _span: Span, function: &Function) {
+        let depth = self.scope.len();
+        if let Some(name) = &function.name {
+            self.bind(name);
+        }
+        function
+            .params
+            .iter()
+            .for_each(|param| self.bind(&param.name));
+        walk_fn(self, function);
+        self.scope.truncate(depth);
+    }
+}
+
+impl Resolver {
+    /// Bring name into scope, reporting it if it shadows an earlier binding.
+    fn bind(&mut self, name: &Ident) {
+        if let Some(previous) = self.lookup(&name.name) {
+            let previous = previous.span;
+            self.resolution.errors.push(ResolveError::Shadowed {
+                name: name.name.clone(),
+                span: name.span,
+                previous,
+            });
+        }
+        self.scope.push(name.clone());
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Ident> {
+        self.scope.iter().rev().find(|ident| ident.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,22 @@
+use crate::span::Span;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ResolveError {
+    #[error("undefined variable '{name}' at {span}")]
+    Undefined { name: String, span: Span },
+    #[error("'{name}' at {span} shadows an earlier binding at {previous}")]
+    Shadowed {
+        name: String,
+        span: Span,
+        previous: Span,
+    },
+}
+
+impl ResolveError {
+    pub fn span(&self) -> Span {
+        match self {
+            ResolveError::Undefined { span, .. } | ResolveError::Shadowed { span, .. } => *span,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use super::resolve;
+use super::ResolveError;
+use crate::parse::parse_expr;
+use crate::span::Span;
+
+#[test]
+fn test_resolve_references() {
+    let source = "(let x = 1; let y = x + 1; x * y)";
+    let resolution = resolve(&parse_expr(source).unwrap());
+    assert_eq!(resolution.errors, vec![]);
+    let x = Span::new(5, 6, 0, 5);
+    let y = Span::new(16, 17, 0, 16);
+    assert_eq!(
+        resolution.references,
+        vec![
+            (Span::new(20, 21, 0, 20), x),
+            (Span::new(27, 28, 0, 27), x),
+            (Span::new(31, 32, 0, 31), y),
+        ]
+    );
+    assert_eq!(resolution.definition(Span::new(31, 32, 0, 31)), Some(y));
+    assert_eq!(resolution.definition(Span::new(0, 1, 0, 0)), None);
+}
+
+#[test]
+fn test_resolve_undefined() {
+    let resolution = resolve(&parse_expr("x + (let y = 1; y) + y").unwrap());
+    assert_eq!(
+        resolution.errors,
+        vec![
+            ResolveError::Undefined {
+                name: "x".to_owned(),
+                span: Span::new(0, 1, 0, 0),
+            },
+            ResolveError::Undefined {
+                name: "y".to_owned(),
+                span: Span::new(21, 22, 0, 21),
+            },
+        ]
+    );
+
+    // A binding's value cannot refer to the binding itself.
+    let resolution = resolve(&parse_expr("(let x = x; x)").unwrap());
+    assert_eq!(
+        resolution.errors,
+        vec![ResolveError::Undefined {
+            name: "x".to_owned(),
+            span: Span::new(9, 10, 0, 9),
+        }]
+    );
+}
+
+#[test]
+fn test_resolve_shadowed() {
+    let source = "(let x = 1;\n  (let x = x + 1;\n   let x = 3; x))";
+    let resolution = resolve(&parse_expr(source).unwrap());
+    let outer = Span::new(5, 6, 0, 5);
+    let middle = Span::new(19, 20, 1, 7);
+    let inner = Span::new(37, 38, 2, 7);
+    assert_eq!(
+        resolution.errors,
+        vec![
+            ResolveError::Shadowed {
+                name: "x".to_owned(),
+                span: middle,
+                previous: outer,
+            },
+            ResolveError::Shadowed {
+                name: "x".to_owned(),
+                span: inner,
+                previous: middle,
+            },
+        ]
+    );
+    assert_eq!(resolution.definition(Span::new(23, 24, 1, 11)), Some(outer));
+    assert_eq!(resolution.definition(Span::new(44, 45, 2, 14)), Some(inner));
+}
+
+#[test]
+fn test_resolve_functions() {
+    let source = "(let y = 1; fn f(x: int) -> int (f(x + y)))";
+    let resolution = resolve(&parse_expr(source).unwrap());
+    assert_eq!(resolution.errors, vec![]);
+    assert_eq!(
+        resolution.references,
+        vec![
+            (Span::new(33, 34, 0, 33), Span::new(15, 16, 0, 15)),
+            (Span::new(35, 36, 0, 35), Span::new(17, 18, 0, 17)),
+            (Span::new(39, 40, 0, 39), Span::new(5, 6, 0, 5)),
+        ]
+    );
+
+    // Parameters go out of scope at the end of the body.
+    let resolution = resolve(&parse_expr("(fn (x: int) -> int (x); x)").unwrap());
+    assert_eq!(
+        resolution.errors,
+        vec![ResolveError::Undefined {
+            name: "x".to_owned(),
+            span: Span::new(25, 26, 0, 25),
+        }]
+    );
+
+    let resolution = resolve(&parse_expr("(let x = 1; fn (x: int) -> int (x))").unwrap());
+    assert_eq!(
+        resolution.errors,
+        vec![ResolveError::Shadowed {
+            name: "x".to_owned(),
+            span: Span::new(16, 17, 0, 16),
+            previous: Span::new(5, 6, 0, 5),
+        }]
+    );
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::parse::TokenData;
+
+/// Region of source text covered by a token or expression.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    /// byte index of the start in source string
+    pub start: usize,
+    /// byte index one past the end in source string
+    pub end: usize,
+    /// line (0-index) the span starts on
+    pub line: usize,
+    /// column (0-index) the span starts
+    pub col: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, col: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            col,
+        }
+    }
+
+    /// Span of len bytes starting at line:col in source.
+    /// This recovers byte indexes for errors that only record line and column.
+    pub fn locate(source: &str, line: usize, col: usize, len: usize) -> Self {
+        let line_start: usize = source.split_inclusive('\n').take(line).map(str::len).sum();
+        let start = (line_start + col).min(source.len());
+        let end = (start + len).min(source.len());
+        Self::new(start, end, line, col)
+    }
+
+    /// Smallest span covering both self and other, which must come after self.
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            end: self.end.max(other.end),
+            ..self
+        }
+    }
+
+    /// Empty span at the start of self.
+    pub fn start_span(self) -> Self {
+        Self {
+            end: self.start,
+            ..self
+        }
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl From<&TokenData<'_>> for Span {
+    fn from(data: &TokenData<'_>) -> Self {
+        Self::new(data.span.start, data.span.end, data.line, data.col)
+    }
+}
+
+/// Display as line:col, like the positions in ParseError.
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
@@ -0,0 +1,226 @@
+//! Helpers shared by the unit tests.
+
+/// Sources exercising every construct of the language, for tests that
+/// check a property of each.  Some fail to parse, check or evaluate, and
+/// tests that need well-formed input skip those.
+pub const CORPUS: &[&str] = &[
+    // Bools and their operators.
+    "true",
+    "false",
+    "not true",
+    "not not false",
+    "not  false",
+    " true and true",
+    "not true and false",
+    "not not true and not not false",
+    "true or false",
+    "true or false and not true or false",
+    "true and false and true",
+    "true or false or true",
+    "true == false",
+    "true != false",
+    "true != false or true == true",
+    "true == false != true",
+    "false and 1 / 0 == 1",
+    "true or 1 / 0 == 1",
+    // Ints and their operators.
+    "1",
+    "12",
+    "-12",
+    "+12",
+    "1 + -2",
+    "1 * 2 + 3",
+    "1 * -2",
+    "-1 * 2",
+    "2 * (3 + 4)",
+    "10 - 3 - 2",
+    "8 / 4 / 2",
+    "7 / 2",
+    "1 - 2 + 3 * 4 / 5",
+    "1 + (2 *\n  3)",
+    "1 <= 2",
+    "1 < 2 == true",
+    "(1 < 2) == (3 < 4)",
+    "1 + 2 >= 3 * 4",
+    "-(-9223372036854775807 - 1)",
+    "9223372036854775807",
+    // Number literals.
+    "1_000_000",
+    "0xff + 0o17",
+    "0b1010_1010",
+    "0x10 + 0o10 + 0b10",
+    "1.5",
+    "2.5e3 / 1e-3",
+    "-1.5 * 2",
+    "1.5 + 2.25",
+    "-1.5 * 2.0",
+    "+0.5 - 1e-1",
+    "7 / 2.0",
+    "1.0 / 0.0 > 1e308",
+    "(let nan = 0.0 / 0.0; nan < 1.0 or nan >= 1.0 or nan == nan)",
+    "fn (x: float) -> float (x)",
+    // Strings.
+    r#""""#,
+    r#""a\tb\n\"c\"""#,
+    r#""caf\u{e9} \{}""#,
+    r#""a" + "b" < "c""#,
+    r#""ab" + "" + "c""#,
+    r#""ab" < "b""#,
+    r#""b" <= "ab""#,
+    r#""ab" == "a" + "b""#,
+    r#"len "ab" + 1"#,
+    r#"len "caf\u{e9}""#,
+    "fn (s: str) -> int (len s)",
+    "fn (s: str) -> int (len(s))",
+    r#""x = {x}, y = { f(y, "{z}") }!""#,
+    "\n  \"a{ b + 1 }\"",
+    r#"(let x = 2; "{x} * {x} = {x * x}, {x > 1}")"#,
+    r#""{1.0} {2.5e-7} {1e20} {-0.0}""#,
+    r#""[{"\{{1}\}"}] {if false (1)} {fn f() -> int (1)}""#,
+    // Blocks, bindings and ifs.
+    "()",
+    "(true)",
+    "(true or false)",
+    "false and (true or false)",
+    "(1; true)",
+    "(1; true;)",
+    "(1; 2)",
+    "(1; 2;)",
+    "(1;\n 2;)",
+    "(let x = 1)",
+    "(let x = 1;)",
+    "(let x = 1; x)",
+    "(let x = 1; let y2 = x + 1; x * y2)",
+    "(let x = 2; let y = x + 1; x * y)",
+    "(let x = 1; (let x = x + 1; x) + x)",
+    "(let b = true; if b (1) else 2)",
+    "x",
+    "x * 2",
+    "if true (false)",
+    "if true (false) else true or false",
+    "if true (false) else if true (true) else false",
+    "if true (1) else -2",
+    "if true (1) else 2",
+    "if true (1)",
+    "if true (1;) else (2)",
+    "if 1 > 2 (1) else 2",
+    "if false (1) else if true (2) else 3",
+    "if false (1)",
+    // Functions and calls.
+    "fn (x: int, y: int) -> int (x + y)",
+    "fn() -> unit (1;)",
+    "fn twice(f: fn(int) -> int, b: bool) -> fn() -> bool (f)",
+    "fn f(x: int) -> int (x)",
+    "fn named() -> int (1)",
+    "fn () -> int (1)()",
+    "f()",
+    "f(1, x + 2)",
+    "f(1)(2)",
+    "-f(1) * 2",
+    "if f (1)",
+    "if f(x) (1)",
+    "(fn (x: int) -> int (x))(1)",
+    "(let add = fn (x: int, y: int) -> int (x + y); add(1, 2))",
+    "(let f = fn (b: bool) -> unit (b;); f(true))",
+    "(fn f(x: int) -> int (x)) == (fn f(x: int) -> int (x))",
+    "(let f = fn (x: int) -> int (x); f == f)",
+    "(
+        let adder = fn (x: int) -> fn(int) -> int (fn (y: int) -> int (x + y));
+        let add2 = adder(2);
+        let x = 10;
+        add2(3) + x
+    )",
+    "(
+        let twice = fn (f: fn(int) -> int, x: int) -> int (f(f(x)));
+        let k = 3;
+        twice(fn (x: int) -> int (x * k), 2)
+    )",
+    "(
+        let fact = fn fact(n: int) -> int (if n <= 1 (1) else (n * fact(n - 1)));
+        fact(10)
+    )",
+    "fn fib(n: int) -> int (
+        if n < 2 (n) else (fib(n - 1) + fib(n - 2))
+    )(15)",
+    "(let one = 1; fn count(n: int) -> int (if n <= one (one) else (one + count(n - 1))))(100)",
+    // Comments.
+    "// sum\n1 + /* two */ 2 // done",
+    "(let x = 1; /* /* nested */ x; */\n x)",
+    "1 //* not a block comment",
+    "4 /**/ / 2",
+    "/* a\n b */ x",
+    "1 +\n  /* two /* */ 2",
+    // Errors when checked or run.
+    "1 / 0",
+    "(1 / 0; 2)",
+    "1 + (9223372036854775807 + 1)",
+    "(-9223372036854775807 - 1) / -1",
+    "1 < 2 < 3",
+    "1 <= 2 > 3",
+    "1 <= 2 != 3 > 4",
+    "1 and true",
+    "not 3",
+    "true + 1",
+    "1 == true",
+    "if 1 (2)",
+    "1.5 + 1",
+    "(let s = \"a\"; s + 1)",
+    "\"{1 / 0}\"",
+    "(let x = 1; x) + x",
+    "(let x = 1; x(2))",
+    "(let f = fn (x: int) -> int (x); f(1, 2))",
+    "(let f = fn (x: int) -> int (x); f(true))",
+    "(let f = fn (y: int) -> int (y); f(1); y)",
+    "fn f(n: int) -> int (f(n))(1)",
+    // Parse errors.
+    "",
+    "!",
+    "\n !",
+    "true false",
+    "(true))",
+    ")",
+    "(",
+    "<",
+    "+",
+    ";",
+    "(1 + ) and (then bar) or true",
+    "1 + then bar",
+    "(1 + )",
+    "(1 + then bar\n 2)",
+    "(1 +",
+    "1 <",
+    "if true 1 else 2",
+    "(let 1 = 2; 3)",
+    "(let x == 2; 3)",
+    "let x = 1",
+    "let x = 1 + 2",
+    "let x = 1; x",
+    "let x =",
+    "(; 1)",
+    "(1;; 2)",
+    "(1; 2;;)",
+    "1; 2",
+    "(1 2)",
+    "(;1;; 2 + ; 3)",
+    "fn (x) -> int (x)",
+    "fn (x: y) -> int (x)",
+    "fn (1: int) -> int (1)",
+    "fn (x: int) (x)",
+    "f(1, )",
+    "f(1 +, 2)",
+    "1 /* two",
+    "(1; 2 /* three",
+    "1 +\n  \"two",
+    "\"a{\"b\"\n}\"",
+    r#" "ab\qc""#,
+    r#""\u{110000}""#,
+    r#""x = {x +}""#,
+    r#""{}""#,
+    r#""{x y}""#,
+    "(\"a\\z\"; \"{1 +}\"; \"b\n)",
+    "1 + 9223372036854775808",
+    "0x1_0000_0000_0000_0000",
+    "1e309",
+    "0x_",
+    "(99999999999999999999 + 1; 1e999)",
+];
@@ -1,11 +1,104 @@
+use std::fmt;
+
 /// Carbide type
 pub trait TType {
     type Native;
     const KEYWORD: &'static str;
+    const TYPE: Type;
 }
 
 pub struct Bool {}
 impl TType for Bool {
     type Native = bool;
     const KEYWORD: &'static str = "bool";
+    const TYPE: Type = Type::Bool;
+}
+
+pub struct Int {}
+impl TType for Int {
+    type Native = i64;
+    const KEYWORD: &'static str = "int";
+    const TYPE: Type = Type::Int;
+}
+
+pub struct Float {}
+impl TType for Float {
+    type Native = f64;
+    const KEYWORD: &'static str = "float";
+    const TYPE: Type = Type::Float;
+}
+
+pub struct Unit {}
+impl TType for Unit {
+    type Native = ();
+    const KEYWORD: &'static str = "unit";
+    const TYPE: Type = Type::Unit;
+}
+
+pub struct Str {}
+impl TType for Str {
+    type Native = String;
+    const KEYWORD: &'static str = "str";
+    const TYPE: Type = Type::Str;
+}
+
+/// Keyword introducing a function literal or function type.
+pub const FN_KEYWORD: &str = "fn";
+
+/// Type of a Carbide expression, as assigned by the type checker.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Type {
+    Bool,
+    Int,
+    Float,
+    Unit,
+    Str,
+    /// Function taking arguments of the params types and returning ret.
+    Fn {
+        params: Vec<Type>,
+        ret: Box<Type>,
+    },
+}
+
+impl Type {
+    pub fn function(params: Vec<Type>, ret: Type) -> Self {
+        Type::Fn {
+            params,
+            ret: Box::new(ret),
+        }
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Type::Bool => Bool::KEYWORD,
+            Type::Int => Int::KEYWORD,
+            Type::Float => Float::KEYWORD,
+            Type::Unit => Unit::KEYWORD,
+            Type::Str => Str::KEYWORD,
+            Type::Fn { .. } => FN_KEYWORD,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Fn { params, ret } => {
+                write!(f, "{FN_KEYWORD}(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{param}")?;
+                }
+                write!(f, ") -> {ret}")
+            }
+            _ => f.write_str(self.keyword()),
+        }
+    }
 }
@@ -0,0 +1,274 @@
+mod error;
+
+pub use error::TypeError;
+
+use crate::ast::expr::BinaryOp;
+use crate::ast::expr::Expr;
+use crate::ast::expr::ExprKind;
+use crate::ast::expr::Function;
+use crate::ast::expr::Ident;
+use crate::ast::expr::Literal;
+use crate::ast::expr::StrPart;
+use crate::ast::expr::UnaryOp;
+use crate::span::Span;
+use crate::ttype::Type;
+
+pub type TypeResult<T> = Result<T, TypeError>;
+
+/// Types of operands of arithmetic operators.
+const NUMBERS: &[Type] = &[Type::Int, Type::Float];
+/// Types of operands of `+` and the ordering comparisons.
+const ORDERED: &[Type] = &[Type::Int, Type::Float, Type::Str];
+
+/// Type check an expression, returning its type.
+pub fn typecheck(expr: &Expr) -> TypeResult<Type> {
+    TypeChecker::new().check(expr)
+}
+
+/// Static type checker for Expr.
+#[derive(Default)]
+pub struct TypeChecker {
+    /// Types of bindings in scope, innermost last.
+    env: Vec<(String, Type)>,
+    /// Type of each expression and binding name checked, in the order
+    /// their checks finished.
+    types: Vec<(Span, Type)>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Types assigned so far, with the span of the expression or binding
+    /// name each was assigned to.  Checks that fail assign no type to the
+    /// enclosing expressions.
+    pub fn types(&self) -> &[(Span, Type)] {
+        &self.types
+    }
+
+    /// Assign a type to expr and each of its subexpressions.
+    /// Return the first TypeError encountered, if any.
+    pub fn check(&mut self, expr: &Expr) -> TypeResult<Type> {
+        let ttype = self.check_kind(expr)?;
+        self.types.push((expr.span, ttype.clone()));
+        Ok(ttype)
+    }
+
+    fn bind(&mut self, name: &Ident, ttype: Type) {
+        self.types.push((name.span, ttype.clone()));
+        self.env.push((name.name.clone(), ttype));
+    }
+
+    fn check_kind(&mut self, expr: &Expr) -> TypeResult<Type> {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Bool(_)) => Ok(Type::Bool),
+            ExprKind::Literal(Literal::Int(_)) => Ok(Type::Int),
+            ExprKind::Literal(Literal::Float(_)) => Ok(Type::Float),
+            ExprKind::Literal(Literal::Str(_)) => Ok(Type::Str),
+            ExprKind::Unary { op, right } => self.check_unary(*op, right),
+            ExprKind::Binary { left, op, right } => self.check_binary(left, *op, right),
+            ExprKind::Block {
+                contents,
+                trailing_semicolon,
+            } => self.check_block(contents, *trailing_semicolon),
+            ExprKind::Var(name) => self.lookup(name, expr.span),
+            ExprKind::Let { name, value } => {
+                let ttype = self.check(value)?;
+                self.bind(name, ttype);
+                Ok(Type::Unit)
+            }
+            ExprKind::If {
+                condition,
+                then_expr,
+                else_expr,
+            } => self.check_if(expr, condition, then_expr, else_expr.as_deref()),
+            ExprKind::Fn(function) => self.check_fn(function),
+            ExprKind::Call { callee, args } => self.check_call(expr, callee, args),
+            ExprKind::Interpolate(parts) => {
+                for part in parts {
+                    if let StrPart::Expr(expr) = part {
+                        self.check(expr)?;
+                    }
+                }
+                Ok(Type::Str)
+            }
+            ExprKind::Error => Err(TypeError::Unparsed { span: expr.span }),
+        }
+    }
+
+    fn check_unary(&mut self, op: UnaryOp, right: &Expr) -> TypeResult<Type> {
+        match op {
+            UnaryOp::Not => {
+                self.expect(right, &Type::Bool)?;
+                Ok(Type::Bool)
+            }
+            UnaryOp::Neg | UnaryOp::Pos => self.check_one_of(right, NUMBERS),
+            UnaryOp::Len => {
+                self.expect(right, &Type::Str)?;
+                Ok(Type::Int)
+            }
+        }
+    }
+
+    fn check_binary(&mut self, left: &Expr, op: BinaryOp, right: &Expr) -> TypeResult<Type> {
+        match op {
+            BinaryOp::And | BinaryOp::Or => {
+                self.expect(left, &Type::Bool)?;
+                self.expect(right, &Type::Bool)?;
+                Ok(Type::Bool)
+            }
+            BinaryOp::Equal | BinaryOp::NotEqual => {
+                let left_type = self.check(left)?;
+                if let Type::Fn { .. } = left_type {
+                    return Err(TypeError::NotComparable {
+                        ttype: left_type,
+                        span: left.span,
+                    });
+                }
+                self.expect(right, &left_type)?;
+                Ok(Type::Bool)
+            }
+            BinaryOp::Less | BinaryOp::LessEq | BinaryOp::Great | BinaryOp::GreatEq => {
+                self.check_operands(left, right, ORDERED)?;
+                Ok(Type::Bool)
+            }
+            BinaryOp::Add => self.check_operands(left, right, ORDERED),
+            BinaryOp::Sub | BinaryOp::Mult | BinaryOp::Div => {
+                self.check_operands(left, right, NUMBERS)
+            }
+        }
+    }
+
+    /// Check that left has one of the allowed types, and right the same
+    /// type, which is returned.
+    fn check_operands(&mut self, left: &Expr, right: &Expr, allowed: &[Type]) -> TypeResult<Type> {
+        let ttype = self.check_one_of(left, allowed)?;
+        self.expect(right, &ttype)?;
+        Ok(ttype)
+    }
+
+    /// Check that expr has one of the allowed types, which is returned.
+    /// Other types are a Mismatch with the first allowed.
+    fn check_one_of(&mut self, expr: &Expr, allowed: &[Type]) -> TypeResult<Type> {
+        let ttype = self.check(expr)?;
+        if !allowed.contains(&ttype) {
+            return Err(TypeError::Mismatch {
+                expected: allowed[0].clone(),
+                found: ttype,
+                span: expr.span,
+            });
+        }
+        Ok(ttype)
+    }
+
+    // Bindings made in the block go out of scope at its end.
+    fn check_block(&mut self, contents: &[Expr], trailing_semicolon: bool) -> TypeResult<Type> {
+        let depth = self.env.len();
+        let mut ttype = Ok(Type::Unit);
+        for expr in contents {
+            ttype = self.check(expr);
+            if ttype.is_err() {
+                break;
+            }
+        }
+        self.env.truncate(depth);
+        match ttype {
+            Ok(_) if trailing_semicolon => Ok(Type::Unit),
+            ttype => ttype,
+        }
+    }
+
+    fn lookup(&self, name: &str, span: Span) -> TypeResult<Type> {
+        self.env
+            .iter()
+            .rev()
+            .find(|(bound, _)| bound == name)
+            .map(|(_, ttype)| ttype.clone())
+            .ok_or_else(|| TypeError::UndefinedVariable {
+                name: name.to_owned(),
+                span,
+            })
+    }
+
+    // An If without an else has type unit, whatever the type of the then branch.
+    fn check_if(
+        &mut self,
+        expr: &Expr,
+        condition: &Expr,
+        then_expr: &Expr,
+        else_expr: Option<&Expr>,
+    ) -> TypeResult<Type> {
+        self.expect(condition, &Type::Bool)?;
+        let then_type = self.check(then_expr)?;
+        match else_expr {
+            None => Ok(Type::Unit),
+            Some(else_expr) => {
+                let else_type = self.check(else_expr)?;
+                if then_type != else_type {
+                    return Err(TypeError::BranchMismatch {
+                        then_type,
+                        else_type,
+                        span: expr.span,
+                    });
+                }
+                Ok(then_type)
+            }
+        }
+    }
+
+    // The body is checked against the declared return type, with the
+    // function's name (if any) and parameters in scope.
+    fn check_fn(&mut self, function: &Function) -> TypeResult<Type> {
+        let ttype = function.ttype();
+        let depth = self.env.len();
+        if let Some(name) = &function.name {
+            self.bind(name, ttype.clone());
+        }
+        for param in &function.params {
+            self.bind(&param.name, param.ttype.clone());
+        }
+        let body = self.expect(&function.body, &function.ret);
+        self.env.truncate(depth);
+        body.map(|_| ttype)
+    }
+
+    fn check_call(&mut self, expr: &Expr, callee: &Expr, args: &[Expr]) -> TypeResult<Type> {
+        let (params, ret) = match self.check(callee)? {
+            Type::Fn { params, ret } => (params, ret),
+            found => {
+                return Err(TypeError::NotCallable {
+                    found,
+                    span: callee.span,
+                })
+            }
+        };
+        if params.len() != args.len() {
+            return Err(TypeError::ArgumentCount {
+                expected: params.len(),
+                found: args.len(),
+                span: expr.span,
+            });
+        }
+        for (arg, param) in args.iter().zip(&params) {
+            self.expect(arg, param)?;
+        }
+        Ok(*ret)
+    }
+
+    /// Check expr, and return TypeError::Mismatch if it is not of the expected type.
+    fn expect(&mut self, expr: &Expr, expected: &Type) -> TypeResult<()> {
+        let found = self.check(expr)?;
+        if found != *expected {
+            return Err(TypeError::Mismatch {
+                expected: expected.clone(),
+                found,
+                span: expr.span,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,48 @@
+use crate::span::Span;
+use crate::ttype::Type;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TypeError {
+    #[error("expected {expected} at {span}, but found {found}")]
+    Mismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    #[error("if and else branches have different types at {span}: {then_type} and {else_type}")]
+    BranchMismatch {
+        then_type: Type,
+        else_type: Type,
+        span: Span,
+    },
+    #[error("undefined variable '{name}' at {span}")]
+    UndefinedVariable { name: String, span: Span },
+    #[error("values of type {ttype} cannot be compared, at {span}")]
+    NotComparable { ttype: Type, span: Span },
+    #[error("expected a function at {span}, but found {found}")]
+    NotCallable { found: Type, span: Span },
+    #[error("expected {expected} arguments at {span}, but found {found}")]
+    ArgumentCount {
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    #[error("cannot type check unparsed expression at {span}")]
+    Unparsed { span: Span },
+}
+
+impl TypeError {
+    /// Source span of the expression with the type error.
+    pub fn span(&self) -> Span {
+        match self {
+            TypeError::Mismatch { span, .. }
+            | TypeError::BranchMismatch { span, .. }
+            | TypeError::UndefinedVariable { span, .. }
+            | TypeError::NotComparable { span, .. }
+            | TypeError::NotCallable { span, .. }
+            | TypeError::ArgumentCount { span, .. }
+            | TypeError::Unparsed { span } => *span,
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use super::typecheck;
+use super::TypeChecker;
+use super::TypeError;
+use crate::parse::parse_expr;
+use crate::span::Span;
+use crate::ttype::Type;
+
+fn check(source: &str) -> Result<Type, TypeError> {
+    typecheck(&parse_expr(source).unwrap())
+}
+
+#[test]
+fn test_check_literals() {
+    assert_eq!(check("true"), Ok(Type::Bool));
+    assert_eq!(check("12"), Ok(Type::Int));
+    assert_eq!(check("(12)"), Ok(Type::Int));
+}
+
+#[test]
+fn test_check_operators() {
+    assert_eq!(check("not not false"), Ok(Type::Bool));
+    assert_eq!(check("-12"), Ok(Type::Int));
+    assert_eq!(check("true or false and not true"), Ok(Type::Bool));
+    assert_eq!(check("1 * 2 + 3"), Ok(Type::Int));
+    assert_eq!(check("1 + 2 >= 3 * 4"), Ok(Type::Bool));
+    assert_eq!(check("1 <= 2 != 3 > 4"), Ok(Type::Bool));
+    assert_eq!(check("1 == 2"), Ok(Type::Bool));
+}
+
+#[test]
+fn test_check_strings() {
+    assert_eq!(check("\"a\" + \"b\""), Ok(Type::Str));
+    assert_eq!(check("\"a\" < \"b\" and 1 >= 2"), Ok(Type::Bool));
+    assert_eq!(check("len \"ab\" * 2"), Ok(Type::Int));
+    assert_eq!(check("\"{1 + 2} {fn () -> bool (true)}\""), Ok(Type::Str));
+    assert_eq!(
+        check("fn (s: str) -> str (s + \"!\")"),
+        Ok(Type::function(vec![Type::Str], Type::Str))
+    );
+    assert_eq!(
+        check("\"a\" + 1"),
+        Err(TypeError::Mismatch {
+            expected: Type::Str,
+            found: Type::Int,
+            span: Span::new(6, 7, 0, 6),
+        })
+    );
+    assert_eq!(
+        check("true < \"a\""),
+        Err(TypeError::Mismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(0, 4, 0, 0),
+        })
+    );
+    assert_eq!(
+        check("len 3"),
+        Err(TypeError::Mismatch {
+            expected: Type::Str,
+            found: Type::Int,
+            span: Span::new(4, 5, 0, 4),
+        })
+    );
+    assert_eq!(
+        check("\"x = {-true}\""),
+        Err(TypeError::Mismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(7, 11, 0, 7),
+        })
+    );
+}
+
+#[test]
+fn test_check_floats() {
+    assert_eq!(check("1.5 * 2.0 - -0.5"), Ok(Type::Float));
+    assert_eq!(check("1.5 < 2e3 == 0x1 > 0"), Ok(Type::Bool));
+    assert_eq!(
+        check("fn (x: float) -> float (x / 2.0)"),
+        Ok(Type::function(vec![Type::Float], Type::Float))
+    );
+    assert_eq!(
+        check("1.5 + 1"),
+        Err(TypeError::Mismatch {
+            expected: Type::Float,
+            found: Type::Int,
+            span: Span::new(6, 7, 0, 6),
+        })
+    );
+    assert_eq!(
+        check("\"a\" - \"b\""),
+        Err(TypeError::Mismatch {
+            expected: Type::Int,
+            found: Type::Str,
+            span: Span::new(0, 3, 0, 0),
+        })
+    );
+}
+
+#[test]
+fn test_check_ifs() {
+    assert_eq!(check("if true (1) else 2"), Ok(Type::Int));
+    assert_eq!(check("if true (1) else if false (2) else 3"), Ok(Type::Int));
+    assert_eq!(check("if true (1)"), Ok(Type::Unit));
+}
+
+#[test]
+fn test_check_let_bindings() {
+    assert_eq!(check("(let x = 2; let y = x > 1; y)"), Ok(Type::Bool));
+    assert_eq!(
+        check("(let x = 1; (let x = true; x) and x == 1)"),
+        Ok(Type::Bool)
+    );
+    assert_eq!(
+        check("(let x = 1; not x)"),
+        Err(TypeError::Mismatch {
+            expected: Type::Bool,
+            found: Type::Int,
+            span: Span::new(16, 17, 0, 16),
+        })
+    );
+    assert_eq!(
+        check("(let x = 1; x) + x"),
+        Err(TypeError::UndefinedVariable {
+            name: "x".to_owned(),
+            span: Span::new(17, 18, 0, 17),
+        })
+    );
+}
+
+#[test]
+fn test_check_semicolons() {
+    assert_eq!(check("(true; 2)"), Ok(Type::Int));
+    assert_eq!(check("(true; 2;)"), Ok(Type::Unit));
+    assert_eq!(check("(let x = 1)"), Ok(Type::Unit));
+    assert_eq!(check("if true (1;) else (2;)"), Ok(Type::Unit));
+    assert_eq!(
+        check("if true (1;) else 2"),
+        Err(TypeError::BranchMismatch {
+            then_type: Type::Unit,
+            else_type: Type::Int,
+            span: Span::new(0, 19, 0, 0),
+        })
+    );
+}
+
+#[test]
+fn test_check_functions() {
+    assert_eq!(
+        check("fn (x: int, b: bool) -> int (x)"),
+        Ok(Type::function(vec![Type::Int, Type::Bool], Type::Int))
+    );
+    assert_eq!(
+        check("(let add = fn (x: int, y: int) -> int (x + y); add(1, 2))"),
+        Ok(Type::Int)
+    );
+    assert_eq!(
+        check("fn (x: int) -> fn(int) -> bool (fn (y: int) -> bool (x < y))"),
+        Ok(Type::function(
+            vec![Type::Int],
+            Type::function(vec![Type::Int], Type::Bool)
+        ))
+    );
+    assert_eq!(
+        check("fn fact(n: int) -> int (if n <= 1 (1) else (n * fact(n - 1)))(5)"),
+        Ok(Type::Int)
+    );
+    assert_eq!(
+        check("fn (x: int) -> int (x)").unwrap().to_string(),
+        "fn(int) -> int"
+    );
+}
+
+#[test]
+fn test_check_function_errors() {
+    assert_eq!(
+        check("fn (x: int) -> bool (x)"),
+        Err(TypeError::Mismatch {
+            expected: Type::Bool,
+            found: Type::Int,
+            span: Span::new(20, 23, 0, 20),
+        })
+    );
+    assert_eq!(
+        check("(fn (x: int) -> int (x); x)"),
+        Err(TypeError::UndefinedVariable {
+            name: "x".to_owned(),
+            span: Span::new(25, 26, 0, 25),
+        })
+    );
+    assert_eq!(
+        check("(let x = 1; x(2))"),
+        Err(TypeError::NotCallable {
+            found: Type::Int,
+            span: Span::new(12, 13, 0, 12),
+        })
+    );
+    assert_eq!(
+        check("fn (x: int) -> int (x)()"),
+        Err(TypeError::ArgumentCount {
+            expected: 1,
+            found: 0,
+            span: Span::new(0, 24, 0, 0),
+        })
+    );
+    assert_eq!(
+        check("fn (x: int) -> int (x)(true)"),
+        Err(TypeError::Mismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(23, 27, 0, 23),
+        })
+    );
+    assert_eq!(
+        check("(let f = fn () -> int (1); f == f)"),
+        Err(TypeError::NotComparable {
+            ttype: Type::function(vec![], Type::Int),
+            span: Span::new(27, 28, 0, 27),
+        })
+    );
+}
+
+#[test]
+fn test_check_errors() {
+    assert_eq!(
+        check("1 and true"),
+        Err(TypeError::Mismatch {
+            expected: Type::Bool,
+            found: Type::Int,
+            span: Span::new(0, 1, 0, 0),
+        })
+    );
+    assert_eq!(
+        check("not 3"),
+        Err(TypeError::Mismatch {
+            expected: Type::Bool,
+            found: Type::Int,
+            span: Span::new(4, 5, 0, 4),
+        })
+    );
+    assert_eq!(
+        check("-true"),
+        Err(TypeError::Mismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(1, 5, 0, 1),
+        })
+    );
+    assert_eq!(
+        check("1 == true"),
+        Err(TypeError::Mismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(5, 9, 0, 5),
+        })
+    );
+    assert_eq!(
+        check("if 1 (2)"),
+        Err(TypeError::Mismatch {
+            expected: Type::Bool,
+            found: Type::Int,
+            span: Span::new(3, 4, 0, 3),
+        })
+    );
+    assert_eq!(
+        check("if true (1) else false"),
+        Err(TypeError::BranchMismatch {
+            then_type: Type::Int,
+            else_type: Type::Bool,
+            span: Span::new(0, 22, 0, 0),
+        })
+    );
+}
+
+#[test]
+fn test_error_positions() {
+    let err = check("1 +\n  (2 * true)").unwrap_err();
+    assert_eq!(err.span(), Span::new(11, 15, 1, 7));
+    assert_eq!(err.to_string(), "expected int at 1:7, but found bool");
+}
+
+#[test]
+fn test_recorded_types() {
+    let mut checker = TypeChecker::new();
+    let expr = parse_expr("(let f = fn (n: int) -> bool (n < 2); f)").unwrap();
+    checker.check(&expr).unwrap();
+    let at = |start, end| {
+        checker
+            .types()
+            .iter()
+            .find(|(span, _)| span.range() == (start..end))
+            .map(|(_, ttype)| ttype.to_string())
+    };
+    let f = Some("fn(int) -> bool".to_owned());
+    assert_eq!(at(5, 6), f);
+    assert_eq!(at(13, 14), Some("int".to_owned()));
+    assert_eq!(at(29, 36), Some("bool".to_owned()));
+    assert_eq!(at(1, 36), Some("unit".to_owned()));
+    assert_eq!(at(38, 39), f);
+    assert_eq!(at(0, 40), f);
+
+    // Failed checks record the types of the subexpressions checked.
+    let mut checker = TypeChecker::new();
+    let expr = parse_expr("(1 + 2) * true").unwrap();
+    assert!(checker.check(&expr).is_err());
+    let spans: Vec<_> = checker
+        .types()
+        .iter()
+        .map(|(span, _)| span.range())
+        .collect();
+    assert_eq!(spans, vec![1..2, 5..6, 0..7, 10..14]);
+}
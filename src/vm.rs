@@ -0,0 +1,406 @@
+mod value;
+
+use std::cmp::Ordering;
+use std::mem;
+use std::rc::Rc;
+
+pub use value::Closure;
+pub use value::Value;
+
+use crate::compile::Capture;
+use crate::compile::Chunk;
+use crate::compile::Constant;
+use crate::compile::Op;
+use crate::eval::EvalResult;
+use crate::eval::RuntimeError;
+use crate::span::Span;
+use crate::ttype::Type;
+
+/// Calls that may be in progress at once, beyond which a call fails with
+/// RuntimeError::CallDepth.  Frames live on the heap, so unlike the
+/// evaluator's MAX_CALL_DEPTH this only stops runaway recursion.
+pub const MAX_FRAMES: usize = 1_000_000;
+
+/// Run a compiled chunk with a fresh Vm.
+pub fn run(chunk: &Rc<Chunk>) -> EvalResult<Value> {
+    Vm::new().run(chunk)
+}
+
+/// Call frame of a function being executed.
+struct Frame {
+    /// Closure being called, or None for the top-level chunk.
+    closure: Option<Rc<Closure>>,
+    chunk: Rc<Chunk>,
+    /// Index of the next instruction.
+    ip: usize,
+    /// Index in the stack of the frame's slot 0.
+    base: usize,
+}
+
+/// Stack-based virtual machine for chunks from compile.
+/// Failures are the RuntimeErrors the evaluator would return.
+#[derive(Default)]
+pub struct Vm {
+    stack: Vec<Value>,
+    /// Frames of the callers of the current function, innermost last.
+    frames: Vec<Frame>,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn run(&mut self, chunk: &Rc<Chunk>) -> EvalResult<Value> {
+        self.stack.clear();
+        self.frames.clear();
+        let mut frame = Frame {
+            closure: None,
+            chunk: chunk.clone(),
+            ip: 0,
+            base: 0,
+        };
+        loop {
+            let op = frame.chunk.code[frame.ip];
+            let span = frame.chunk.spans[frame.ip];
+            frame.ip += 1;
+            match op {
+                Op::Constant(index) => match &frame.chunk.constants[index as usize] {
+                    Constant::Int(i) => self.push(Value::Int(*i)),
+                    Constant::Float(x) => self.push(Value::Float(*x)),
+                    Constant::Str(s) => self.push(Value::Str(s.clone())),
+                    constant => panic!("Constant instruction for {constant:?}"),
+                },
+                Op::True => self.push(Value::Bool(true)),
+                Op::False => self.push(Value::Bool(false)),
+                Op::Unit => self.push(Value::Unit),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::PopUnder(count) => {
+                    let value = self.pop();
+                    self.stack.truncate(self.stack.len() - count as usize);
+                    self.push(value);
+                }
+                Op::GetLocal(slot) => {
+                    let value = self.stack[frame.base + slot as usize].clone();
+                    self.push(value);
+                }
+                Op::GetCapture(index) => {
+                    let closure = frame.closure.as_ref().expect("capture outside closure");
+                    self.push(closure.captures[index as usize].clone());
+                }
+                Op::Undefined(index) => {
+                    let name = match &frame.chunk.constants[index as usize] {
+                        Constant::Name(name) => name.clone(),
+                        constant => panic!("Undefined instruction for non-name {constant:?}"),
+                    };
+                    return Err(RuntimeError::UndefinedVariable { name, span });
+                }
+                Op::Unparsed => return Err(RuntimeError::Unparsed { span }),
+
+                Op::CheckBool => self.check(Type::Bool, span)?,
+                Op::CheckInt => self.check(Type::Int, span)?,
+                Op::CheckStr => self.check(Type::Str, span)?,
+                Op::CheckNumber => {
+                    if !matches!(self.peek(), Value::Int(_) | Value::Float(_)) {
+                        self.check(Type::Int, span)?;
+                    }
+                }
+                Op::CheckNumberOrStr => {
+                    if !matches!(self.peek(), Value::Int(_) | Value::Float(_) | Value::Str(_)) {
+                        self.check(Type::Int, span)?;
+                    }
+                }
+                Op::CheckSame => {
+                    let expected = self.stack[self.stack.len() - 2].ttype();
+                    self.check(expected, span)?;
+                }
+                Op::Not => {
+                    let b = self.pop_bool(span)?;
+                    self.push(Value::Bool(!b));
+                }
+                Op::Neg => match self.pop() {
+                    Value::Int(i) => self.push(checked(i.checked_neg(), "-", span)?),
+                    Value::Float(x) => self.push(Value::Float(-x)),
+                    value => {
+                        return Err(RuntimeError::TypeMismatch {
+                            expected: Type::Int,
+                            found: value.ttype(),
+                            span,
+                        })
+                    }
+                },
+                Op::Add => match self.peek() {
+                    Value::Str(_) => {
+                        let right = self.pop_str(span)?;
+                        let left = self.pop_str(span)?;
+                        self.push(Value::Str(format!("{left}{right}").into()));
+                    }
+                    _ => self.arithmetic(span, "+", i64::checked_add, |l, r| l + r)?,
+                },
+                Op::Sub => self.arithmetic(span, "-", i64::checked_sub, |l, r| l - r)?,
+                Op::Mult => self.arithmetic(span, "*", i64::checked_mul, |l, r| l * r)?,
+                Op::Div => {
+                    if self.peek() == &Value::Int(0) {
+                        return Err(RuntimeError::DivisionByZero { span });
+                    }
+                    self.arithmetic(span, "/", i64::checked_div, |l, r| l / r)?;
+                }
+                Op::Less => self.comparison(span, Ordering::is_lt)?,
+                Op::LessEq => self.comparison(span, Ordering::is_le)?,
+                Op::Great => self.comparison(span, Ordering::is_gt)?,
+                Op::GreatEq => self.comparison(span, Ordering::is_ge)?,
+                Op::Equal => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    if left.ttype() != right.ttype() {
+                        return Err(RuntimeError::TypeMismatch {
+                            expected: left.ttype(),
+                            found: right.ttype(),
+                            span,
+                        });
+                    }
+                    self.push(Value::Bool(left == right));
+                }
+                Op::Len => {
+                    let s = self.pop_str(span)?;
+                    self.push(Value::Int(s.chars().count() as i64));
+                }
+                Op::Interpolate(count) => {
+                    let values = self.stack.split_off(self.stack.len() - count as usize);
+                    let text: String = values.iter().map(Value::to_string).collect();
+                    self.push(Value::Str(text.into()));
+                }
+
+                Op::Jump(offset) => frame.ip += offset as usize,
+                Op::JumpIfFalse(offset) => {
+                    if self.peek() == &Value::Bool(false) {
+                        frame.ip += offset as usize;
+                    }
+                }
+                Op::JumpIfTrue(offset) => {
+                    if self.peek() == &Value::Bool(true) {
+                        frame.ip += offset as usize;
+                    }
+                }
+
+                Op::Closure(index) => {
+                    let prototype = match &frame.chunk.constants[index as usize] {
+                        Constant::Function(prototype) => prototype.clone(),
+                        constant => panic!("Closure instruction for non-function {constant:?}"),
+                    };
+                    let captures = prototype
+                        .captures
+                        .iter()
+                        .map(|capture| match capture {
+                            Capture::Local(slot) => self.stack[frame.base + *slot as usize].clone(),
+                            Capture::Capture(i) => {
+                                let closure =
+                                    frame.closure.as_ref().expect("capture outside closure");
+                                closure.captures[*i as usize].clone()
+                            }
+                        })
+                        .collect();
+                    self.push(Value::Fn(Rc::new(Closure {
+                        prototype,
+                        captures,
+                    })));
+                }
+                Op::CheckCallable => {
+                    if !matches!(self.peek(), Value::Fn(_)) {
+                        let found = self.peek().ttype();
+                        return Err(RuntimeError::NotCallable { found, span });
+                    }
+                }
+                Op::CheckArity(argc) => self.check_arity(0, argc, span)?,
+                Op::CheckArg(index) => {
+                    let params = &self.callee(index as usize + 1, span)?.prototype.params;
+                    let Some(expected) = params.get(index as usize) else {
+                        return Err(RuntimeError::ArgumentCount {
+                            expected: params.len(),
+                            found: index as usize + 1,
+                            span,
+                        });
+                    };
+                    let found = self.peek().ttype();
+                    if found != *expected {
+                        return Err(RuntimeError::TypeMismatch {
+                            expected: expected.clone(),
+                            found,
+                            span,
+                        });
+                    }
+                }
+                Op::Call(argc) => {
+                    if self.frames.len() == MAX_FRAMES {
+                        return Err(RuntimeError::CallDepth { span });
+                    }
+                    self.check_arity(argc as usize, argc, span)?;
+                    let closure = self.callee(argc as usize, span)?.clone();
+                    let callee = Frame {
+                        chunk: closure.prototype.chunk.clone(),
+                        closure: Some(closure),
+                        ip: 0,
+                        base: self.stack.len() - argc as usize - 1,
+                    };
+                    self.frames.push(mem::replace(&mut frame, callee));
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    self.stack.truncate(frame.base);
+                    match self.frames.pop() {
+                        Some(caller) => frame = caller,
+                        None => return Ok(value),
+                    }
+                    self.push(value);
+                }
+            }
+        }
+    }
+
+    /// The closure under count values on the stack.  Compiled code checks
+    /// this before calling, but a decoded chunk may not.
+    fn callee(&self, count: usize, span: Span) -> EvalResult<&Rc<Closure>> {
+        match &self.stack[self.stack.len() - count - 1] {
+            Value::Fn(closure) => Ok(closure),
+            value => Err(RuntimeError::NotCallable {
+                found: value.ttype(),
+                span,
+            }),
+        }
+    }
+
+    /// Fail unless the closure under count values takes argc arguments.
+    fn check_arity(&self, count: usize, argc: u8, span: Span) -> EvalResult<()> {
+        let expected = self.callee(count, span)?.prototype.params.len();
+        if expected != argc as usize {
+            return Err(RuntimeError::ArgumentCount {
+                expected,
+                found: argc as usize,
+                span,
+            });
+        }
+        Ok(())
+    }
+
+    /// Fail unless the top of the stack has the expected type.
+    fn check(&self, expected: Type, span: Span) -> EvalResult<()> {
+        let found = self.peek().ttype();
+        if found != expected {
+            return Err(RuntimeError::TypeMismatch {
+                expected,
+                found,
+                span,
+            });
+        }
+        Ok(())
+    }
+
+    /// Apply an arithmetic operator to two ints, which may overflow, or
+    /// to two floats.
+    fn arithmetic(
+        &mut self,
+        span: Span,
+        op: &'static str,
+        apply: fn(i64, i64) -> Option<i64>,
+        apply_float: fn(f64, f64) -> f64,
+    ) -> EvalResult<()> {
+        if let Value::Float(_) = self.peek() {
+            let right = self.pop_float(span)?;
+            let left = self.pop_float(span)?;
+            self.push(Value::Float(apply_float(left, right)));
+            return Ok(());
+        }
+        let right = self.pop_int(span)?;
+        let left = self.pop_int(span)?;
+        let value = checked(apply(left, right), op, span)?;
+        self.push(value);
+        Ok(())
+    }
+
+    /// Compare two numbers or two strs, which compare lexicographically.
+    /// No ordering is accepted for a NaN float.
+    fn comparison(&mut self, span: Span, apply: fn(Ordering) -> bool) -> EvalResult<()> {
+        let right = self.pop();
+        let ordering = match (self.pop(), right) {
+            (Value::Int(left), Value::Int(right)) => Some(left.cmp(&right)),
+            (Value::Float(left), Value::Float(right)) => left.partial_cmp(&right),
+            (Value::Str(left), Value::Str(right)) => Some(left.cmp(&right)),
+            (left, right) => {
+                return Err(RuntimeError::TypeMismatch {
+                    expected: left.ttype(),
+                    found: right.ttype(),
+                    span,
+                })
+            }
+        };
+        self.push(Value::Bool(ordering.is_some_and(apply)));
+        Ok(())
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("stack underflow")
+    }
+
+    fn pop_bool(&mut self, span: Span) -> EvalResult<bool> {
+        match self.pop() {
+            Value::Bool(b) => Ok(b),
+            value => Err(RuntimeError::TypeMismatch {
+                expected: Type::Bool,
+                found: value.ttype(),
+                span,
+            }),
+        }
+    }
+
+    fn pop_int(&mut self, span: Span) -> EvalResult<i64> {
+        match self.pop() {
+            Value::Int(i) => Ok(i),
+            value => Err(RuntimeError::TypeMismatch {
+                expected: Type::Int,
+                found: value.ttype(),
+                span,
+            }),
+        }
+    }
+
+    fn pop_float(&mut self, span: Span) -> EvalResult<f64> {
+        match self.pop() {
+            Value::Float(x) => Ok(x),
+            value => Err(RuntimeError::TypeMismatch {
+                expected: Type::Float,
+                found: value.ttype(),
+                span,
+            }),
+        }
+    }
+
+    fn pop_str(&mut self, span: Span) -> EvalResult<Rc<str>> {
+        match self.pop() {
+            Value::Str(s) => Ok(s),
+            value => Err(RuntimeError::TypeMismatch {
+                expected: Type::Str,
+                found: value.ttype(),
+                span,
+            }),
+        }
+    }
+}
+
+fn checked(result: Option<i64>, op: &'static str, span: Span) -> EvalResult<Value> {
+    result
+        .map(Value::Int)
+        .ok_or(RuntimeError::IntegerOverflow { op, span })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,201 @@
+use std::rc::Rc;
+
+use super::run;
+use super::Value;
+use super::MAX_FRAMES;
+use crate::compile::compile;
+use crate::eval::eval;
+use crate::eval::with_eval_stack;
+use crate::eval::RuntimeError;
+use crate::parse::parse_expr;
+use crate::parse::parse_expr_recovering;
+use crate::span::Span;
+use crate::test_util::CORPUS;
+use crate::ttype::Type;
+
+fn run_str(source: &str) -> Result<Value, RuntimeError> {
+    let chunk = compile(&parse_expr(source).unwrap()).unwrap();
+    run(&Rc::new(chunk))
+}
+
+/// Assert that the vm and the evaluator agree on source, including its
+/// unparsed parts.
+fn assert_matches_eval(source: &str) {
+    let (expr, _) = parse_expr_recovering(source);
+    let expected = with_eval_stack(|| eval(&expr).map(|value| value.to_string()));
+    let chunk = compile(&expr).unwrap();
+    let actual = run(&Rc::new(chunk)).map(|value| value.to_string());
+    assert_eq!(actual, expected, "for source {source:?}");
+}
+
+#[test]
+fn test_corpus() {
+    for source in CORPUS {
+        assert_matches_eval(source);
+    }
+}
+
+#[test]
+fn test_run_values() {
+    assert_eq!(run_str("1 + 2 * 3"), Ok(Value::Int(7)));
+    assert_eq!(run_str("-(1 - 3)"), Ok(Value::Int(2)));
+    assert_eq!(run_str("1 < 2 and not (2 <= 1)"), Ok(Value::Bool(true)));
+    assert_eq!(run_str("(1 == 1) != false"), Ok(Value::Bool(true)));
+    assert_eq!(run_str("if 1 > 2 (1) else (2)"), Ok(Value::Int(2)));
+    assert_eq!(run_str("if true (1)"), Ok(Value::Unit));
+    assert_eq!(run_str("(1; 2;)"), Ok(Value::Unit));
+    assert_eq!(
+        run_str("(let x = 1; let y = (let z = x + 1; z * 10); x + y)"),
+        Ok(Value::Int(21))
+    );
+    // Short-circuiting skips the failing right side.
+    assert_eq!(run_str("false and 1 / 0 == 1"), Ok(Value::Bool(false)));
+    assert_eq!(run_str("true or 1 / 0 == 1"), Ok(Value::Bool(true)));
+}
+
+#[test]
+fn test_run_floats() {
+    assert_eq!(run_str("(let x = 1.5; -x * 2.0)"), Ok(Value::Float(-3.0)));
+    assert_eq!(
+        run_str("(let x = 2.0; x / 0.0 == 1.0 / 0.0)"),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(
+        run_str("(let x = 0.0 / 0.0; x < x or x >= x or x == x)"),
+        Ok(Value::Bool(false))
+    );
+    assert_eq!(
+        run_str("(let x = 1.5; x + 1)"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Float,
+            found: Type::Int,
+            span: Span::new(18, 19, 0, 18),
+        })
+    );
+    assert_eq!(
+        run_str("(let x = true; -x)"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(16, 17, 0, 16),
+        })
+    );
+}
+
+#[test]
+fn test_run_strings() {
+    let text = |s: &str| Ok(Value::Str(s.into()));
+    assert_eq!(run_str("\"a\" + \"b\" + \"c\""), text("abc"));
+    assert_eq!(run_str("len (\"ab\" + \"\\u{e9}\")"), Ok(Value::Int(3)));
+    assert_eq!(run_str("(let s = \"b\"; \"a\" < s)"), Ok(Value::Bool(true)));
+    assert_eq!(
+        run_str("(let n = 3; \"{n} is {if n > 2 (\"big\") else \"small\"}\")"),
+        text("3 is big")
+    );
+    assert_eq!(
+        run_str("(let s = \"a\"; s + 1)"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Str,
+            found: Type::Int,
+            span: Span::new(18, 19, 0, 18),
+        })
+    );
+    assert_eq!(
+        run_str("(let b = true; b < \"a\")"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(15, 16, 0, 15),
+        })
+    );
+}
+
+#[test]
+fn test_run_functions() {
+    assert_eq!(
+        run_str(
+            "(
+            let adder = fn (x: int) -> fn(int) -> int (fn (y: int) -> int (x + y));
+            let add2 = adder(2);
+            let x = 10;
+            add2(3) + x
+        )"
+        ),
+        Ok(Value::Int(15))
+    );
+    // Captures of captures.
+    assert_eq!(
+        run_str(
+            "(
+            let a = 1;
+            let f = fn () -> fn() -> fn() -> int (fn () -> fn() -> int (fn () -> int (a + 1)));
+            f()()()
+        )"
+        ),
+        Ok(Value::Int(2))
+    );
+    assert_eq!(
+        run_str("fn fib(n: int) -> int (if n < 2 (n) else (fib(n - 1) + fib(n - 2)))(20)"),
+        Ok(Value::Int(6765))
+    );
+    // A closure made inside a recursive function can call it.
+    assert_eq!(
+        run_str(
+            "fn count(n: int) -> int (
+                let again = fn () -> int (count(n - 1));
+                if n == 0 (0) else (1 + again())
+            )(5)"
+        ),
+        Ok(Value::Int(5))
+    );
+    assert_eq!(
+        run_str("fn named() -> int (1)").unwrap().to_string(),
+        "<fn named>"
+    );
+}
+
+#[test]
+fn test_run_errors() {
+    assert_eq!(
+        run_str("(let x = 1; 2 + x / (x - 1))"),
+        Err(RuntimeError::DivisionByZero {
+            span: Span::new(16, 27, 0, 16)
+        })
+    );
+    assert_eq!(
+        run_str("(let x = true; 1 + x)"),
+        Err(RuntimeError::TypeMismatch {
+            expected: Type::Int,
+            found: Type::Bool,
+            span: Span::new(19, 20, 0, 19),
+        })
+    );
+    assert_eq!(
+        run_str("(let f = fn (x: int) -> int (x); f(1, 2))"),
+        Err(RuntimeError::ArgumentCount {
+            expected: 1,
+            found: 2,
+            span: Span::new(33, 40, 0, 33),
+        })
+    );
+    assert_eq!(
+        run_str("fn f(n: int) -> int (f(n))(1)"),
+        Err(RuntimeError::CallDepth {
+            span: Span::new(20, 26, 0, 20),
+        })
+    );
+    // The deepest recursion allowed, in calls of a closure with captures.
+    let count =
+        "(let one = 1; fn count(n: int) -> int (if n <= one (one) else (one + count(n - 1))))";
+    assert_eq!(
+        run_str(&format!("{count}({MAX_FRAMES})")),
+        Ok(Value::Int(MAX_FRAMES as i64))
+    );
+    assert_eq!(
+        run_str("y"),
+        Err(RuntimeError::UndefinedVariable {
+            name: "y".to_owned(),
+            span: Span::new(0, 1, 0, 0),
+        })
+    );
+}
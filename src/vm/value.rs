@@ -0,0 +1,69 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::compile::Prototype;
+use crate::ttype::Type;
+
+/// Runtime value on the vm stack.
+/// These display the same as the evaluator's values.
+#[derive(Clone, Debug)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(Rc<str>),
+    Unit,
+    Fn(Rc<Closure>),
+}
+
+/// Compiled function together with the values it captured when it was made.
+#[derive(Debug)]
+pub struct Closure {
+    pub prototype: Rc<Prototype>,
+    pub captures: Vec<Value>,
+}
+
+impl Value {
+    pub fn ttype(&self) -> Type {
+        match self {
+            Value::Bool(_) => Type::Bool,
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Str(_) => Type::Str,
+            Value::Unit => Type::Unit,
+            Value::Fn(closure) => closure.prototype.ttype(),
+        }
+    }
+}
+
+/// Closures are equal only if they are the same closure, and floats follow
+/// IEEE 754, so NaN is not equal to itself.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Bool(left), Value::Bool(right)) => left == right,
+            (Value::Int(left), Value::Int(right)) => left == right,
+            (Value::Float(left), Value::Float(right)) => left == right,
+            (Value::Str(left), Value::Str(right)) => left == right,
+            (Value::Unit, Value::Unit) => true,
+            (Value::Fn(left), Value::Fn(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Int(i) => write!(f, "{i}"),
+            Value::Float(x) => write!(f, "{x:?}"),
+            Value::Str(s) => f.write_str(s),
+            Value::Unit => write!(f, "()"),
+            Value::Fn(closure) => match &closure.prototype.name {
+                Some(name) => write!(f, "<fn {name}>"),
+                None => write!(f, "<fn>"),
+            },
+        }
+    }
+}
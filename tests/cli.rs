@@ -0,0 +1,168 @@
+//! Tests of the carbide command line tool, run as a separate process.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+
+use serde_json::json;
+use serde_json::Value;
+
+/// Run carbide with args, and stdin as its standard input.
+fn carbide(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_carbide"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+fn json(output: &Output) -> Value {
+    serde_json::from_str(stdout(output)).unwrap()
+}
+
+/// Write source to a file named name, unique to the test.
+fn script(name: &str, source: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, source).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn test_run() {
+    let output = carbide(&["run"], "(let x = 2; x * 3)");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "6\n");
+
+    let output = carbide(&["run"], "1 / 0");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).contains("division by zero"), "{output:?}");
+
+    let path = script("run.cb", "\"a{1 + 1}\"");
+    let output = carbide(&["run", &path], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "a2\n");
+
+    let sum = "fn sum(n: int) -> int (if n == 0 (0) else (n + sum(n - 1)))(5000)";
+    let output = carbide(&["run"], sum);
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    assert_eq!(stdout(&output), "12502500\n");
+}
+
+#[test]
+fn test_run_json() {
+    let output = carbide(&["run", "--format", "json"], "1 + 2");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(json(&output), json!({"value": "3", "diagnostics": []}));
+
+    let output = carbide(&["run", "--format", "json"], "1 / 0");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        json(&output),
+        json!({
+            "value": null,
+            "diagnostics": [{
+                "file": "<stdin>",
+                "severity": "error",
+                "message": "division by zero",
+                "line": 1,
+                "column": 1,
+                "start": 0,
+                "end": 5,
+                "label": "divisor is zero",
+                "notes": [],
+                "help": null,
+            }],
+        })
+    );
+}
+
+#[test]
+fn test_check() {
+    let good = script("check_good.cb", "1 + 2");
+    let bad = script("check_bad.cb", "1 +\nx");
+    let output = carbide(&["check", &good], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "");
+
+    let output = carbide(&["check", "--format", "json", &good, &bad], "");
+    assert_eq!(output.status.code(), Some(1));
+    let diagnostics = &json(&output)["diagnostics"];
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1, "{diagnostics}");
+    assert_eq!(diagnostics[0]["file"], json!(bad));
+    assert_eq!(diagnostics[0]["message"], "undefined variable 'x'");
+    assert_eq!(diagnostics[0]["line"], 2);
+
+    let output = carbide(&["check"], "true + 1");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("<stdin>"), "{output:?}");
+}
+
+#[test]
+fn test_tokens() {
+    let output = carbide(&["tokens"], "let x // x");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "1:1 Let \"let\"\n1:5 Identifier \"x\"\n");
+
+    let output = carbide(&["tokens", "--format", "json"], "let\n x");
+    assert_eq!(
+        json(&output),
+        json!({"tokens": [
+            {"type": "Let", "lexeme": "let", "line": 1, "column": 1, "start": 0, "end": 3},
+            {"type": "Identifier", "lexeme": "x", "line": 2, "column": 2, "start": 5, "end": 6},
+        ]})
+    );
+}
+
+#[test]
+fn test_ast() {
+    let output = carbide(&["ast"], "-x");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("Neg"), "{output:?}");
+
+    let output = carbide(&["ast", "--format", "json"], "1 +");
+    assert_eq!(output.status.code(), Some(1));
+    let value = json(&output);
+    assert!(value["ast"].as_str().unwrap().contains("Error"), "{value}");
+    assert_eq!(value["diagnostics"].as_array().unwrap().len(), 1, "{value}");
+}
+
+#[test]
+fn test_usage_errors() {
+    for args in [
+        &[][..],
+        &["frob"],
+        &["run", "a", "b"],
+        &["run", "--format", "yaml"],
+        &["check", "--format"],
+        &["tokens", "--verbose"],
+    ] {
+        let output = carbide(args, "");
+        assert_eq!(output.status.code(), Some(2), "for {args:?}");
+        assert!(stderr(&output).contains("usage:"), "for {args:?}");
+    }
+
+    let output = carbide(&["run", "no/such/file.cb"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("cannot read no/such/file.cb"));
+}